@@ -0,0 +1,106 @@
+//! Compares the palette-compressed chunk storage with the previous
+//! `HashMap<(i32, i32, i32), Block>` layout.
+//!
+//! Run with `cargo bench --bench chunk_storage`.
+
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{Chunk, CHUNK_SIZE};
+use std::collections::HashMap;
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const CHUNK_RADIUS: i32 = 4;
+const CHUNK_LAYERS: i32 = 4;
+const ITERATIONS: u32 = 20;
+
+type ChunkMap = HashMap<(i32, i32, i32), Block>;
+
+fn main() {
+    let chunks: Vec<Chunk> = (-CHUNK_RADIUS..CHUNK_RADIUS)
+        .flat_map(|x| (-CHUNK_RADIUS..CHUNK_RADIUS).map(move |y| (x, y)))
+        .flat_map(|(x, y)| (0..CHUNK_LAYERS).map(move |z| (x, y, z)))
+        .map(|(x, y, z)| Chunk::generate(0, x, y, z))
+        .collect();
+    let maps: Vec<ChunkMap> = chunks
+        .iter()
+        .map(|chunk| chunk.blocks().collect())
+        .collect();
+
+    let block_count: usize = maps.iter().map(|map| map.len()).sum();
+    println!("{} chunks, {} blocks", chunks.len(), block_count);
+
+    let palette_bytes: usize = chunks
+        .iter()
+        .map(|chunk| size_of::<Chunk>() + chunk.heap_size())
+        .sum();
+    let map_bytes: usize = maps.iter().map(map_size).sum();
+    println!(
+        "memory:    palette {:>10} B    map {:>10} B",
+        palette_bytes, map_bytes
+    );
+
+    let palette_time = measure(|| {
+        chunks
+            .iter()
+            .flat_map(|chunk| chunk.blocks())
+            .map(|(_, block)| block.get_id() as i64)
+            .sum::<i64>()
+    });
+    let map_time = measure(|| {
+        maps.iter()
+            .flat_map(|map| map.values())
+            .map(|block| block.get_id() as i64)
+            .sum::<i64>()
+    });
+    println!(
+        "iteration: palette {:>10?}    map {:>10?}",
+        palette_time, map_time
+    );
+
+    let palette_time = measure(|| {
+        chunks
+            .iter()
+            .map(|chunk| count_transparent(|x, y, z| !chunk.is_opaque(x, y, z)))
+            .sum::<usize>()
+    });
+    let map_time = measure(|| {
+        maps.iter()
+            .map(|map| {
+                count_transparent(|x, y, z| map.get(&(x, y, z)).is_none_or(|b| b.is_transparent()))
+            })
+            .sum::<usize>()
+    });
+    println!(
+        "lookup:    palette {:>10?}    map {:>10?}",
+        palette_time, map_time
+    );
+}
+
+fn measure<T>(f: impl Fn() -> T) -> Duration {
+    black_box(f());
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    start.elapsed() / ITERATIONS
+}
+
+fn count_transparent(is_transparent: impl Fn(i32, i32, i32) -> bool) -> usize {
+    let mut count = 0;
+    for z in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for x in 0..CHUNK_SIZE {
+                if is_transparent(x, y, z) {
+                    count += 1;
+                }
+            }
+        }
+    }
+    count
+}
+
+// Each hashbrown bucket stores the key/value pair plus one control byte
+fn map_size(map: &ChunkMap) -> usize {
+    size_of::<ChunkMap>() + map.capacity() * (size_of::<((i32, i32, i32), Block)>() + 1)
+}
@@ -0,0 +1,183 @@
+//! Compares the greedy chunk mesher with the previous instanced cubes, where
+//! every visible block was an instance of a cube mesh shared by the blocks
+//! with the same material and visible faces, and reports what the meshes of
+//! distant chunks cost at each level of detail.
+//!
+//! Run with `cargo bench --bench meshing`.
+
+use mycraft::world::block::Face;
+use mycraft::world::chunk::{Chunk, ChunkProvider, MAX_CHUNK_Z, MIN_CHUNK_Z};
+use mycraft::world::generator::GeneratorPreset;
+use mycraft::world::World;
+use std::collections::{HashMap, HashSet};
+use std::hint::black_box;
+use std::time::{Duration, Instant};
+
+const CHUNK_RADIUS: i32 = 3;
+const ITERATIONS: u32 = 5;
+
+// A model matrix, a normal matrix and two words of face light per instance
+const INSTANCE_BYTES: usize = size_of::<[f32; 25]>() + size_of::<[u32; 2]>();
+// Position, uv, normal, tangent and bitangent, then the face index
+const CUBE_VERTEX_BYTES: usize = size_of::<[f32; 14]>() + size_of::<u32>();
+// One packed word per vertex, and the chunk origin per mesh
+const QUAD_VERTEX_BYTES: usize = size_of::<u32>();
+const ORIGIN_BYTES: usize = size_of::<[f32; 3]>();
+
+#[derive(Default)]
+struct Counts {
+    draw_calls: usize,
+    // Vertices stored in the meshes
+    vertices: usize,
+    // Vertices run through the vertex shader per frame
+    shaded_vertices: usize,
+    bytes: usize,
+}
+
+fn main() {
+    // Keep the columns around the measured ones loaded, so that the faces
+    // along the edges are culled and lit as in game
+    let mut world = World::new(ChunkProvider::new(0, GeneratorPreset::Default));
+    for x in -CHUNK_RADIUS - 1..=CHUNK_RADIUS {
+        for y in -CHUNK_RADIUS - 1..=CHUNK_RADIUS {
+            for z in (MIN_CHUNK_Z..=MAX_CHUNK_Z).rev() {
+                world.load_chunk(x, y, z);
+            }
+        }
+    }
+    let keys: Vec<_> = (-CHUNK_RADIUS..CHUNK_RADIUS)
+        .flat_map(|x| (-CHUNK_RADIUS..CHUNK_RADIUS).map(move |y| (x, y)))
+        .flat_map(|(x, y)| (MIN_CHUNK_Z..=MAX_CHUNK_Z).map(move |z| (x, y, z)))
+        .collect();
+    let chunks: Vec<&Chunk> = keys
+        .iter()
+        .map(|&(x, y, z)| world.get_chunk(x, y, z).unwrap())
+        .collect();
+    println!("{} chunks", chunks.len());
+
+    let instanced = chunks
+        .iter()
+        .map(|chunk| count_instanced(&world, chunk))
+        .fold(Counts::default(), add);
+    let greedy = chunks
+        .iter()
+        .map(|chunk| count_greedy(&world, chunk))
+        .fold(Counts::default(), add);
+    println!(
+        "draw calls:      instanced {:>10}    greedy {:>10}",
+        instanced.draw_calls, greedy.draw_calls
+    );
+    println!(
+        "vertices:        instanced {:>10}    greedy {:>10}",
+        instanced.vertices, greedy.vertices
+    );
+    println!(
+        "shaded vertices: instanced {:>10}    greedy {:>10}",
+        instanced.shaded_vertices, greedy.shaded_vertices
+    );
+    println!(
+        "buffer bytes:    instanced {:>10}    greedy {:>10}",
+        instanced.bytes, greedy.bytes
+    );
+
+    // The instanced meshes are only counted, not built, so there is no time
+    // to compare against
+    let greedy_time = measure(|| {
+        chunks
+            .iter()
+            .map(|chunk| world.greedy_quads(chunk).len())
+            .sum::<usize>()
+    });
+    println!("meshing:         {:>24}greedy {:>10?}", "", greedy_time);
+
+    // The coarser meshes of distant chunks
+    for scale in [2, 4, 8] {
+        let quads = chunks
+            .iter()
+            .map(|chunk| world.lod_quads(chunk, scale).len())
+            .sum::<usize>();
+        let time = measure(|| {
+            chunks
+                .iter()
+                .map(|chunk| world.lod_quads(chunk, scale).len())
+                .sum::<usize>()
+        });
+        println!(
+            "lod {scale}x:          vertices {:>10}    meshing {:>10?}",
+            4 * quads,
+            time
+        );
+    }
+}
+
+// One cube mesh per material and set of visible faces, drawn once for every
+// block that uses it
+fn count_instanced(world: &World, chunk: &Chunk) -> Counts {
+    let mut variations: HashMap<(usize, [bool; 6]), usize> = HashMap::new();
+    for ((x, y, z), block) in chunk.blocks() {
+        let visible_faces = world.visible_faces(chunk, x, y, z);
+        let mut materials = Face::ALL.map(|face| block.get_material_id(face));
+        materials.sort_unstable();
+        for (index, &material) in materials.iter().enumerate() {
+            if index > 0 && materials[index - 1] == material {
+                continue;
+            }
+            let faces = Face::ALL.map(|face| {
+                visible_faces[face as usize] && block.get_material_id(face) == material
+            });
+            if faces.contains(&true) {
+                *variations.entry((material, faces)).or_default() += 1;
+            }
+        }
+    }
+
+    let mut counts = Counts::default();
+    for ((_, faces), instances) in variations {
+        // Six unindexed vertices per face
+        let vertices = 6 * faces.iter().filter(|visible| **visible).count();
+        counts.draw_calls += 1;
+        counts.vertices += vertices;
+        counts.shaded_vertices += vertices * instances;
+        counts.bytes +=
+            vertices * (CUBE_VERTEX_BYTES + size_of::<u32>()) + instances * INSTANCE_BYTES;
+    }
+    counts
+}
+
+// One mesh of four corners and six indices per quad for each material
+fn count_greedy(world: &World, chunk: &Chunk) -> Counts {
+    let quads = world.greedy_quads(chunk);
+    let draw_calls = quads
+        .iter()
+        .map(|quad| quad.material)
+        .collect::<HashSet<_>>()
+        .len();
+    let vertices = 4 * quads.len();
+    Counts {
+        draw_calls,
+        vertices,
+        shaded_vertices: vertices,
+        bytes: vertices * QUAD_VERTEX_BYTES
+            + 6 * quads.len() * size_of::<u32>()
+            + draw_calls * ORIGIN_BYTES,
+    }
+}
+
+fn add(total: Counts, counts: Counts) -> Counts {
+    Counts {
+        draw_calls: total.draw_calls + counts.draw_calls,
+        vertices: total.vertices + counts.vertices,
+        shaded_vertices: total.shaded_vertices + counts.shaded_vertices,
+        bytes: total.bytes + counts.bytes,
+    }
+}
+
+fn measure<T>(f: impl Fn() -> T) -> Duration {
+    black_box(f());
+
+    let start = Instant::now();
+    for _ in 0..ITERATIONS {
+        black_box(f());
+    }
+    start.elapsed() / ITERATIONS
+}
@@ -0,0 +1,93 @@
+//! Renders the terrain noise to grayscale images, comparing the single
+//! octave noise the terrain used to be made of with the fractal noise.
+//!
+//! ```sh
+//! cargo run --release --example noise_samples -- docs/noise
+//! ```
+
+use image::{GrayImage, Luma};
+use mycraft::world::generator::{sub_seed, FractalNoise, TerrainStage};
+use noise::{NoiseFn, Simplex};
+use std::path::Path;
+
+const SEED: u32 = 0;
+const SIZE: u32 = 256;
+// Blocks covered by a pixel
+const BLOCKS_PER_PIXEL: f64 = 4.0;
+
+fn render(path: &Path, sample: impl Fn(f64, f64) -> f64) -> anyhow::Result<()> {
+    let image = GrayImage::from_fn(SIZE, SIZE, |x, y| {
+        let value = sample(x as f64 * BLOCKS_PER_PIXEL, y as f64 * BLOCKS_PER_PIXEL);
+        Luma([(value.clamp(0.0, 1.0) * 255.0) as u8])
+    });
+    image.save(path)?;
+    println!("Wrote {}", path.display());
+    Ok(())
+}
+
+fn main() -> anyhow::Result<()> {
+    let directory = std::env::args()
+        .nth(1)
+        .unwrap_or_else(|| "docs/noise".to_string());
+    let directory = Path::new(&directory);
+    std::fs::create_dir_all(directory)?;
+
+    let noise = Simplex::new(SEED);
+    let single_octave =
+        |increment: f64| move |x: f64, y: f64| noise.get([x * increment, y * increment, 0.0]).abs();
+    let terrain = TerrainStage::default();
+    // The stages sample each layer from its own noise channel
+    let fractal = |channel: &str, settings: FractalNoise| {
+        let noise = Simplex::new(sub_seed(SEED, channel));
+        move |x, y| settings.sample(&noise, x, y)
+    };
+
+    render(
+        &directory.join("height_single_octave.png"),
+        single_octave(0.01),
+    )?;
+    render(
+        &directory.join("height_fractal.png"),
+        fractal(
+            "height",
+            FractalNoise {
+                warp_strength: 0.0,
+                ..terrain.height_noise.clone()
+            },
+        ),
+    )?;
+    render(
+        &directory.join("height_fractal_warped.png"),
+        fractal("height", terrain.height_noise.clone()),
+    )?;
+    render(
+        &directory.join("height_ridged.png"),
+        fractal(
+            "height",
+            FractalNoise {
+                ridged: true,
+                contrast: 1.0,
+                ..terrain.height_noise.clone()
+            },
+        ),
+    )?;
+
+    render(
+        &directory.join("temperature_single_octave.png"),
+        single_octave(0.013),
+    )?;
+    render(
+        &directory.join("temperature_fractal.png"),
+        fractal("temperature", terrain.temperature_noise.clone()),
+    )?;
+    render(
+        &directory.join("humidity_single_octave.png"),
+        single_octave(0.0025),
+    )?;
+    render(
+        &directory.join("humidity_fractal.png"),
+        fractal("humidity", terrain.humidity_noise.clone()),
+    )?;
+
+    Ok(())
+}
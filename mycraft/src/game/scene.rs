@@ -1,22 +1,78 @@
-use super::block_renderer::create_block_mesh;
+use super::block_renderer::create_chunk_meshes;
 use super::camera;
 use super::controller::CameraController;
 use crate::graphics::{model, state};
-use crate::world::chunk;
-use cgmath::Rotation3;
-use std::collections::HashMap;
+use crate::world::cache::CacheBudget;
+use crate::world::generator::GeneratorPreset;
+use crate::world::region::{RegionStore, SaveMode};
+use crate::world::visibility::{self, FaceConnections};
+use crate::world::{chunk, World};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::time::Duration;
 use winit::event::{DeviceEvent, ElementState, Event, KeyEvent, WindowEvent};
 use winit::event_loop::EventLoop;
 use winit::keyboard::{KeyCode, PhysicalKey};
 use winit::window::WindowBuilder;
 
-const CHUNK_RADIUS: i32 = 10;
+// Levels of detail only thin out the meshes. Every section in range is still
+// generated, lit and cached at full detail: about 62k sections, taking some
+// 60 MB as most of them are empty sky, and 90 s of generation on one core in
+// release builds. Each chunk moved brings in a new edge of about 1.2k sections.
+const CHUNK_RADIUS: i32 = 32;
+// Columns nearer than each distance, in chunks, are meshed with cubes of the
+// matching scale, and the columns past them with `FARTHEST_LOD_SCALE`
+const LOD_LEVELS: [(i32, i32); 3] = [(8, 1), (16, 2), (24, 4)];
+const FARTHEST_LOD_SCALE: i32 = 8;
+// Chunks a column has to move past a level's distance before it is meshed at
+// another scale, so that columns near the distance don't keep switching
+const LOD_HYSTERESIS: f32 = 1.0;
+const WINDOW_TITLE: &str = "mycraft";
+const SAVE_DIRECTORY: &str = "saves/world";
+// Generator preset and settings of the saved world, if any
+const WORLD_CONFIG: &str = "saves/world/world.toml";
+// Caps the meshing work done per frame while generated chunks stream in
+const MESHES_PER_FRAME: usize = 64;
+// The world simulates fluids at a fixed rate, whatever the frame rate
+const TICK_LENGTH: Duration = Duration::from_millis(50);
+// Ticks caught up in one frame at most, after a stall
+const MAX_TICKS_PER_FRAME: u32 = 4;
+
+// Chunks whose faces depend on the chunk at the origin
+const NEIGHBOUR_OFFSETS: [(i32, i32, i32); 7] = [
+    (0, 0, 0),
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
 
 pub struct Scene {
     camera: camera::Camera,
     camera_controller: CameraController,
-    chunk_provider: chunk::ChunkProvider,
+    world: World,
     last_render: (i32, i32),
+    // Chunks in range whose meshes are on the GPU, including empty ones
+    meshed_chunks: HashMap<model::MeshKey, MeshedChunk>,
+    // Chunks in range waiting for themselves or a neighbour to generate
+    unmeshed_chunks: HashSet<model::MeshKey>,
+    ready_chunks: VecDeque<model::MeshKey>,
+    // Camera section the visible chunks were last found from
+    visible_from: Option<model::MeshKey>,
+    // Set when the connections between chunks or the range changed since then
+    visibility_changed: bool,
+    // Time not yet simulated by world ticks
+    unticked_time: Duration,
+    // Debug counters in the window title, toggled with F3
+    show_debug: bool,
+}
+
+struct MeshedChunk {
+    // Faces that see each other through the chunk
+    connections: FaceConnections,
+    // Blocks per cube of the mesh, see `World::lod_quads`
+    scale: i32,
 }
 
 impl Scene {
@@ -24,21 +80,43 @@ impl Scene {
         let camera = camera::Camera::new((0.0, 5.0, 0.0), cgmath::Deg(-90.0), cgmath::Deg(-20.0));
         let camera_controller = CameraController::new(4.0, 0.4);
 
+        let mut chunk_provider = chunk::ChunkProvider::with_region_store(
+            0,
+            load_preset(),
+            RegionStore::with_mode(SAVE_DIRECTORY, SaveMode::Deltas),
+        );
+        // Every section of the loaded columns, with room for the columns left
+        // behind while moving
+        let loaded_width = 2 * (CHUNK_RADIUS + 2) as usize;
+        let sections = (chunk::MAX_CHUNK_Z - chunk::MIN_CHUNK_Z + 1) as usize;
+        chunk_provider
+            .set_cache_budget(CacheBudget::Chunks(loaded_width * loaded_width * sections));
+
         Self {
             camera,
             camera_controller,
-            chunk_provider: chunk::ChunkProvider::default(),
+            world: World::new(chunk_provider),
             last_render: (0, 0),
+            meshed_chunks: HashMap::new(),
+            unmeshed_chunks: HashSet::new(),
+            ready_chunks: VecDeque::new(),
+            visible_from: None,
+            visibility_changed: true,
+            unticked_time: Duration::ZERO,
+            show_debug: false,
         }
     }
 
     pub async fn run(self: &mut Self) {
         let event_loop = EventLoop::new().unwrap();
-        let window = WindowBuilder::new().build(&event_loop).unwrap();
+        let window = WindowBuilder::new()
+            .with_title(WINDOW_TITLE)
+            .build(&event_loop)
+            .unwrap();
 
         // State::new uses async code, so we're going to wait for it to finish
-        let meshes = self.render_chunks((0, 0));
-        let mut state = state::State::new(&window, &self.camera, &meshes).await;
+        self.render_chunks((0, 0));
+        let mut state = state::State::new(&window, &self.camera).await;
         let mut last_render_time = instant::Instant::now();
 
         event_loop
@@ -51,7 +129,7 @@ impl Scene {
                     Event::WindowEvent {
                         ref event,
                         window_id,
-                    } if window_id == window.id() && !self.input(event) => {
+                    } if window_id == window.id() && !self.input(event, &window) => {
                         match event {
                             WindowEvent::CloseRequested
                             | WindowEvent::KeyboardInput {
@@ -62,7 +140,10 @@ impl Scene {
                                         ..
                                     },
                                 ..
-                            } => control_flow.exit(),
+                            } => {
+                                self.world.save();
+                                control_flow.exit();
+                            }
                             WindowEvent::Resized(physical_size) => {
                                 state.resize(state::Size {
                                     width: physical_size.width,
@@ -77,6 +158,7 @@ impl Scene {
                                 let dt = now - last_render_time;
                                 last_render_time = now;
                                 self.camera_controller.update_camera(&mut self.camera, dt);
+                                self.tick_world(dt);
                                 let chunk_x = (self.camera.position.x / 16.0) as i32;
                                 let chunk_y = (self.camera.position.z / 16.0) as i32;
                                 if !is_point_within_circle(
@@ -85,10 +167,32 @@ impl Scene {
                                     2,
                                 ) {
                                     self.last_render = (chunk_x, chunk_y);
-                                    state.set_meshes(&self.render_chunks(self.last_render));
+                                    for key in self.drop_chunks_out_of_range() {
+                                        state.remove_key_meshes(key);
+                                    }
+                                    self.render_chunks(self.last_render);
+                                }
+                                for key in self.world.receive_chunks() {
+                                    self.queue_ready_chunks(key);
+                                }
+                                for (key, meshes) in self.render_ready_chunks() {
+                                    state.set_key_meshes(key, &meshes);
+                                }
+                                for (key, meshes) in self.render_dirty_chunks() {
+                                    state.set_key_meshes(key, &meshes);
                                 }
                                 state.update(&self.camera);
+                                if self.is_visibility_outdated() {
+                                    state.set_visible_chunks(self.visible_chunks());
+                                }
                                 match state.render() {
+                                    Ok(_) if self.show_debug => {
+                                        let metrics = state.cull_metrics();
+                                        window.set_title(&format!(
+                                            "{WINDOW_TITLE} - chunks drawn {}, culled {}, occluded {}",
+                                            metrics.drawn, metrics.culled, metrics.occluded
+                                        ));
+                                    }
                                     Ok(_) => {}
                                     // Reconfigure the surface if it's lost or outdated
                                     Err(
@@ -117,8 +221,22 @@ impl Scene {
             .unwrap();
     }
 
-    fn input(&mut self, event: &WindowEvent) -> bool {
+    fn input(&mut self, event: &WindowEvent, window: &winit::window::Window) -> bool {
         match event {
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        physical_key: PhysicalKey::Code(KeyCode::F3),
+                        state: ElementState::Pressed,
+                        repeat: false,
+                        ..
+                    },
+                ..
+            } => {
+                self.show_debug = !self.show_debug;
+                window.set_title(WINDOW_TITLE);
+                true
+            }
             WindowEvent::KeyboardInput {
                 event:
                     KeyEvent {
@@ -132,75 +250,243 @@ impl Scene {
         }
     }
 
-    fn render_chunks(self: &mut Self, position: (i32, i32)) -> Vec<model::Mesh> {
-        let mut block_variations: HashMap<(i32, u8), ([bool; 6], Vec<model::Instance>)> =
-            HashMap::new();
+    /// Queues the chunks that came into range around `position` for meshing,
+    /// nearest first, and requests the ones that aren't loaded yet. Chunks
+    /// already meshed keep their meshes, unless they moved far enough to be
+    /// meshed at another scale.
+    fn render_chunks(self: &mut Self, position: (i32, i32)) {
+        let z_chunk = (self.camera.position.y / chunk::CHUNK_SIZE as f32).floor() as i32;
+        self.world.set_focus(position.0, position.1, z_chunk);
+
+        // Load one extra ring so the outermost chunks can cull against their neighbours
+        self.world.cancel_requests(|(x_chunk, y_chunk, _)| {
+            is_point_within_circle(&position, &(*x_chunk, *y_chunk), CHUNK_RADIUS + 1)
+        });
+        for (x_chunk, y_chunk) in chunks_within_circle(position, CHUNK_RADIUS + 1) {
+            for z_chunk in chunk::MIN_CHUNK_Z..=chunk::MAX_CHUNK_Z {
+                self.world.request_chunk(x_chunk, y_chunk, z_chunk);
+            }
+        }
+
+        let mut queued: HashSet<_> = self.ready_chunks.iter().copied().collect();
+        for (key, meshed) in &self.meshed_chunks {
+            let scale = lod_scale(column_distance(position, *key), Some(meshed.scale));
+            if scale != meshed.scale && queued.insert(*key) {
+                self.ready_chunks.push_back(*key);
+            }
+        }
 
-        for x_chunk in -CHUNK_RADIUS..=CHUNK_RADIUS {
-            for y_chunk in -CHUNK_RADIUS..=CHUNK_RADIUS {
-                if !is_point_within_circle(&(0, 0), &(x_chunk, y_chunk), CHUNK_RADIUS) {
+        // Chunks still generating are queued as they arrive
+        for (x_chunk, y_chunk) in chunks_within_circle(position, CHUNK_RADIUS) {
+            for z_chunk in chunk::MIN_CHUNK_Z..=chunk::MAX_CHUNK_Z {
+                let key = (x_chunk, y_chunk, z_chunk);
+                if self.meshed_chunks.contains_key(&key)
+                    || self.unmeshed_chunks.contains(&key)
+                    || queued.contains(&key)
+                {
                     continue;
                 }
-
-                let chunk = self
-                    .chunk_provider
-                    .get_chunk(position.0 + x_chunk, position.1 + y_chunk);
-                for block_entry in chunk.chunk_map.iter() {
-                    let ((x, y, z), block) = block_entry;
-                    let render_faces = [
-                        chunk.is_opaque(*x, y + 1, *z),
-                        chunk.is_opaque(*x, y - 1, *z),
-                        chunk.is_opaque(*x, *y, z + 1),
-                        chunk.is_opaque(*x, *y, z - 1),
-                        chunk.is_opaque(x + 1, *y, *z),
-                        chunk.is_opaque(x - 1, *y, *z),
-                    ];
-                    if render_faces.contains(&true) {
-                        let render_time = instant::Instant::now();
-                        let block_index = bool_array_to_int(render_faces);
-                        // println!("Block index calculated in {:?}", render_time.elapsed());
-                        let material_id = block.get_material_id();
-                        let (_, instances) = block_variations
-                            .entry((material_id, block_index))
-                            .or_insert_with(|| (render_faces, Vec::new()));
-                        // Create instance data for visible block
-                        let position = cgmath::Vector3 {
-                            x: (16 * chunk.x + x) as f32,
-                            y: *z as f32,
-                            z: (16 * chunk.y + y) as f32,
-                        };
-
-                        let rotation = cgmath::Quaternion::from_axis_angle(
-                            cgmath::Vector3::unit_z(),
-                            cgmath::Deg(0.0),
-                        );
-
-                        instances.push(model::Instance { position, rotation });
-                    }
+                if self.is_ready(key) {
+                    self.ready_chunks.push_back(key);
+                } else {
+                    self.unmeshed_chunks.insert(key);
                 }
             }
         }
+        self.ready_chunks.make_contiguous().sort_by(|a, b| {
+            column_distance(position, *a).total_cmp(&column_distance(position, *b))
+        });
+
+        log::debug!("Chunk cache: {:?}", self.world.cache_metrics());
+    }
+
+    /// Forgets the chunks that are out of range of the last render position,
+    /// and returns the meshed ones so that their meshes can be freed.
+    fn drop_chunks_out_of_range(&mut self) -> Vec<model::MeshKey> {
+        let position = self.last_render;
+        let in_range = |(x_chunk, y_chunk, _): &model::MeshKey| {
+            is_point_within_circle(&position, &(*x_chunk, *y_chunk), CHUNK_RADIUS)
+        };
+
+        self.unmeshed_chunks.retain(in_range);
+        self.ready_chunks.retain(in_range);
+        let dropped = self
+            .meshed_chunks
+            .keys()
+            .filter(|key| !in_range(key))
+            .copied()
+            .collect();
+        self.meshed_chunks.retain(|key, _| in_range(key));
+        self.visibility_changed = true;
+        dropped
+    }
 
-        let mut meshes: Vec<model::Mesh> = block_variations
+    /// Queues the waiting chunks that `key` completed for meshing.
+    fn queue_ready_chunks(&mut self, (x, y, z): model::MeshKey) {
+        for (dx, dy, dz) in NEIGHBOUR_OFFSETS {
+            let key = (x + dx, y + dy, z + dz);
+            if self.unmeshed_chunks.contains(&key) && self.is_ready(key) {
+                self.unmeshed_chunks.remove(&key);
+                self.ready_chunks.push_back(key);
+            }
+        }
+    }
+
+    /// Meshes up to `MESHES_PER_FRAME` of the queued chunks, at the scale
+    /// that fits their distance.
+    fn render_ready_chunks(&mut self) -> Vec<(model::MeshKey, Vec<model::Mesh>)> {
+        let count = self.ready_chunks.len().min(MESHES_PER_FRAME);
+        let position = self.last_render;
+
+        self.ready_chunks
+            .drain(..count)
+            .filter_map(|key| {
+                let chunk = self.world.get_chunk(key.0, key.1, key.2)?;
+                let current = self.meshed_chunks.get(&key);
+                let scale = lod_scale(
+                    column_distance(position, key),
+                    current.map(|meshed| meshed.scale),
+                );
+                let connections = chunk.face_connections();
+                // Chunks not meshed yet were seen through
+                self.visibility_changed |= connections
+                    != current.map_or(FaceConnections::ALL, |meshed| meshed.connections);
+                self.meshed_chunks
+                    .insert(key, MeshedChunk { connections, scale });
+                Some((key, mesh_chunk(&self.world, chunk, scale)))
+            })
+            .collect()
+    }
+
+    // A chunk can be meshed once it and every neighbour it culls against and
+    // takes light from are loaded and lit
+    fn is_ready(&self, (x, y, z): model::MeshKey) -> bool {
+        NEIGHBOUR_OFFSETS.iter().all(|(dx, dy, dz)| {
+            let z_neighbour = z + dz;
+            !(chunk::MIN_CHUNK_Z..=chunk::MAX_CHUNK_Z).contains(&z_neighbour)
+                || self
+                    .world
+                    .get_chunk(x + dx, y + dy, z_neighbour)
+                    .is_some_and(|chunk| chunk.is_lit())
+        })
+    }
+
+    /// Runs the world ticks that fall into the time since the last frame.
+    /// Their changes are meshed together, see `render_dirty_chunks`.
+    fn tick_world(&mut self, dt: Duration) {
+        self.unticked_time = (self.unticked_time + dt).min(TICK_LENGTH * MAX_TICKS_PER_FRAME);
+        while self.unticked_time >= TICK_LENGTH {
+            self.unticked_time -= TICK_LENGTH;
+            self.world.tick();
+        }
+    }
+
+    /// Meshes the meshed chunks that were edited since the last frame, and
+    /// updates the faces that see each other through them. Chunks still
+    /// waiting to be meshed pick up their edits when they are.
+    fn render_dirty_chunks(&mut self) -> Vec<(model::MeshKey, Vec<model::Mesh>)> {
+        self.world
+            .take_dirty_chunks()
             .into_iter()
-            .map(|((material_id, _), (faces, instances))| {
-                create_block_mesh(material_id as usize, faces, instances)
+            .filter_map(|key| {
+                let meshed = self.meshed_chunks.get_mut(&key)?;
+                let chunk = self.world.get_chunk(key.0, key.1, key.2)?;
+                let connections = chunk.face_connections();
+                self.visibility_changed |= connections != meshed.connections;
+                meshed.connections = connections;
+                Some((key, mesh_chunk(&self.world, chunk, meshed.scale)))
             })
-            .collect();
+            .collect()
+    }
+
+    /// Returns `true` when the visible chunks need to be found again, because
+    /// the camera moved into another section or the connections between the
+    /// chunks in range changed since they were last found.
+    fn is_visibility_outdated(&mut self) -> bool {
+        let section = self.camera_section();
+        if !self.visibility_changed && self.visible_from == Some(section) {
+            return false;
+        }
+        self.visible_from = Some(section);
+        self.visibility_changed = false;
+        true
+    }
+
+    fn camera_section(&self) -> model::MeshKey {
+        // Blocks are centered on their coordinates
+        let camera =
+            (self.camera.position + cgmath::Vector3::new(0.5, 0.5, 0.5)) / chunk::CHUNK_SIZE as f32;
+        (
+            camera.x.floor() as i32,
+            camera.z.floor() as i32,
+            camera.y.floor() as i32,
+        )
+    }
+
+    /// The chunks in range that can be seen from the camera's section through
+    /// the caves and openings between them, or `None` when the camera is
+    /// above or below the world. Chunks not meshed yet are seen through.
+    fn visible_chunks(&self) -> Option<HashSet<model::MeshKey>> {
+        let start = self.camera_section();
+        let z_range = chunk::MIN_CHUNK_Z..=chunk::MAX_CHUNK_Z;
+        if !z_range.contains(&start.2) {
+            return None;
+        }
+
+        let position = self.last_render;
+        Some(visibility::visible_sections(
+            start,
+            |(x_chunk, y_chunk, z_chunk)| {
+                z_range.contains(&z_chunk)
+                    && is_point_within_circle(&position, &(x_chunk, y_chunk), CHUNK_RADIUS)
+            },
+            |key| {
+                self.meshed_chunks
+                    .get(&key)
+                    .map_or(FaceConnections::ALL, |meshed| meshed.connections)
+            },
+        ))
+    }
+}
 
-        meshes
+fn mesh_chunk(world: &World, chunk: &chunk::Chunk, scale: i32) -> Vec<model::Mesh> {
+    if scale == 1 {
+        create_chunk_meshes(chunk, &world.greedy_quads(chunk))
+    } else {
+        create_chunk_meshes(chunk, &world.lod_quads(chunk, scale))
     }
 }
 
-fn bool_array_to_int(array: [bool; 6]) -> u8 {
-    let mut value: u8 = 0;
-    for i in 0..6 {
-        if array[i] {
-            value |= 1 << i;
+// Scale of the cubes to mesh a chunk `distance` chunks away with, given the
+// scale it is meshed at, if any
+fn lod_scale(distance: f32, current: Option<i32>) -> i32 {
+    let scale_at = |distance: f32| {
+        LOD_LEVELS
+            .iter()
+            .find(|(limit, _)| distance < *limit as f32)
+            .map_or(FARTHEST_LOD_SCALE, |(_, scale)| *scale)
+    };
+    match current {
+        Some(scale)
+            if scale_at(distance - LOD_HYSTERESIS) <= scale
+                && scale <= scale_at(distance + LOD_HYSTERESIS) =>
+        {
+            scale
         }
+        _ => scale_at(distance),
     }
+}
+
+// Distance in chunks from the column at `position` to the column of a chunk
+fn column_distance((x, y): (i32, i32), (x_chunk, y_chunk, _): model::MeshKey) -> f32 {
+    ((x_chunk - x) as f32).hypot((y_chunk - y) as f32)
+}
 
-    value
+fn chunks_within_circle((x, y): (i32, i32), radius: i32) -> impl Iterator<Item = (i32, i32)> {
+    (-radius..=radius)
+        .flat_map(move |dx| (-radius..=radius).map(move |dy| (dx, dy)))
+        .filter(move |offset| is_point_within_circle(&(0, 0), offset, radius))
+        .map(move |(dx, dy)| (x + dx, y + dy))
 }
 
 fn is_point_within_circle((x, y): &(i32, i32), (x1, y1): &(i32, i32), radius: i32) -> bool {
@@ -216,3 +502,14 @@ fn is_point_within_circle((x, y): &(i32, i32), (x1, y1): &(i32, i32), radius: i3
 
     distance_squared <= radius_squared
 }
+
+fn load_preset() -> GeneratorPreset {
+    if !std::path::Path::new(WORLD_CONFIG).exists() {
+        return GeneratorPreset::Default;
+    }
+
+    GeneratorPreset::load(WORLD_CONFIG).unwrap_or_else(|error| {
+        log::error!("Can't load world config, using the default world: {error:#}");
+        GeneratorPreset::Default
+    })
+}
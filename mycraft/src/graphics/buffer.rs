@@ -1,5 +1,4 @@
 use super::light::LightUniform;
-use super::model::Vertex;
 use super::perspective::CameraUniform;
 use super::raw_model;
 use wgpu::util::DeviceExt;
@@ -22,34 +21,14 @@ pub fn create_light_buffer(light_uniform: LightUniform, device: &wgpu::Device) -
 
 pub fn create_vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
     wgpu::VertexBufferLayout {
-        array_stride: size_of::<Vertex>() as wgpu::BufferAddress,
+        array_stride: size_of::<raw_model::VertexRaw>() as wgpu::BufferAddress,
         step_mode: wgpu::VertexStepMode::Vertex,
         attributes: &[
+            // Position, face and light packed into one word
             wgpu::VertexAttribute {
                 offset: 0,
                 shader_location: 0,
-                format: wgpu::VertexFormat::Float32x3,
-            },
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 3]>() as wgpu::BufferAddress,
-                shader_location: 1,
-                format: wgpu::VertexFormat::Float32x2,
-            },
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 5]>() as wgpu::BufferAddress,
-                shader_location: 2,
-                format: wgpu::VertexFormat::Float32x3,
-            },
-            // Tangent and bitangent
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                shader_location: 3,
-                format: wgpu::VertexFormat::Float32x3,
-            },
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 11]>() as wgpu::BufferAddress,
-                shader_location: 4,
-                format: wgpu::VertexFormat::Float32x3,
+                format: wgpu::VertexFormat::Uint32,
             },
         ],
     }
@@ -58,50 +37,12 @@ pub fn create_vertex_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
 pub fn create_instance_buffer_layout() -> wgpu::VertexBufferLayout<'static> {
     wgpu::VertexBufferLayout {
         array_stride: size_of::<raw_model::InstanceRaw>() as wgpu::BufferAddress,
-        // We need to switch from using a step mode of Vertex to Instance
-        // This means that our shaders will only change to use the next
-        // instance when the shader starts processing a new instance
+        // Each mesh is drawn as a single instance, placed at its chunk
         step_mode: wgpu::VertexStepMode::Instance,
-        attributes: &[
-            wgpu::VertexAttribute {
-                offset: 0,
-                // While our vertex shader only uses locations 0, and 1 now, in later tutorials we'll
-                // be using 2, 3, and 4, for Vertex. We'll start at slot 5 not conflict with them later
-                shader_location: 5,
-                format: wgpu::VertexFormat::Float32x4,
-            },
-            // A mat4 takes up 4 vertex slots as it is technically 4 vec4s. We need to define a slot
-            // for each vec4. We don't have to do this in code though.
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 4]>() as wgpu::BufferAddress,
-                shader_location: 6,
-                format: wgpu::VertexFormat::Float32x4,
-            },
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 8]>() as wgpu::BufferAddress,
-                shader_location: 7,
-                format: wgpu::VertexFormat::Float32x4,
-            },
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 12]>() as wgpu::BufferAddress,
-                shader_location: 8,
-                format: wgpu::VertexFormat::Float32x4,
-            },
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 16]>() as wgpu::BufferAddress,
-                shader_location: 9,
-                format: wgpu::VertexFormat::Float32x3,
-            },
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 19]>() as wgpu::BufferAddress,
-                shader_location: 10,
-                format: wgpu::VertexFormat::Float32x3,
-            },
-            wgpu::VertexAttribute {
-                offset: size_of::<[f32; 22]>() as wgpu::BufferAddress,
-                shader_location: 11,
-                format: wgpu::VertexFormat::Float32x3,
-            },
-        ],
+        attributes: &[wgpu::VertexAttribute {
+            offset: 0,
+            shader_location: 1,
+            format: wgpu::VertexFormat::Float32x3,
+        }],
     }
 }
@@ -1,8 +1,14 @@
-use super::{material, raw_model};
+use super::frustum::{Aabb, CullMetrics, Frustum};
+use super::{material, model, raw_model};
+use std::collections::{HashMap, HashSet};
 
 pub struct DrawableState {
-    pub meshes: Vec<raw_model::MeshRaw>,
+    pub meshes: HashMap<model::MeshKey, Vec<raw_model::MeshRaw>>,
+    /// The bounds of every mesh of a group together, updated with `meshes`.
+    pub bounds: HashMap<model::MeshKey, Aabb>,
     pub materials: Vec<material::Material>,
+    /// The mesh groups that can be seen, or `None` to draw all of them.
+    pub visible: Option<HashSet<model::MeshKey>>,
 }
 
 pub trait DrawModel<'a> {
@@ -25,16 +31,20 @@ pub trait DrawModel<'a> {
     fn draw_model(
         &mut self,
         model: &'a DrawableState,
+        frustum: &Frustum,
         camera_bind_group: &'a wgpu::BindGroup,
         light_bind_group: &'a wgpu::BindGroup,
-    );
+    ) -> CullMetrics;
 
+    /// Draws the visible mesh groups whose bounds are in the frustum, and
+    /// counts the groups drawn and skipped.
     fn draw_model_instanced(
         &mut self,
         model: &'a DrawableState,
+        frustum: &Frustum,
         camera_bind_group: &'a wgpu::BindGroup,
         light_bind_group: &'a wgpu::BindGroup,
-    );
+    ) -> CullMetrics;
 }
 
 impl<'a, 'b> DrawModel<'b> for wgpu::RenderPass<'a>
@@ -58,83 +68,58 @@ where
         camera_bind_group: &'b wgpu::BindGroup,
         light_bind_group: &'b wgpu::BindGroup,
     ) {
-        if mesh.num_instances > 0 {
-            if let Some(instance_buffer) = &mesh.instance_buffer {
-                self.set_vertex_buffer(1, instance_buffer.slice(..));
-            }
-        }
         self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
+        self.set_vertex_buffer(1, mesh.instance_buffer.slice(..));
 
         self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
         self.set_bind_group(0, &material.bind_group, &[]);
         self.set_bind_group(1, camera_bind_group, &[]);
         self.set_bind_group(2, light_bind_group, &[]);
-        self.draw_indexed(0..mesh.num_elements, 0, 0..mesh.num_instances);
+        self.draw_indexed(0..mesh.num_elements, 0, 0..1);
     }
 
     fn draw_model(
         &mut self,
         model: &'b DrawableState,
+        frustum: &Frustum,
         camera_bind_group: &'b wgpu::BindGroup,
         light_bind_group: &'b wgpu::BindGroup,
-    ) {
-        self.draw_model_instanced(model, camera_bind_group, light_bind_group);
+    ) -> CullMetrics {
+        self.draw_model_instanced(model, frustum, camera_bind_group, light_bind_group)
     }
 
     fn draw_model_instanced(
         &mut self,
         model: &'b DrawableState,
+        frustum: &Frustum,
         camera_bind_group: &'b wgpu::BindGroup,
         light_bind_group: &'b wgpu::BindGroup,
-    ) {
-        for mesh in &model.meshes {
-            let material = &model.materials[mesh.material];
-            self.draw_mesh_instanced(mesh, material, camera_bind_group, light_bind_group);
-        }
-    }
-}
-
-pub trait DrawLight<'a> {
-    fn draw_light_mesh(
-        &mut self,
-        mesh: &'a raw_model::MeshRaw,
-        camera_bind_group: &'a wgpu::BindGroup,
-        light_bind_group: &'a wgpu::BindGroup,
-    );
-
-    fn draw_light_model(
-        &mut self,
-        model: &'a DrawableState,
-        camera_bind_group: &'a wgpu::BindGroup,
-        light_bind_group: &'a wgpu::BindGroup,
-    );
-}
-
-impl<'a, 'b> DrawLight<'b> for wgpu::RenderPass<'a>
-where
-    'b: 'a,
-{
-    fn draw_light_mesh(
-        &mut self,
-        mesh: &'b raw_model::MeshRaw,
-        camera_bind_group: &'b wgpu::BindGroup,
-        light_bind_group: &'b wgpu::BindGroup,
-    ) {
-        self.set_vertex_buffer(0, mesh.vertex_buffer.slice(..));
-        self.set_index_buffer(mesh.index_buffer.slice(..), wgpu::IndexFormat::Uint32);
-        self.set_bind_group(0, camera_bind_group, &[]);
-        self.set_bind_group(1, light_bind_group, &[]);
-        self.draw_indexed(0..mesh.num_elements, 0, 0..1);
-    }
+    ) -> CullMetrics {
+        let mut metrics = CullMetrics::default();
+        for (key, meshes) in &model.meshes {
+            if model
+                .visible
+                .as_ref()
+                .is_some_and(|visible| !visible.contains(key))
+            {
+                metrics.occluded += 1;
+                continue;
+            }
+            let visible = model
+                .bounds
+                .get(key)
+                .is_some_and(|bounds| frustum.intersects(bounds));
+            if !visible {
+                metrics.culled += 1;
+                continue;
+            }
 
-    fn draw_light_model(
-        &mut self,
-        model: &'b DrawableState,
-        camera_bind_group: &'b wgpu::BindGroup,
-        light_bind_group: &'b wgpu::BindGroup,
-    ) {
-        for mesh in &model.meshes {
-            self.draw_light_mesh(mesh, camera_bind_group, light_bind_group);
+            metrics.drawn += 1;
+            for mesh in meshes {
+                let material = &model.materials[mesh.material];
+                self.draw_mesh_instanced(mesh, material, camera_bind_group, light_bind_group);
+            }
         }
+        metrics
     }
 }
@@ -0,0 +1,79 @@
+use cgmath::{InnerSpace, Matrix};
+
+/// Axis-aligned box in render space.
+#[derive(Copy, Clone, Debug)]
+pub struct Aabb {
+    pub min: cgmath::Vector3<f32>,
+    pub max: cgmath::Vector3<f32>,
+}
+
+impl Aabb {
+    /// The smallest box around both boxes.
+    pub fn union(&self, other: &Aabb) -> Aabb {
+        Aabb {
+            min: cgmath::Vector3::new(
+                self.min.x.min(other.min.x),
+                self.min.y.min(other.min.y),
+                self.min.z.min(other.min.z),
+            ),
+            max: cgmath::Vector3::new(
+                self.max.x.max(other.max.x),
+                self.max.y.max(other.max.y),
+                self.max.z.max(other.max.z),
+            ),
+        }
+    }
+}
+
+/// The six planes around what the camera sees, pointing inwards.
+pub struct Frustum {
+    planes: [cgmath::Vector4<f32>; 6],
+}
+
+impl Frustum {
+    /// Extracts the planes from a view-projection matrix whose clip space
+    /// depth runs from 0 to 1, as in wgpu.
+    pub fn from_view_proj(view_proj: cgmath::Matrix4<f32>) -> Self {
+        let [x, y, z, w] = [0, 1, 2, 3].map(|row| view_proj.row(row));
+        // Left, right, bottom, top, near and far
+        Self {
+            planes: [w + x, w - x, w + y, w - y, z, w - z],
+        }
+    }
+
+    /// Whether any part of the box may be visible. Boxes that are near a
+    /// corner of the frustum can pass without being in it.
+    pub fn intersects(&self, aabb: &Aabb) -> bool {
+        self.planes.iter().all(|plane| {
+            // The corner furthest along the plane's normal
+            let corner = cgmath::Vector3::new(
+                if plane.x >= 0.0 {
+                    aabb.max.x
+                } else {
+                    aabb.min.x
+                },
+                if plane.y >= 0.0 {
+                    aabb.max.y
+                } else {
+                    aabb.min.y
+                },
+                if plane.z >= 0.0 {
+                    aabb.max.z
+                } else {
+                    aabb.min.z
+                },
+            );
+            plane.truncate().dot(corner) + plane.w >= 0.0
+        })
+    }
+}
+
+/// Chunks drawn and skipped in the last frame.
+#[derive(Copy, Clone, Debug, Default)]
+pub struct CullMetrics {
+    pub drawn: usize,
+    /// Chunks outside of the frustum.
+    pub culled: usize,
+    /// Chunks that can't be seen from the camera's section.
+    pub occluded: usize,
+}
@@ -1,8 +1,10 @@
+/// Colors of full sky light and full block light. Meshes carry the light
+/// levels falling on their faces, which scale these colors.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct LightUniform {
-    pub position: [f32; 3],
+    pub sky_color: [f32; 3],
     pub _padding: u32,
-    pub color: [f32; 3],
+    pub block_color: [f32; 3],
     pub _padding2: u32,
 }
@@ -1,5 +1,6 @@
 mod bind_group;
 mod buffer;
+mod frustum;
 mod light;
 mod material;
 pub mod model;
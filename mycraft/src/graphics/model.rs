@@ -1,14 +1,16 @@
-pub struct Vertex {
-    pub position: cgmath::Vector3<f32>,
-    pub tex_coords: cgmath::Vector2<f32>,
-    pub normal: cgmath::Vector3<f32>,
-    pub tangent: cgmath::Vector3<f32>,
-    pub bitangent: cgmath::Vector3<f32>,
-}
+/// Identifies the group of meshes that is replaced together, such as the meshes of one chunk.
+pub type MeshKey = (i32, i32, i32);
 
-pub struct Instance {
-    pub position: cgmath::Vector3<f32>,
-    pub rotation: cgmath::Quaternion<f32>,
+/// A corner of a block quad, relative to the chunk the mesh belongs to.
+pub struct Vertex {
+    /// Chunk-local world coordinates of the corner, from 0 to the chunk size.
+    pub position: [u8; 3],
+    /// Index of the face in the block renderer's face order, which gives the
+    /// quad its normal and the part of the texture it shows.
+    pub face: u8,
+    /// Light falling on the quad, sky light in the high four bits and block
+    /// light in the low four.
+    pub light: u8,
 }
 
 pub struct Mesh {
@@ -16,5 +18,6 @@ pub struct Mesh {
     pub vertices: Vec<Vertex>,
     pub indices: Vec<u32>,
     pub material: usize,
-    pub instances: Vec<Instance>,
+    /// World position of the chunk's lowest corner, in render space.
+    pub origin: cgmath::Vector3<f32>,
 }
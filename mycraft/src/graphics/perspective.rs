@@ -1,3 +1,4 @@
+use super::frustum::Frustum;
 use crate::game::camera;
 use cgmath::{perspective, Rad, SquareMatrix};
 
@@ -56,4 +57,9 @@ impl CameraUniform {
         self.view_position = camera.position.to_homogeneous().into();
         self.view_proj = (projection.calc_matrix() * camera.calc_matrix()).into();
     }
+
+    /// The frustum of the view-projection last uploaded to the shader.
+    pub fn frustum(&self) -> Frustum {
+        Frustum::from_view_proj(self.view_proj.into())
+    }
 }
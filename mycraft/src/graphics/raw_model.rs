@@ -1,45 +1,33 @@
+use super::frustum::Aabb;
 use super::model;
 use wgpu::util::DeviceExt;
 
+/// A vertex packed into one word: the corner's x, y and z in five bits
+/// each, then three bits of face and eight of light.
 #[repr(C)]
 #[derive(Copy, Clone, Debug, bytemuck::Pod, bytemuck::Zeroable)]
 pub struct VertexRaw {
-    pub position: [f32; 3],
-    pub tex_coords: [f32; 2],
-    pub normal: [f32; 3],
-    pub tangent: [f32; 3],
-    pub bitangent: [f32; 3],
+    data: u32,
 }
 
 impl VertexRaw {
     pub fn new(vertex: &model::Vertex) -> VertexRaw {
+        let [x, y, z] = vertex.position.map(u32::from);
         Self {
-            position: vertex.position.into(),
-            tex_coords: vertex.tex_coords.into(),
-            normal: vertex.normal.into(),
-            tangent: vertex.tangent.into(),
-            bitangent: vertex.bitangent.into(),
+            data: x
+                | y << 5
+                | z << 10
+                | u32::from(vertex.face) << 15
+                | u32::from(vertex.light) << 18,
         }
     }
 }
 
+/// Places the vertices of a mesh, which are relative to their chunk.
 #[repr(C)]
 #[derive(Debug, Copy, Clone, bytemuck::Pod, bytemuck::Zeroable)]
-#[allow(dead_code)]
 pub struct InstanceRaw {
-    model: [[f32; 4]; 4],
-    normal: [[f32; 3]; 3],
-}
-
-impl InstanceRaw {
-    pub fn new(instance: &model::Instance) -> InstanceRaw {
-        Self {
-            model: (cgmath::Matrix4::from_translation(instance.position)
-                * cgmath::Matrix4::from(instance.rotation))
-                .into(),
-            normal: cgmath::Matrix3::from(instance.rotation).into(),
-        }
-    }
+    origin: [f32; 3],
 }
 
 pub struct MeshRaw {
@@ -48,33 +36,48 @@ pub struct MeshRaw {
     pub index_buffer: wgpu::Buffer,
     pub num_elements: u32,
     pub material: usize,
-    pub instance_buffer: Option<wgpu::Buffer>,
-    pub num_instances: u32,
+    pub instance_buffer: wgpu::Buffer,
+    pub bounds: Aabb,
 }
 
 impl MeshRaw {
     pub fn new(device: &wgpu::Device, mesh: &model::Mesh) -> MeshRaw {
         let raw_vertices = mesh.vertices.iter().map(|v| VertexRaw::new(v)).collect::<Vec<_>>();
         let vertex_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Base Block Vertex Buffer"),
+            label: Some("Chunk Vertex Buffer"),
             contents: bytemuck::cast_slice(&raw_vertices),
             usage: wgpu::BufferUsages::VERTEX,
         });
         let index_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-            label: Some("Base Block Index Buffer"),
+            label: Some("Chunk Index Buffer"),
             contents: bytemuck::cast_slice(&mesh.indices),
             usage: wgpu::BufferUsages::INDEX,
         });
-        let instance_buffer = if mesh.instances.is_empty() {
-            None
-        } else {
-            let instance_data = mesh.instances.iter().map(InstanceRaw::new).collect::<Vec<_>>();
+        let instance_buffer = device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
+            label: Some("Instance Buffer"),
+            contents: bytemuck::cast_slice(&[InstanceRaw {
+                origin: mesh.origin.into(),
+            }]),
+            usage: wgpu::BufferUsages::VERTEX,
+        });
 
-            Some(device.create_buffer_init(&wgpu::util::BufferInitDescriptor {
-                label: Some("Instance Buffer"),
-                contents: bytemuck::cast_slice(&instance_data),
-                usage: wgpu::BufferUsages::VERTEX,
-            }))
+        let (min, max) =
+            mesh.vertices
+                .iter()
+                .fold(([u8::MAX; 3], [u8::MIN; 3]), |(min, max), vertex| {
+                    let position = vertex.position;
+                    (
+                        [0, 1, 2].map(|axis| min[axis].min(position[axis])),
+                        [0, 1, 2].map(|axis| max[axis].max(position[axis])),
+                    )
+                });
+        // Vertices are chunk-local with z up, render space has y up
+        let to_render = |[x, y, z]: [u8; 3]| {
+            mesh.origin + cgmath::Vector3::new(f32::from(x), f32::from(z), f32::from(y))
+        };
+        let bounds = Aabb {
+            min: to_render(min),
+            max: to_render(max),
         };
 
         Self {
@@ -84,7 +87,7 @@ impl MeshRaw {
             material: mesh.material,
             num_elements: mesh.indices.len() as u32,
             instance_buffer,
-            num_instances: if mesh.instances.is_empty() { 1 } else { mesh.instances.len() as u32 },
+            bounds,
         }
     }
 }
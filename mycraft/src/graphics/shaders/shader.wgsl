@@ -6,66 +6,89 @@ struct Camera {
 var<uniform> camera: Camera;
 
 struct VertexInput {
-    @location(0) position: vec3<f32>,
-    @location(1) tex_coords: vec2<f32>,
-    @location(2) normal: vec3<f32>,
-    @location(3) tangent: vec3<f32>,
-    @location(4) bitangent: vec3<f32>,
+    // Chunk-local corner in five bits per axis, then the face and its light
+    @location(0) data: u32,
 };
 
 struct InstanceInput {
-    @location(5) model_matrix_0: vec4<f32>,
-    @location(6) model_matrix_1: vec4<f32>,
-    @location(7) model_matrix_2: vec4<f32>,
-    @location(8) model_matrix_3: vec4<f32>,
-    @location(9) normal_matrix_0: vec3<f32>,
-    @location(10) normal_matrix_1: vec3<f32>,
-    @location(11) normal_matrix_2: vec3<f32>,
+    // Render space position of the chunk's lowest corner
+    @location(1) origin: vec3<f32>,
 }
 
 struct VertexOutput {
     @builtin(position) clip_position: vec4<f32>,
-    @location(0) tex_coords: vec2<f32>,
-    @location(1) tangent_position: vec3<f32>,
-    @location(2) tangent_light_position: vec3<f32>,
-    @location(3) tangent_view_position: vec3<f32>,
+    // Position on the face in blocks, which repeats the texture once per block
+    @location(0) tile: vec2<f32>,
+    // Corner of the face's part of the block texture
+    @location(1) @interpolate(flat) cell: vec2<f32>,
+    @location(2) sky_light: f32,
+    @location(3) block_light: f32,
+    @location(4) shade: f32,
 };
 
+// Render space normals in the block renderer's face order: front, back,
+// top, bottom, right and left
+const FACE_NORMALS = array<vec3<f32>, 6>(
+    vec3<f32>(0.0, 0.0, 1.0),
+    vec3<f32>(0.0, 0.0, -1.0),
+    vec3<f32>(0.0, 1.0, 0.0),
+    vec3<f32>(0.0, -1.0, 0.0),
+    vec3<f32>(1.0, 0.0, 0.0),
+    vec3<f32>(-1.0, 0.0, 0.0),
+);
+
+// Block textures unfold the cube into a four by three grid
+const CELL_SIZE = vec2<f32>(0.25, 1.0 / 3.0);
+const FACE_CELLS = array<vec2<f32>, 6>(
+    vec2<f32>(1.0, 1.0),
+    vec2<f32>(3.0, 1.0),
+    vec2<f32>(1.0, 0.0),
+    vec2<f32>(1.0, 2.0),
+    vec2<f32>(2.0, 1.0),
+    vec2<f32>(0.0, 1.0),
+);
+
+// Brightness of a light level, each level a fifth dimmer than the one above
+fn brightness(level: u32) -> f32 {
+    return pow(0.8, f32(15u - level));
+}
+
+// Texture coordinates across a face, in blocks, running the same way as in
+// the face's part of the texture
+fn face_tile(face: u32, position: vec3<f32>) -> vec2<f32> {
+    switch face {
+        case 0u: { return vec2<f32>(position.x, -position.y); }
+        case 1u: { return vec2<f32>(-position.x, -position.y); }
+        case 2u: { return vec2<f32>(position.x, -position.z); }
+        case 3u: { return vec2<f32>(position.x, position.z); }
+        case 5u: { return vec2<f32>(-position.z, -position.y); }
+        default: { return vec2<f32>(position.z, -position.y); }
+    }
+}
+
 @vertex
 fn vs_main(
     model: VertexInput,
     instance: InstanceInput,
 ) -> VertexOutput {
-    let model_matrix = mat4x4<f32>(
-        instance.model_matrix_0,
-        instance.model_matrix_1,
-        instance.model_matrix_2,
-        instance.model_matrix_3,
-    );
-    let normal_matrix = mat3x3<f32>(
-        instance.normal_matrix_0,
-        instance.normal_matrix_1,
-        instance.normal_matrix_2,
-    );
-
-    // Construct the tangent matrix
-    let world_normal = normalize(normal_matrix * model.normal);
-    let world_tangent = normalize(normal_matrix * model.tangent);
-    let world_bitangent = normalize(normal_matrix * model.bitangent);
-    let tangent_matrix = transpose(mat3x3<f32>(
-        world_tangent,
-        world_bitangent,
-        world_normal,
-    ));
-
-    let world_position = model_matrix * vec4<f32>(model.position, 1.0);
+    // Chunks store x, y and z with z up, render space has y up
+    let x = f32(model.data & 0x1fu);
+    let y = f32((model.data >> 5u) & 0x1fu);
+    let z = f32((model.data >> 10u) & 0x1fu);
+    let face = (model.data >> 15u) & 0x7u;
+    let level = (model.data >> 18u) & 0xffu;
+    let position = vec3<f32>(x, z, y);
+    let normal = FACE_NORMALS[face];
 
     var out: VertexOutput;
-    out.clip_position = camera.view_proj * world_position;
-    out.tex_coords = model.tex_coords;
-    out.tangent_position = tangent_matrix * world_position.xyz;
-    out.tangent_view_position = tangent_matrix * camera.view_pos.xyz;
-    out.tangent_light_position = tangent_matrix * light.position;
+    out.clip_position = camera.view_proj * vec4<f32>(instance.origin + position, 1.0);
+    out.tile = face_tile(face, position);
+    out.cell = FACE_CELLS[face] * CELL_SIZE;
+    out.sky_light = brightness(level >> 4u);
+    out.block_light = brightness(level & 0xfu);
+    // Faces are shaded by the way they look, so that edges stand out under
+    // even light: tops brightest, bottoms darkest
+    out.shade = 0.8 + 0.2 * normal.y - 0.1 * abs(normal.x);
     return out;
 }
 
@@ -75,8 +98,8 @@ var texture_diffuse: texture_2d<f32>;
 var sampler_diffuse: sampler;
 
 struct Light {
-    position: vec3<f32>,
-    color: vec3<f32>,
+    sky_color: vec3<f32>,
+    block_color: vec3<f32>,
 }
 @group(2) @binding(0)
 var<uniform> light: Light;
@@ -84,35 +107,18 @@ var<uniform> light: Light;
 
 @fragment
 fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
-    let object_color: vec4<f32> = textureSample(texture_diffuse, sampler_diffuse, in.tex_coords);
+    let tex_coords = in.cell + fract(in.tile) * CELL_SIZE;
+    let object_color: vec4<f32> = textureSample(texture_diffuse, sampler_diffuse, tex_coords);
 
     // Keep transparency
     if (object_color.a < 0.01) {
         discard;
     }
 
-    // Create the lighting vectors
-    let light_dir = normalize(in.tangent_light_position - in.tangent_position);
-    let view_dir = normalize(in.tangent_view_position - in.tangent_position);
-    let half_dir = normalize(view_dir + light_dir);
-
-
-    // Ambient component
-
-    let ambient_strength = 0.15; // Number pulled out of nowhere, because it looks good
-    let ambient_color = light.color * ambient_strength;
-
-    // Diffuse component
-
-    let diffuse_strength = max(dot(in.tangent_position, light_dir), 0.0);
-    let diffuse_color = light.color * diffuse_strength;
-
-    // Specular component
-
-    let specular_strength = pow(max(dot(in.tangent_position, half_dir), 0.0), 32.0);
-    let specular_color = specular_strength * light.color;
-
-    let result = (ambient_color + diffuse_color + specular_color) * object_color.xyz;
+    // The brighter of sky and block light wins, and nothing is pitch black
+    let ambient = 0.03;
+    let light_color = max(light.sky_color * in.sky_light, light.block_color * in.block_light);
+    let result = max(light_color, vec3<f32>(ambient)) * in.shade * object_color.xyz;
 
     return vec4<f32>(result, object_color.a);
 }
@@ -1,11 +1,16 @@
 use super::drawable::DrawModel;
+use super::frustum::CullMetrics;
 use super::light::LightUniform;
 use super::perspective::{CameraUniform, Projection};
 use super::render_pipeline::RenderPipelineBuilder;
 use super::{bind_group, buffer, drawable, material, model, raw_model, state};
 use crate::game::camera::Camera;
+use crate::world::registry::registry;
 use cgmath;
-use instant;
+use std::collections::{HashMap, HashSet};
+
+// Past the chunks at the edge of the render distance, 32 chunks away
+const Z_FAR: f32 = 600.0;
 
 #[derive(Copy, Clone, Debug)]
 pub struct Size {
@@ -20,7 +25,6 @@ pub struct State<'a> {
     config: wgpu::SurfaceConfiguration,
     pub size: Size,
     render_pipeline: wgpu::RenderPipeline,
-    light_render_pipeline: wgpu::RenderPipeline,
     clear_color: wgpu::Color,
     projection: Projection,
     camera_buffer: wgpu::Buffer,
@@ -28,18 +32,14 @@ pub struct State<'a> {
     camera_uniform: CameraUniform,
     light_buffer: wgpu::Buffer,
     light_uniform: LightUniform,
-    light_bind_group_layout: wgpu::BindGroupLayout,
     light_bind_group: wgpu::BindGroup,
     depth_texture: material::Texture,
     drawable_state: drawable::DrawableState,
+    cull_metrics: CullMetrics,
 }
 
 impl<'a> State<'a> {
-    pub async fn new(
-        window: &'a winit::window::Window,
-        camera: &Camera,
-        initial_meshes: &Vec<model::Mesh>,
-    ) -> Self {
+    pub async fn new(window: &'a winit::window::Window, camera: &Camera) -> Self {
         let physical_size = window.inner_size();
         let size = state::Size {
             width: physical_size.width,
@@ -96,7 +96,7 @@ impl<'a> State<'a> {
             bind_group::create_material_bind_group_layout(&device, "Material Bind Group Layout");
 
         let projection =
-            Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, 100.0);
+            Projection::new(config.width, config.height, cgmath::Deg(45.0), 0.1, Z_FAR);
         let camera_bind_group_layout =
             bind_group::create_camera_bind_group_layout(&device, "Camera Bind Group Layout");
         let mut camera_uniform = CameraUniform::new();
@@ -112,9 +112,9 @@ impl<'a> State<'a> {
         let light_bind_group_layout =
             bind_group::create_light_bind_group_layout(&device, "Light Bind Group Layout");
         let light_uniform = LightUniform {
-            position: [2.0, 2.0, 2.0],
+            sky_color: [1.0, 1.0, 1.0],
             _padding: 0,
-            color: [1.0, 1.0, 1.0],
+            block_color: [1.0, 0.85, 0.6],
             _padding2: 0,
         };
         let light_buffer = buffer::create_light_buffer(light_uniform, &device);
@@ -138,23 +138,11 @@ impl<'a> State<'a> {
             render_pipeline = pipeline_builder.build("Render Pipeline");
         }
 
-        let light_render_pipeline: wgpu::RenderPipeline;
-        {
-            let mut pipeline_builder = RenderPipelineBuilder::new(&device);
-            pipeline_builder.set_shader_module("shaders/light.wgsl", "vs_main", "fs_main");
-            pipeline_builder.set_pixel_format(config.format);
-            pipeline_builder.add_vertex_buffer_layout(buffer::create_vertex_buffer_layout());
-            pipeline_builder.add_bind_group_layout(&camera_bind_group_layout);
-            pipeline_builder.add_bind_group_layout(&light_bind_group_layout);
-            light_render_pipeline = pipeline_builder.build("Light Render Pipeline");
-        }
-
         let depth_texture =
             material::Texture::create_depth_texture(&device, &config, "depth_texture");
 
         let mut materials: Vec<material::Material> = Vec::new();
-        for i in 0..8 {
-            let texture_file = format!("textures/{}.png", i);
+        for texture_file in registry().textures() {
             let diffuse_texture =
                 material::Texture::load_texture(texture_file.as_str(), &device, &queue, false)
                     .await
@@ -168,18 +156,11 @@ impl<'a> State<'a> {
             ));
         }
 
-        let render_time = instant::Instant::now();
-
-        let raw_meshes: Vec<raw_model::MeshRaw> = initial_meshes
-            .iter()
-            .map(|mesh| raw_model::MeshRaw::new(&device, mesh))
-            .collect();
-
-        println!("Raw meshes rendered in: {:?}", render_time.elapsed());
-
         let drawable_state = drawable::DrawableState {
-            meshes: raw_meshes,
+            meshes: HashMap::new(),
+            bounds: HashMap::new(),
             materials,
+            visible: None,
         };
 
         Self {
@@ -189,7 +170,6 @@ impl<'a> State<'a> {
             config,
             size,
             render_pipeline,
-            light_render_pipeline,
             clear_color: wgpu::Color {
                 r: 0.69,
                 g: 0.88,
@@ -202,10 +182,10 @@ impl<'a> State<'a> {
             camera_uniform,
             light_uniform,
             light_buffer,
-            light_bind_group_layout,
             light_bind_group,
             depth_texture,
             drawable_state,
+            cull_metrics: CullMetrics::default(),
         }
     }
 
@@ -224,27 +204,38 @@ impl<'a> State<'a> {
         }
     }
 
-    pub fn set_meshes(&mut self, meshes: &Vec<model::Mesh>) {
-        let render_time = instant::Instant::now();
-        self.drawable_state.meshes = meshes
-            .iter()
-            .map(|mesh| raw_model::MeshRaw::new(&self.device, mesh))
-            .collect();
-        println!("Raw meshes rendered in: {:?}", render_time.elapsed());
+    /// Replaces the meshes stored under `key`, leaving every other group untouched.
+    pub fn set_key_meshes(&mut self, key: model::MeshKey, meshes: &[model::Mesh]) {
+        if meshes.is_empty() {
+            self.remove_key_meshes(key);
+        } else {
+            let raw_meshes = create_raw_meshes(&self.device, meshes);
+            if let Some(bounds) = raw_meshes
+                .iter()
+                .map(|mesh| mesh.bounds)
+                .reduce(|bounds, other| bounds.union(&other))
+            {
+                self.drawable_state.bounds.insert(key, bounds);
+            }
+            self.drawable_state.meshes.insert(key, raw_meshes);
+        }
+    }
+
+    /// Limits drawing to the mesh groups in `visible`, or draws all of them
+    /// when it is `None`.
+    pub fn set_visible_chunks(&mut self, visible: Option<HashSet<model::MeshKey>>) {
+        self.drawable_state.visible = visible;
+    }
+
+    /// Drops the meshes stored under `key` along with their GPU buffers.
+    pub fn remove_key_meshes(&mut self, key: model::MeshKey) {
+        self.drawable_state.meshes.remove(&key);
+        self.drawable_state.bounds.remove(&key);
     }
 
     pub fn update(&mut self, camera: &Camera) {
         self.camera_uniform
             .update_view_proj(&camera, &self.projection);
-        // Update the light
-        // let old_position: cgmath::Vector3<_> = self.light_uniform.position.into();
-        // self.light_uniform.position = (cgmath::Quaternion::from_axis_angle(
-        //     (0.0, 1.0, 0.0).into(),
-        //     cgmath::Deg(60.0 * dt.as_secs_f32()),
-        // ) * old_position)
-        //     .into();
-        // self.light_uniform.position = self.camera.position.clone().into();
-
         self.queue.write_buffer(
             &self.light_buffer,
             0,
@@ -292,17 +283,10 @@ impl<'a> State<'a> {
         };
         {
             let mut render_pass = command_encoder.begin_render_pass(&render_pass_descriptor);
-            // render_pass.set_vertex_buffer(1, self.instance_buffer.slice(..));
-            // render_pass.set_pipeline(&self.light_render_pipeline);
-            // render_pass.draw_light_model(
-            //     &self.obj_model,
-            //     &self.camera_bind_group,
-            //     &self.light_bind_group,
-            // );
-
             render_pass.set_pipeline(&self.render_pipeline);
-            render_pass.draw_model_instanced(
+            self.cull_metrics = render_pass.draw_model_instanced(
                 &self.drawable_state,
+                &self.camera_uniform.frustum(),
                 &self.camera_bind_group,
                 &self.light_bind_group,
             );
@@ -315,7 +299,19 @@ impl<'a> State<'a> {
         Ok(())
     }
 
+    /// Chunks drawn and culled in the last frame, for debugging.
+    pub fn cull_metrics(&self) -> CullMetrics {
+        self.cull_metrics
+    }
+
     pub fn set_clear_color(&mut self, new_color: wgpu::Color) {
         self.clear_color = new_color;
     }
 }
+
+fn create_raw_meshes(device: &wgpu::Device, meshes: &[model::Mesh]) -> Vec<raw_model::MeshRaw> {
+    meshes
+        .iter()
+        .map(|mesh| raw_model::MeshRaw::new(device, mesh))
+        .collect()
+}
@@ -0,0 +1,3 @@
+pub mod world;
+mod graphics;
+pub mod game;
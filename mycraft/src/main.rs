@@ -1,6 +1,4 @@
-mod world;
-mod graphics;
-pub mod game;
+use mycraft::game;
 
 fn main() {
     let mut scene = game::scene::Scene::new();
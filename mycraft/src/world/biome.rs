@@ -1,14 +1,112 @@
+// Elevations above sea level, in blocks, at which the terrain stops being
+// shore and starts being a mountain
+const BEACH_ELEVATION: i32 = 2;
+const SWAMP_ELEVATION: i32 = 6;
+const MOUNTAIN_ELEVATION: i32 = 22;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
 pub enum Biome {
-    Grass,
+    Plains,
+    Forest,
+    Taiga,
     Desert,
     Snow,
+    Swamp,
+    Mountains,
+    Beach,
+    Ocean,
+}
+
+/// How a biome shapes and covers the terrain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BiomeProperties {
+    /// Top block of dry columns.
+    pub surface_block: &'static str,
+    /// Blocks below the top one, down to the stone.
+    pub filler_block: &'static str,
+    /// Top block of columns below sea level.
+    pub underwater_block: &'static str,
+    /// Chance of a tree growing in any one column.
+    pub tree_density: f64,
+    /// Multiplies the distance of the terrain from sea level.
+    pub height_scale: f64,
+    /// Blocks added to the terrain height after scaling.
+    pub height_offset: f64,
+}
+
+impl Biome {
+    pub const ALL: [Biome; 9] = [
+        Biome::Plains,
+        Biome::Forest,
+        Biome::Taiga,
+        Biome::Desert,
+        Biome::Snow,
+        Biome::Swamp,
+        Biome::Mountains,
+        Biome::Beach,
+        Biome::Ocean,
+    ];
+
+    /// Whether the shore of the biome turns into a beach where it meets the
+    /// sea. Swamps turn into the sea, mountains drop into it.
+    pub fn has_beaches(self) -> bool {
+        matches!(
+            self,
+            Biome::Plains | Biome::Forest | Biome::Taiga | Biome::Desert | Biome::Snow
+        )
+    }
+
+    pub fn properties(self) -> BiomeProperties {
+        let (surface_block, filler_block, underwater_block) = match self {
+            Biome::Plains | Biome::Forest | Biome::Swamp => ("grass", "dirt", "dirt"),
+            Biome::Taiga => ("snow", "dirt", "dirt"),
+            Biome::Snow => ("snow", "snow", "dirt"),
+            Biome::Desert | Biome::Beach | Biome::Ocean => ("sand", "sand", "sand"),
+            Biome::Mountains => ("stone", "stone", "stone"),
+        };
+        let tree_density = match self {
+            Biome::Forest => 0.02,
+            Biome::Taiga => 0.012,
+            Biome::Swamp => 0.008,
+            Biome::Plains => 0.001,
+            Biome::Mountains => 0.0005,
+            Biome::Snow | Biome::Desert | Biome::Beach | Biome::Ocean => 0.0,
+        };
+        let (height_scale, height_offset) = match self {
+            Biome::Plains => (0.6, 1.0),
+            Biome::Forest => (0.9, 1.0),
+            Biome::Taiga => (1.0, 1.0),
+            Biome::Desert => (0.5, 1.0),
+            Biome::Snow => (0.7, 1.0),
+            Biome::Swamp => (0.2, 0.0),
+            Biome::Mountains => (1.5, 4.0),
+            Biome::Beach => (0.5, 0.0),
+            Biome::Ocean => (1.0, -3.0),
+        };
+
+        BiomeProperties {
+            surface_block,
+            filler_block,
+            underwater_block,
+            tree_density,
+            height_scale,
+            height_offset,
+        }
+    }
 }
 
-pub fn get_biome_by_params(temperature: f64, humidity: f64) -> Biome {
+/// Picks the biome of a column from its climate, both in `0.0..=1.0`, and
+/// the elevation of its unshaped terrain above sea level.
+pub fn get_biome_by_params(temperature: f64, humidity: f64, elevation: i32) -> Biome {
     match (humidity, temperature) {
-        (h, t) if h < 0.5 && t >= 0.6 => Biome::Desert,
-        (h, t) if h >= 0.5 && t >= 0.3 && t < 0.6 => Biome::Grass,
-        (h, t) if h >= 0.5 && t < 0.3 => Biome::Snow,
-        _ => Biome::Grass,
+        _ if elevation < 0 => Biome::Ocean,
+        _ if elevation < BEACH_ELEVATION => Biome::Beach,
+        _ if elevation > MOUNTAIN_ELEVATION => Biome::Mountains,
+        (h, t) if t < 0.35 && h >= 0.5 => Biome::Taiga,
+        (_, t) if t < 0.35 => Biome::Snow,
+        (h, t) if h < 0.5 && t >= 0.65 => Biome::Desert,
+        (h, _) if h >= 0.7 && elevation < SWAMP_ELEVATION => Biome::Swamp,
+        (h, _) if h >= 0.5 => Biome::Forest,
+        _ => Biome::Plains,
     }
 }
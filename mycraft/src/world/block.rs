@@ -1,118 +1,124 @@
+use super::registry::{self, BlockDefinition, FluidDefinition};
 use std::fmt;
 
-#[derive(Clone)]
-pub enum BlockType {
-    Dirt,
-    Grass,
-    Sand,
-    Snow,
-    Stone,
-    Coal,
-    TreeLog,
-    TreeLeaf,
+pub type BlockId = u16;
+
+pub const AIR_ID: BlockId = 0;
+
+/// Block faces in the order used by the block renderer.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Face {
+    Front,
+    Back,
+    Top,
+    Bottom,
+    Right,
+    Left,
 }
 
-impl fmt::Display for BlockType {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        let name = match self {
-            BlockType::Dirt => "Dirt",
-            BlockType::Grass => "Grass",
-            BlockType::Sand => "Sand",
-            BlockType::Snow => "Snow",
-            BlockType::Stone => "Stone",
-            BlockType::Coal => "Coal",
-            BlockType::TreeLog => "TreeLog",
-            BlockType::TreeLeaf => "TreeLeaf",
-        };
-        write!(f, "{name}")
+impl Face {
+    pub const ALL: [Face; 6] = [
+        Face::Front,
+        Face::Back,
+        Face::Top,
+        Face::Bottom,
+        Face::Right,
+        Face::Left,
+    ];
+
+    /// Offset to the neighbour the face looks at, in world coordinates.
+    pub fn normal(self) -> (i32, i32, i32) {
+        match self {
+            Face::Front => (0, 1, 0),
+            Face::Back => (0, -1, 0),
+            Face::Top => (0, 0, 1),
+            Face::Bottom => (0, 0, -1),
+            Face::Right => (1, 0, 0),
+            Face::Left => (-1, 0, 0),
+        }
+    }
+
+    pub fn opposite(self) -> Face {
+        match self {
+            Face::Front => Face::Back,
+            Face::Back => Face::Front,
+            Face::Top => Face::Bottom,
+            Face::Bottom => Face::Top,
+            Face::Right => Face::Left,
+            Face::Left => Face::Right,
+        }
     }
 }
 
-#[derive(Clone)]
+/// A handle to a block definition from the global [`registry::BlockRegistry`].
+#[derive(Clone, Copy)]
 pub struct Block {
-    block_type: BlockType,
-    material_id: i32,
-    opaque: bool,
+    definition: &'static BlockDefinition,
 }
 
 impl Block {
-    pub fn get_block_type(&self) -> &BlockType {
-        &self.block_type
+    pub fn from_id(id: BlockId) -> Option<Block> {
+        registry::registry()
+            .get(id)
+            .map(|definition| Block { definition })
     }
 
-    pub fn get_material_id(&self) -> i32 {
-        self.material_id
+    pub fn from_name(name: &str) -> Option<Block> {
+        registry::registry()
+            .get_by_name(name)
+            .map(|definition| Block { definition })
     }
 
-    pub fn is_opaque(&self) -> bool {
-        self.opaque
+    pub fn get_id(&self) -> BlockId {
+        self.definition.id
     }
-}
 
-pub struct BlockFactory;
+    pub fn get_name(&self) -> &'static str {
+        &self.definition.name
+    }
 
-impl BlockFactory {
-    pub fn create_dirt() -> Block {
-        Block {
-            block_type: BlockType::Dirt,
-            material_id: 0,
-            opaque: false,
-        }
+    pub fn get_material_id(&self, face: Face) -> usize {
+        self.definition.get_material_id(face)
     }
 
-    pub fn create_grass() -> Block {
-        Block {
-            block_type: BlockType::Grass,
-            material_id: 1,
-            opaque: false,
-        }
+    pub fn get_hardness(&self) -> f32 {
+        self.definition.hardness
     }
 
-    pub fn create_sand() -> Block {
-        Block {
-            block_type: BlockType::Sand,
-            material_id: 2,
-            opaque: false,
-        }
+    pub fn get_light_emission(&self) -> u8 {
+        self.definition.light_emission
     }
 
-    pub fn create_snow() -> Block {
-        Block {
-            block_type: BlockType::Snow,
-            material_id: 3,
-            opaque: false,
-        }
+    pub fn is_opaque(&self) -> bool {
+        self.definition.opaque
     }
 
-    pub fn create_stone() -> Block {
-        Block {
-            block_type: BlockType::Stone,
-            material_id: 4,
-            opaque: false,
-        }
+    pub fn get_fluid(&self) -> Option<&'static FluidDefinition> {
+        self.definition.fluid.as_ref()
     }
 
-    pub fn create_coal() -> Block {
-        Block {
-            block_type: BlockType::Coal,
-            material_id: 5,
-            opaque: false,
-        }
+    pub fn is_fluid(&self) -> bool {
+        self.definition.fluid.is_some()
     }
 
-    pub fn create_tree_log() -> Block {
-        Block {
-            block_type: BlockType::TreeLog,
-            material_id: 6,
-            opaque: false,
-        }
+    /// Whether faces of neighbouring blocks stay visible through this block.
+    pub fn is_transparent(&self) -> bool {
+        !self.definition.opaque
     }
 
-    pub fn create_tree_leaf() -> Block {
-        Block {
-            block_type: BlockType::TreeLeaf,
-            material_id: 7,
-            opaque: true,
-        }
+    /// Whether the face this block shows towards `neighbour` is visible.
+    /// See-through neighbours show it, except for blocks of the same kind
+    /// culling against each other.
+    pub fn shows_face_to(&self, neighbour: Option<Block>) -> bool {
+        neighbour.is_none_or(|neighbour| {
+            neighbour.is_transparent()
+                && !(self.definition.cull_same && neighbour.get_id() == self.get_id())
+        })
+    }
+}
+
+impl fmt::Display for Block {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.definition.name)
     }
 }
@@ -0,0 +1,230 @@
+use super::chunk::Chunk;
+use std::collections::{HashMap, HashSet};
+
+pub type ChunkKey = (i32, i32, i32);
+
+/// Upper bound on what the chunk cache may hold.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CacheBudget {
+    Chunks(usize),
+    /// Approximate bytes, as reported by `Chunk::heap_size`.
+    Bytes(usize),
+}
+
+/// Which chunks are evicted first once the budget is exceeded.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum EvictionPolicy {
+    LeastRecentlyUsed,
+    /// Farthest from the focus first, least recently used among equals.
+    Distance,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct CacheMetrics {
+    pub hits: u64,
+    pub misses: u64,
+    pub evictions: u64,
+}
+
+struct CachedChunk {
+    chunk: Chunk,
+    last_used: u64,
+    // Bytes counted for the chunk in `estimated_bytes`
+    size: usize,
+}
+
+/// Loaded chunks, bounded by a `CacheBudget`.
+///
+/// Once the budget is exceeded, `evict` drops chunks down to seven eighths of
+/// it, so that the cost of ranking the chunks is spread over many loads.
+pub struct ChunkCache {
+    chunks: HashMap<ChunkKey, CachedChunk>,
+    budget: CacheBudget,
+    policy: EvictionPolicy,
+    focus: ChunkKey,
+    clock: u64,
+    estimated_bytes: usize,
+    // Chunks handed out mutably since their size was last counted
+    unmeasured: HashSet<ChunkKey>,
+    metrics: CacheMetrics,
+}
+
+impl ChunkCache {
+    pub fn new(budget: CacheBudget, policy: EvictionPolicy) -> Self {
+        Self {
+            chunks: HashMap::new(),
+            budget,
+            policy,
+            focus: (0, 0, 0),
+            clock: 0,
+            estimated_bytes: 0,
+            unmeasured: HashSet::new(),
+            metrics: CacheMetrics::default(),
+        }
+    }
+
+    pub fn budget(&self) -> CacheBudget {
+        self.budget
+    }
+
+    pub fn set_budget(&mut self, budget: CacheBudget) {
+        self.budget = budget;
+    }
+
+    pub fn policy(&self) -> EvictionPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: EvictionPolicy) {
+        self.policy = policy;
+    }
+
+    /// Chunk the player is in, used by `EvictionPolicy::Distance`.
+    pub fn set_focus(&mut self, focus: ChunkKey) {
+        self.focus = focus;
+    }
+
+    pub fn metrics(&self) -> CacheMetrics {
+        self.metrics
+    }
+
+    pub fn len(&self) -> usize {
+        self.chunks.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.chunks.is_empty()
+    }
+
+    pub fn contains(&self, key: &ChunkKey) -> bool {
+        self.chunks.contains_key(key)
+    }
+
+    /// Looks a chunk up without counting it as a use.
+    pub fn peek(&self, key: &ChunkKey) -> Option<&Chunk> {
+        self.chunks.get(key).map(|cached| &cached.chunk)
+    }
+
+    /// Looks a chunk up without counting it as a use. Its size is counted
+    /// again before the next eviction.
+    pub fn get_mut(&mut self, key: &ChunkKey) -> Option<&mut Chunk> {
+        let cached = self.chunks.get_mut(key)?;
+        self.unmeasured.insert(*key);
+        Some(&mut cached.chunk)
+    }
+
+    /// Marks a chunk as used and records a hit, or records a miss if the
+    /// chunk isn't loaded.
+    pub fn touch(&mut self, key: &ChunkKey) -> bool {
+        self.clock += 1;
+        match self.chunks.get_mut(key) {
+            Some(cached) => {
+                self.metrics.hits += 1;
+                cached.last_used = self.clock;
+                true
+            }
+            None => {
+                self.metrics.misses += 1;
+                false
+            }
+        }
+    }
+
+    pub fn insert(&mut self, chunk: Chunk) -> &mut Chunk {
+        let size = chunk_size(&chunk);
+        self.estimated_bytes += size;
+        let key = (chunk.x, chunk.y, chunk.z);
+        let cached = CachedChunk {
+            chunk,
+            last_used: self.clock,
+            size,
+        };
+
+        if let Some(previous) = self.chunks.insert(key, cached) {
+            self.estimated_bytes -= previous.size;
+        }
+        self.unmeasured.insert(key);
+        &mut self.chunks.get_mut(&key).unwrap().chunk
+    }
+
+    pub fn remove(&mut self, key: &ChunkKey) -> Option<Chunk> {
+        let cached = self.chunks.remove(key)?;
+        self.estimated_bytes -= cached.size;
+        self.unmeasured.remove(key);
+        Some(cached.chunk)
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &ChunkKey> {
+        self.chunks.keys()
+    }
+
+    pub fn values_mut(&mut self) -> impl Iterator<Item = &mut Chunk> {
+        self.unmeasured.extend(self.chunks.keys().copied());
+        self.chunks.values_mut().map(|cached| &mut cached.chunk)
+    }
+
+    /// Makes room for one more chunk if the cache is full, returning the
+    /// evicted chunks.
+    pub fn evict(&mut self) -> Vec<Chunk> {
+        let limit = match self.budget {
+            CacheBudget::Chunks(limit) => limit,
+            CacheBudget::Bytes(limit) => limit,
+        };
+        // Edits, light and fluid may have grown chunks since they were counted
+        self.measure();
+        if self.usage() < limit {
+            return Vec::new();
+        }
+
+        let mut ranked: Vec<(ChunkKey, u64, u64)> = self
+            .chunks
+            .iter()
+            .map(|(key, cached)| (*key, distance_squared(self.focus, *key), cached.last_used))
+            .collect();
+        match self.policy {
+            EvictionPolicy::LeastRecentlyUsed => ranked.sort_unstable_by_key(|entry| entry.2),
+            EvictionPolicy::Distance => {
+                ranked.sort_unstable_by(|a, b| b.1.cmp(&a.1).then(a.2.cmp(&b.2)))
+            }
+        }
+
+        let target = limit - limit / 8;
+        let mut evicted = Vec::new();
+        for (key, _, _) in ranked {
+            if self.usage() < target.max(1) {
+                break;
+            }
+            evicted.extend(self.remove(&key));
+        }
+
+        self.metrics.evictions += evicted.len() as u64;
+        evicted
+    }
+
+    fn measure(&mut self) {
+        for key in self.unmeasured.drain() {
+            if let Some(cached) = self.chunks.get_mut(&key) {
+                let size = chunk_size(&cached.chunk);
+                self.estimated_bytes = self.estimated_bytes - cached.size + size;
+                cached.size = size;
+            }
+        }
+    }
+
+    fn usage(&self) -> usize {
+        match self.budget {
+            CacheBudget::Chunks(_) => self.chunks.len(),
+            CacheBudget::Bytes(_) => self.estimated_bytes,
+        }
+    }
+}
+
+/// Squared distance between two chunks, in chunks.
+pub fn distance_squared(a: ChunkKey, b: ChunkKey) -> u64 {
+    let (dx, dy, dz) = ((a.0 - b.0) as i64, (a.1 - b.1) as i64, (a.2 - b.2) as i64);
+    (dx * dx + dy * dy + dz * dz) as u64
+}
+
+fn chunk_size(chunk: &Chunk) -> usize {
+    size_of::<Chunk>() + chunk.heap_size()
+}
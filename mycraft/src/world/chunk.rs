@@ -1,418 +1,612 @@
-use super::biome;
 use super::biome::Biome;
-use super::block::{Block, BlockFactory};
-use noise::{NoiseFn, Simplex};
-use std::cmp::{max, min};
-use std::collections::HashMap;
-
-// Max values
-pub const MAX_HEIGHT: i32 = 50;
-
-// Increments
-const HEIGHT_INCREMENT: f64 = 0.01;
-
-const TEMPERATURE_INCREMENT: f64 = 0.013;
-const HUMIDITY_INCREMENT: f64 = 0.0025;
-
-const CAVE_INCREMENT_XY: f64 = 0.03;
-const CAVE_INCREMENT_Z: f64 = 0.006;
-
-const BLOCK_INCREMENT: f64 = 0.2;
-
-const TREE_TRUNK_INCREMENT: f64 = 0.75;
-const TREE_BRANCH_INCREMENT: f64 = 0.9;
-const TREE_LEAF_INCREMENT: f64 = 0.6;
-
-// Misc
-const CHUNK_SIZE: i32 = 16;
-const CAVE_MULTIPLIER: i32 = 3;
-const MIN_HEIGHT: i32 = 1;
-const HEIGHT_AMPLIFIER: f64 = 1.2;
-
+use super::block::{Block, BlockId, AIR_ID};
+use super::cache::{CacheBudget, CacheMetrics, ChunkCache, ChunkKey, EvictionPolicy};
+use super::decoration::{self, BlockWrite, PendingWrites};
+use super::delta::ChunkDelta;
+use super::generation::{self, GenerationPool};
+use super::generator::{GeneratorPipeline, GeneratorPreset};
+use super::light::LightStorage;
+use super::palette::PaletteStorage;
+use super::region::{ChunkRecord, RegionStore, SaveMode};
+use anyhow::bail;
+use std::collections::{BTreeMap, HashSet};
+use std::sync::Arc;
+
+// Bump whenever the built-in stages generate different terrain, so that saved
+// edit deltas are not replayed on top of it, see `GeneratorPipeline::version`
+pub const GENERATOR_VERSION: u32 = 9;
+
+// World limits, in chunks
+pub const MIN_CHUNK_Z: i32 = -2;
+pub const MAX_CHUNK_Z: i32 = 15;
+
+pub const CHUNK_SIZE: i32 = 16;
+pub const CHUNK_VOLUME: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+pub const WORLD_BOTTOM: i32 = MIN_CHUNK_Z * CHUNK_SIZE;
+
+/// A 16x16x16 section of a chunk column. Sections are stacked along `z`
+/// and are generated, stored and unloaded independently of each other.
 pub struct Chunk {
     pub x: i32,
     pub y: i32,
-    pub chunk_map: HashMap<(i32, i32, i32), Block>,
+    pub z: i32,
+    blocks: PaletteStorage,
+    // Not lit until the world loads the section, see `World::get_light`
+    light: Option<LightStorage>,
+    // Flow levels of the flowing fluid blocks by block index, see
+    // `World::fluid_level`. Sources have none.
+    fluid_levels: BTreeMap<u16, u8>,
+    modified: bool,
 }
 
 impl Chunk {
-    pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<&Block> {
-        self.chunk_map.get(&(x, y, z))
+    /// An empty section filled with air.
+    pub fn new(x: i32, y: i32, z: i32) -> Self {
+        Self::from_storage(x, y, z, PaletteStorage::new(CHUNK_VOLUME))
     }
 
-    pub fn is_opaque(&self, x: i32, y: i32, z: i32) -> bool {
-        let key = (x, y, z);
-        !self.chunk_map.contains_key(&key) || self.chunk_map.get(&key).unwrap().is_opaque()
+    /// A section completely filled with `block`.
+    pub fn filled(x: i32, y: i32, z: i32, block: Block) -> Self {
+        Self::from_storage(
+            x,
+            y,
+            z,
+            PaletteStorage::filled(CHUNK_VOLUME, block.get_id()),
+        )
+    }
+
+    /// Wraps previously stored blocks. Returns `None` if the storage is not
+    /// the size of a section.
+    pub fn from_blocks(x: i32, y: i32, z: i32, blocks: PaletteStorage) -> Option<Self> {
+        (blocks.len() == CHUNK_VOLUME).then(|| Self::from_storage(x, y, z, blocks))
     }
 
-    pub fn generate(noise: &Simplex, x: i32, y: i32) -> Self {
-        let mut chunk = Chunk {
+    fn from_storage(x: i32, y: i32, z: i32, blocks: PaletteStorage) -> Self {
+        Self {
             x,
             y,
-            chunk_map: HashMap::new(),
+            z,
+            blocks,
+            light: None,
+            fluid_levels: BTreeMap::new(),
+            modified: false,
+        }
+    }
+
+    pub fn get_block(&self, x: i32, y: i32, z: i32) -> Option<Block> {
+        block_index(x, y, z).and_then(|index| Block::from_id(self.blocks.get(index)))
+    }
+
+    pub fn is_opaque(&self, x: i32, y: i32, z: i32) -> bool {
+        self.get_block(x, y, z)
+            .is_some_and(|block| block.is_opaque())
+    }
+
+    /// Places `block` at the local coordinates and returns the block it replaced.
+    /// Coordinates outside of the section are ignored.
+    pub fn set_block(&mut self, x: i32, y: i32, z: i32, block: Block) -> Option<Block> {
+        let previous = self.get_block(x, y, z);
+        self.insert(x, y, z, block.get_id());
+        if previous.map(|previous| previous.get_id()) != Some(block.get_id()) {
+            self.modified = true;
+            self.set_fluid_level(x, y, z, 0);
+        }
+        previous
+    }
+
+    /// Replaces the block at the local coordinates with air and returns it.
+    pub fn remove_block(&mut self, x: i32, y: i32, z: i32) -> Option<Block> {
+        let previous = self.get_block(x, y, z);
+        self.remove(x, y, z);
+        if previous.is_some() {
+            self.modified = true;
+            self.set_fluid_level(x, y, z, 0);
+        }
+        previous
+    }
+
+    /// Flow level of the fluid block at the local coordinates, 0 for sources
+    /// and for blocks that aren't fluid.
+    pub fn fluid_level(&self, x: i32, y: i32, z: i32) -> u8 {
+        block_index(x, y, z)
+            .and_then(|index| self.fluid_levels.get(&(index as u16)))
+            .copied()
+            .unwrap_or(0)
+    }
+
+    /// Sets the flow level of the fluid block at the local coordinates.
+    /// Coordinates outside of the section are ignored.
+    pub fn set_fluid_level(&mut self, x: i32, y: i32, z: i32, level: u8) {
+        let Some(index) = block_index(x, y, z) else {
+            return;
+        };
+        let previous = if level == 0 {
+            self.fluid_levels.remove(&(index as u16))
+        } else {
+            self.fluid_levels.insert(index as u16, level)
         };
+        self.modified |= previous.unwrap_or(0) != level;
+    }
 
-        chunk.generate_terrain(noise);
-        chunk.generate_caves(noise);
-        chunk.generate_block_types(noise);
-        chunk.generate_trees(noise);
+    /// The flow levels of the flowing fluid blocks, keyed by their index in
+    /// the block storage and in index order.
+    pub fn fluid_levels(&self) -> impl Iterator<Item = (u16, u8)> + '_ {
+        self.fluid_levels
+            .iter()
+            .map(|(&index, &level)| (index, level))
+    }
 
-        chunk
+    /// Restores saved flow levels, keyed by block index. Indices outside of
+    /// the section are ignored.
+    pub fn with_fluid_levels(mut self, levels: impl IntoIterator<Item = (u16, u8)>) -> Self {
+        self.fluid_levels = levels
+            .into_iter()
+            .filter(|&(index, level)| (index as usize) < CHUNK_VOLUME && level > 0)
+            .collect();
+        self
     }
 
-    fn generate_terrain(&mut self, noise: &Simplex) {
-        let mut height_x_offset: f64 = (self.x * CHUNK_SIZE) as f64 * HEIGHT_INCREMENT;
+    /// Iterates over every non-air block together with its local coordinates.
+    pub fn blocks(&self) -> impl Iterator<Item = ((i32, i32, i32), Block)> + '_ {
+        self.blocks.iter_non_air().filter_map(|(index, id)| {
+            Block::from_id(id).map(|block| (block_position(index), block))
+        })
+    }
 
-        for i in 0..CHUNK_SIZE {
-            let mut height_y_offset: f64 = (self.y * CHUNK_SIZE) as f64 * HEIGHT_INCREMENT;
+    /// The palette-compressed block ids of the section.
+    pub fn storage(&self) -> &PaletteStorage {
+        &self.blocks
+    }
 
-            for j in 0..CHUNK_SIZE {
-                let height_noise_value: f64 =
-                    noise.get([height_x_offset, height_y_offset, 0.0]).abs();
+    /// Returns `true` if blocks were edited since the section was generated,
+    /// loaded or last saved.
+    pub fn is_modified(&self) -> bool {
+        self.modified
+    }
 
-                let height: i32 = max(
-                    MIN_HEIGHT,
-                    (MAX_HEIGHT as f64 * HEIGHT_AMPLIFIER * height_noise_value).floor() as i32,
-                );
+    /// Returns `true` if the section holds nothing but air.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_uniform() && self.blocks.get(0) == AIR_ID
+    }
 
-                for k in 0..min(MAX_HEIGHT, height) {
-                    self.chunk_map
-                        .insert((i, j, k), BlockFactory::create_stone());
-                }
+    /// Returns `true` once the world has lit the section.
+    pub fn is_lit(&self) -> bool {
+        self.light.is_some()
+    }
+
+    /// Approximate heap usage of the block, light and fluid level storage in
+    /// bytes.
+    pub fn heap_size(&self) -> usize {
+        self.blocks.heap_size()
+            + self.light.as_ref().map_or(0, LightStorage::heap_size)
+            + self.fluid_levels.len() * size_of::<(u16, u8)>()
+    }
 
-                height_y_offset += HEIGHT_INCREMENT;
+    pub(super) fn light_storage(&self) -> Option<&LightStorage> {
+        self.light.as_ref()
+    }
+
+    pub(super) fn light_storage_mut(&mut self) -> Option<&mut LightStorage> {
+        self.light.as_mut()
+    }
+
+    pub(super) fn set_light_storage(&mut self, light: LightStorage) {
+        self.light = Some(light);
+    }
+
+    /// Generates a section with the default `GeneratorPipeline`.
+    pub fn generate(seed: u32, x: i32, y: i32, z: i32) -> Self {
+        Self::generate_decorated(seed, x, y, z).0
+    }
+
+    /// Like `generate`, but also returns the decoration blocks that landed
+    /// outside of the section, to be applied to the sections they landed in.
+    pub fn generate_decorated(seed: u32, x: i32, y: i32, z: i32) -> (Self, Vec<BlockWrite>) {
+        GeneratorPipeline::default().generate(seed, x, y, z)
+    }
+
+    /// Applies a decoration block placed by another section. Returns `true`
+    /// if the block changed; writes outside of the section are ignored.
+    pub fn apply_write(&mut self, write: &BlockWrite) -> bool {
+        let x = write.x - self.x * CHUNK_SIZE;
+        let y = write.y - self.y * CHUNK_SIZE;
+        let z = write.z - self.bottom();
+
+        match block_index(x, y, z) {
+            Some(index) if decoration::replaces(self.blocks.get(index), write.id) => {
+                self.blocks.set(index, write.id);
+                self.fluid_levels.remove(&(index as u16));
+                true
             }
+            _ => false,
+        }
+    }
+
+    // World z coordinate of the lowest block layer in this section
+    fn bottom(&self) -> i32 {
+        self.z * CHUNK_SIZE
+    }
 
-            height_x_offset += HEIGHT_INCREMENT;
+    // Writes outside of the section bounds are dropped
+    fn insert(&mut self, x: i32, y: i32, z: i32, id: BlockId) {
+        if let Some(index) = block_index(x, y, z) {
+            self.blocks.set(index, id);
         }
     }
 
-    fn generate_caves(&mut self, noise: &Simplex) {
-        let height_x_offset: f64 = (self.x * CHUNK_SIZE) as f64 * HEIGHT_INCREMENT;
-        let mut cave_x_offset: f64 = (self.x * CHUNK_SIZE) as f64 * CAVE_INCREMENT_XY;
+    fn remove(&mut self, x: i32, y: i32, z: i32) {
+        if let Some(index) = block_index(x, y, z) {
+            self.blocks.set(index, AIR_ID);
+        }
+    }
+}
 
-        for i in 0..CHUNK_SIZE {
-            let mut cave_y_offset: f64 = (self.y * CHUNK_SIZE) as f64 * CAVE_INCREMENT_XY;
-            let height_y_offset: f64 = (self.y * CHUNK_SIZE) as f64 * HEIGHT_INCREMENT;
+pub fn block_index(x: i32, y: i32, z: i32) -> Option<usize> {
+    if !(0..CHUNK_SIZE).contains(&x)
+        || !(0..CHUNK_SIZE).contains(&y)
+        || !(0..CHUNK_SIZE).contains(&z)
+    {
+        return None;
+    }
 
-            for j in 0..CHUNK_SIZE {
-                let height_noise_value: f64 =
-                    noise.get([height_x_offset, height_y_offset, 0.0]).abs();
-                let height: i32 = (MAX_HEIGHT as f64 * height_noise_value).floor() as i32;
+    Some((x + y * CHUNK_SIZE + z * CHUNK_SIZE * CHUNK_SIZE) as usize)
+}
 
-                for k in 0..height {
-                    let cave_z_offset = (k * CHUNK_SIZE) as f64 * CAVE_INCREMENT_Z;
+pub fn block_position(index: usize) -> (i32, i32, i32) {
+    let index = index as i32;
+    (
+        index % CHUNK_SIZE,
+        index / CHUNK_SIZE % CHUNK_SIZE,
+        index / (CHUNK_SIZE * CHUNK_SIZE),
+    )
+}
 
-                    let cave_noise_value: f64 = noise
-                        .get([cave_x_offset, cave_y_offset, cave_z_offset])
-                        .abs();
+const DEFAULT_CACHE_BUDGET: CacheBudget = CacheBudget::Chunks(16384);
 
-                    if k > 0 && cave_noise_value < 0.2 {
-                        self.dig_cross_section(i, j, k);
-                    }
-                }
+type EvictionHook = Box<dyn FnMut(Chunk) + Send>;
 
-                cave_y_offset += CAVE_INCREMENT_XY;
-            }
+pub struct ChunkProvider {
+    seed: u32,
+    pipeline: Arc<GeneratorPipeline>,
+    loaded_chunks: ChunkCache,
+    region_store: Option<RegionStore>,
+    eviction_hook: Option<EvictionHook>,
+    // Started on the first background request
+    generation_pool: Option<GenerationPool<(Chunk, Vec<BlockWrite>)>>,
+    requested_chunks: HashSet<ChunkKey>,
+    pending_writes: PendingWrites,
+    // Blocks of loaded chunks changed by the decoration of a neighbour
+    decorated_blocks: Vec<(i32, i32, i32)>,
+    // Chunks unloaded or evicted since `take_unloaded_chunks` was last called
+    unloaded_chunks: Vec<ChunkKey>,
+}
+
+impl ChunkProvider {
+    pub fn default() -> Self {
+        Self::new(0, GeneratorPreset::Default)
+    }
 
-            cave_x_offset += CAVE_INCREMENT_XY;
+    pub fn new(seed: u32, preset: GeneratorPreset) -> Self {
+        Self {
+            seed,
+            pipeline: Arc::new(preset.pipeline()),
+            loaded_chunks: ChunkCache::new(DEFAULT_CACHE_BUDGET, EvictionPolicy::Distance),
+            region_store: None,
+            eviction_hook: None,
+            generation_pool: None,
+            requested_chunks: HashSet::new(),
+            pending_writes: PendingWrites::new(),
+            decorated_blocks: Vec::new(),
+            unloaded_chunks: Vec::new(),
         }
     }
 
-    fn dig_cross_section(&mut self, x: i32, y: i32, z: i32) {
-        for iz in -CAVE_MULTIPLIER..=CAVE_MULTIPLIER {
-            for iy in -iz..=iz {
-                let dx: i32 = (iz * iz - iy * iy).isqrt();
-                for ix in -dx..=dx {
-                    let i = x + ix;
-                    let j = y + iy;
-                    let k = z + iz;
-                    if k > 0 && self.chunk_map.contains_key(&(i, j, k)) {
-                        self.chunk_map.remove(&(i, j, k));
-                    }
-                }
-            }
+    /// Like `new`, but chunks saved in `region_store` are loaded instead of
+    /// generated, and modified chunks are written back when unloaded.
+    pub fn with_region_store(
+        seed: u32,
+        preset: GeneratorPreset,
+        region_store: RegionStore,
+    ) -> Self {
+        Self {
+            region_store: Some(region_store),
+            ..Self::new(seed, preset)
         }
     }
 
-    fn generate_block_types(&mut self, noise: &Simplex) {
-        for block_entry in self
-            .chunk_map
-            .keys()
-            .copied()
-            .collect::<Vec<(i32, i32, i32)>>()
-        {
-            let x_coord: i32 = block_entry.0;
-            let y_coord: i32 = block_entry.1;
-            let z_coord: i32 = block_entry.2;
-
-            let temperature_x_offset: f64 =
-                (self.x * CHUNK_SIZE + x_coord) as f64 * TEMPERATURE_INCREMENT;
-            let temperature_y_offset: f64 =
-                (self.y * CHUNK_SIZE + y_coord) as f64 * TEMPERATURE_INCREMENT;
-            let humidity_x_offset: f64 =
-                (self.x * CHUNK_SIZE + x_coord) as f64 * HUMIDITY_INCREMENT;
-            let humidity_y_offset: f64 =
-                (self.y * CHUNK_SIZE + y_coord) as f64 * HUMIDITY_INCREMENT;
-
-            let block_x_offset: f64 = (self.x * CHUNK_SIZE + x_coord) as f64 * BLOCK_INCREMENT;
-            let block_y_offset: f64 = (self.y * CHUNK_SIZE + y_coord) as f64 * BLOCK_INCREMENT;
-            let block_z_offset: f64 = z_coord as f64 * BLOCK_INCREMENT;
-
-            let temperature_noise_value: f64 = noise
-                .get([temperature_x_offset, temperature_y_offset, 0.0])
-                .abs();
-            let humidity_noise_value: f64 =
-                noise.get([humidity_x_offset, humidity_y_offset, 0.0]).abs();
-            let block_noise_value: f64 = noise
-                .get([block_x_offset, block_y_offset, block_z_offset])
-                .abs();
-
-            let biome = biome::get_biome_by_params(temperature_noise_value, humidity_noise_value);
-
-            if z_coord > self.get_max_height(noise, x_coord, y_coord) - 5 {
-                if block_noise_value < 0.95 {
-                    self.chunk_map.remove(&(x_coord, y_coord, z_coord));
-
-                    match biome {
-                        Biome::Grass => {
-                            if self
-                                .chunk_map
-                                .contains_key(&(x_coord, y_coord, z_coord + 1))
-                            {
-                                self.chunk_map.insert(
-                                    (x_coord, y_coord, z_coord),
-                                    BlockFactory::create_dirt(),
-                                );
-                            } else {
-                                self.chunk_map.insert(
-                                    (x_coord, y_coord, z_coord),
-                                    BlockFactory::create_grass(),
-                                );
-                            }
-                        }
-                        Biome::Desert => {
-                            self.chunk_map
-                                .insert((x_coord, y_coord, z_coord), BlockFactory::create_sand());
-                        }
-                        Biome::Snow => {
-                            self.chunk_map
-                                .insert((x_coord, y_coord, z_coord), BlockFactory::create_snow());
-                        }
-                        _ => {
-                            self.chunk_map
-                                .insert((x_coord, y_coord, z_coord), BlockFactory::create_dirt());
-                        }
-                    }
-                }
-            } else if z_coord > 0 {
-                if block_noise_value < 0.2 {
-                    self.chunk_map
-                        .insert((x_coord, y_coord, z_coord), BlockFactory::create_coal());
-                }
-            }
+    /// Generates chunks with `pipeline` from now on. Chunks requested in the
+    /// background are dropped and need to be requested again; loaded chunks
+    /// are kept as they are.
+    pub fn set_pipeline(&mut self, pipeline: GeneratorPipeline) {
+        self.pipeline = Arc::new(pipeline);
+        self.generation_pool = None;
+        self.requested_chunks.clear();
+    }
+
+    pub fn set_cache_budget(&mut self, budget: CacheBudget) {
+        self.loaded_chunks.set_budget(budget);
+    }
+
+    pub fn set_eviction_policy(&mut self, policy: EvictionPolicy) {
+        self.loaded_chunks.set_policy(policy);
+    }
+
+    /// Called with every chunk evicted from the cache, after it was saved.
+    pub fn set_eviction_hook(&mut self, hook: impl FnMut(Chunk) + Send + 'static) {
+        self.eviction_hook = Some(Box::new(hook));
+    }
+
+    /// Chunk the player is in, used by `EvictionPolicy::Distance`.
+    /// Background requests nearest to it are generated first.
+    pub fn set_focus(&mut self, x: i32, y: i32, z: i32) {
+        self.loaded_chunks.set_focus((x, y, z));
+        if let Some(generation_pool) = &self.generation_pool {
+            generation_pool.set_focus((x, y, z));
         }
     }
 
-    fn get_max_height(&self, noise: &Simplex, x: i32, y: i32) -> i32 {
-        let height_x_offset: f64 = (self.x * CHUNK_SIZE + x) as f64 * HEIGHT_INCREMENT;
-        let height_y_offset: f64 = (self.y * CHUNK_SIZE + y) as f64 * HEIGHT_INCREMENT;
+    pub fn cache_metrics(&self) -> CacheMetrics {
+        self.loaded_chunks.metrics()
+    }
 
-        let height_noise_value: f64 = noise.get([height_x_offset, height_y_offset, 0.0]).abs();
-        let height: i32 = (MAX_HEIGHT as f64 * height_noise_value).floor() as i32;
+    pub fn loaded_chunk_count(&self) -> usize {
+        self.loaded_chunks.len()
+    }
 
-        for z in height..=0 {
-            if self.chunk_map.contains_key(&(x, y, z)) {
-                return z;
-            }
+    /// Biome of the world column at the block coordinates, or `None` if the
+    /// world has no biomes.
+    pub fn biome_at(&self, x: i32, y: i32) -> Option<Biome> {
+        self.pipeline.biome_at(self.seed, x, y)
+    }
+
+    pub fn get_chunk(&mut self, x: i32, y: i32, z: i32) -> &Chunk {
+        self.get_chunk_mut(x, y, z)
+    }
+
+    pub fn get_chunk_mut(&mut self, x: i32, y: i32, z: i32) -> &mut Chunk {
+        if self.loaded_chunks.touch(&(x, y, z)) {
+            return self.loaded_chunks.get_mut(&(x, y, z)).unwrap();
         }
 
-        0
+        let (chunk, overflow) = self.pipeline.generate(self.seed, x, y, z);
+        self.load_generated(chunk, overflow)
     }
 
-    fn generate_trees(&mut self, noise: &Simplex) {
-        let mut tree_x_offset: f64 = (self.x * CHUNK_SIZE) as f64 * TREE_TRUNK_INCREMENT;
+    /// Queues a chunk for generation on the worker threads, unless it's
+    /// already loaded or requested. See `receive_chunks`.
+    pub fn request_chunk(&mut self, x: i32, y: i32, z: i32) {
+        if self.requested_chunks.contains(&(x, y, z)) || self.loaded_chunks.touch(&(x, y, z)) {
+            return;
+        }
 
-        for i in 0..CHUNK_SIZE {
-            let mut tree_y_offset: f64 = (self.y * CHUNK_SIZE) as f64 * TREE_TRUNK_INCREMENT;
+        self.requested_chunks.insert((x, y, z));
+        let (seed, pipeline) = (self.seed, self.pipeline.clone());
+        self.generation_pool
+            .get_or_insert_with(|| {
+                GenerationPool::new(generation::default_worker_count(), move |x, y, z| {
+                    pipeline.generate(seed, x, y, z)
+                })
+            })
+            .request(x, y, z);
+    }
 
-            for j in 0..CHUNK_SIZE {
-                let tree_trunk_noise_value: f64 =
-                    noise.get([tree_x_offset, tree_y_offset, 0.0]).abs();
+    /// Drops the queued requests for which `keep` returns `false`.
+    pub fn cancel_requests(&mut self, mut keep: impl FnMut(&ChunkKey) -> bool) {
+        if let Some(generation_pool) = &self.generation_pool {
+            generation_pool.retain(&mut keep);
+        }
+        self.requested_chunks.retain(keep);
+    }
 
-                if tree_trunk_noise_value > 0.5 && tree_trunk_noise_value < 0.50425 {
-                    self.generate_tree(noise, i, j);
-                }
+    /// Chunks requested but not received yet.
+    pub fn requested_chunk_count(&self) -> usize {
+        self.requested_chunks.len()
+    }
 
-                tree_y_offset += TREE_TRUNK_INCREMENT;
+    /// Loads the requested chunks that finished generating, without blocking,
+    /// and returns their keys.
+    pub fn receive_chunks(&mut self) -> Vec<ChunkKey> {
+        let Some(generation_pool) = &self.generation_pool else {
+            return Vec::new();
+        };
+        let generated: Vec<_> = std::iter::from_fn(|| generation_pool.try_recv()).collect();
+
+        let mut received = Vec::new();
+        for (chunk, overflow) in generated {
+            let key = (chunk.x, chunk.y, chunk.z);
+            // Cancelled, or loaded synchronously in the meantime
+            if !self.requested_chunks.remove(&key) || self.loaded_chunks.contains(&key) {
+                continue;
             }
 
-            tree_x_offset += TREE_TRUNK_INCREMENT;
+            self.load_generated(chunk, overflow);
+            received.push(key);
         }
+
+        received
     }
 
-    fn generate_tree(&mut self, noise: &Simplex, x: i32, y: i32) {
-        let mut height = self.get_max_height(noise, x, y) + 1;
+    pub fn get_loaded_chunk(&self, x: i32, y: i32, z: i32) -> Option<&Chunk> {
+        self.loaded_chunks.peek(&(x, y, z))
+    }
+
+    /// Like `get_loaded_chunk`, but for changing the chunk.
+    pub fn get_loaded_chunk_mut(&mut self, x: i32, y: i32, z: i32) -> Option<&mut Chunk> {
+        self.loaded_chunks.get_mut(&(x, y, z))
+    }
 
-        for _ in 0..4 {
-            self.chunk_map
-                .insert((x, y, height), BlockFactory::create_tree_log());
-            height += 1;
+    pub fn unload_chunk(&mut self, x: i32, y: i32, z: i32) {
+        if let Some(chunk) = self.loaded_chunks.remove(&(x, y, z)) {
+            self.save_if_modified(&chunk);
+            self.pending_writes.remove_source((x, y, z));
+            self.unloaded_chunks.push((x, y, z));
         }
+    }
 
-        let tree_branch_x_offset: f64 = (self.x * CHUNK_SIZE + x) as f64 * TREE_BRANCH_INCREMENT;
-        let tree_branch_y_offset: f64 = (self.y * CHUNK_SIZE + y) as f64 * TREE_BRANCH_INCREMENT;
-        let mut tree_branch_z_offset: f64 = height as f64 * TREE_BRANCH_INCREMENT;
-
-        let mut tree_branch_noise_value: f64 = noise
-            .get([
-                tree_branch_x_offset,
-                tree_branch_y_offset,
-                tree_branch_z_offset,
-            ])
-            .abs();
-
-        while tree_branch_noise_value < 0.4 {
-            if self.chunk_map.contains_key(&(x, y, height)) {
-                self.chunk_map.remove(&(x, y, height));
-            }
+    /// Returns the world coordinates of the blocks of loaded chunks that the
+    /// decoration of a neighbour changed since the last call.
+    pub fn take_decorated_blocks(&mut self) -> Vec<(i32, i32, i32)> {
+        std::mem::take(&mut self.decorated_blocks)
+    }
 
-            self.chunk_map
-                .insert((x, y, height), BlockFactory::create_tree_log());
-
-            let left_noise: f64 = noise
-                .get([
-                    tree_branch_x_offset + TREE_BRANCH_INCREMENT,
-                    tree_branch_y_offset,
-                    tree_branch_z_offset,
-                ])
-                .abs();
-            let right_noise: f64 = noise
-                .get([
-                    tree_branch_x_offset - TREE_BRANCH_INCREMENT,
-                    tree_branch_y_offset,
-                    tree_branch_z_offset,
-                ])
-                .abs();
-            let up_noise: f64 = noise
-                .get([
-                    tree_branch_x_offset,
-                    tree_branch_y_offset + TREE_BRANCH_INCREMENT,
-                    tree_branch_z_offset,
-                ])
-                .abs();
-            let down_noise: f64 = noise
-                .get([
-                    tree_branch_x_offset,
-                    tree_branch_y_offset - TREE_BRANCH_INCREMENT,
-                    tree_branch_z_offset,
-                ])
-                .abs();
-
-            if left_noise < 0.4 {
-                self.generate_branch(noise, x - 1, y, height);
-            }
+    /// Returns the keys of the chunks unloaded or evicted from the cache
+    /// since the last call.
+    pub fn take_unloaded_chunks(&mut self) -> Vec<ChunkKey> {
+        std::mem::take(&mut self.unloaded_chunks)
+    }
 
-            if right_noise < 0.4 {
-                self.generate_branch(noise, x + 1, y, height);
-            }
+    pub fn unload_column(&mut self, x: i32, y: i32) {
+        let keys: Vec<_> = self
+            .loaded_chunks
+            .keys()
+            .filter(|&&(chunk_x, chunk_y, _)| chunk_x == x && chunk_y == y)
+            .copied()
+            .collect();
 
-            if down_noise < 0.4 {
-                self.generate_branch(noise, x, y - 1, height);
-            }
+        for (x, y, z) in keys {
+            self.unload_chunk(x, y, z);
+        }
+    }
+
+    /// Writes every modified chunk to the region store without unloading it.
+    pub fn save_all(&mut self) {
+        let Some(region_store) = &self.region_store else {
+            return;
+        };
 
-            if up_noise < 0.4 {
-                self.generate_branch(noise, x, y + 1, height);
+        for chunk in self.loaded_chunks.values_mut() {
+            if chunk.modified {
+                match save_chunk(
+                    region_store,
+                    &self.pipeline,
+                    &self.pending_writes,
+                    self.seed,
+                    chunk,
+                ) {
+                    Ok(()) => chunk.modified = false,
+                    Err(error) => log::error!("Can't save chunk: {error:#}"),
+                }
             }
+        }
+    }
 
-            height += 1;
-            tree_branch_z_offset += TREE_BRANCH_INCREMENT;
-            tree_branch_noise_value = noise
-                .get([
-                    tree_branch_x_offset,
-                    tree_branch_y_offset,
-                    tree_branch_z_offset,
-                ])
-                .abs();
+    // Combines a generated chunk with the decoration of its neighbours and its
+    // saved state, and hands its own overflow to them
+    fn load_generated(&mut self, mut chunk: Chunk, overflow: Vec<BlockWrite>) -> &mut Chunk {
+        let key = (chunk.x, chunk.y, chunk.z);
+        // Saved blocks already hold the decoration that reached them, so it
+        // goes underneath, where player edits can override it
+        for write in self.pending_writes.for_target(key) {
+            chunk.apply_write(write);
         }
+        let chunk = self.restore_saved(chunk);
 
-        self.generate_leaves(noise, x, y, height - 1, 2);
-    }
-
-    fn generate_branch(&mut self, noise: &Simplex, x: i32, y: i32, z: i32) {
-        self.chunk_map
-            .insert((x, y, y), BlockFactory::create_tree_log());
-        self.generate_leaves(noise, x, y, z, 1);
-    }
-
-    fn generate_leaves(
-        &mut self,
-        noise: &Simplex,
-        center_x: i32,
-        center_y: i32,
-        center_z: i32,
-        radius: i32,
-    ) {
-        for iz in -radius..=radius {
-            let r: i32 = radius - iz.abs();
-            for iy in -r..=r {
-                let dx: i32 = (r * r - iy * iy).abs().isqrt();
-                for ix in -dx..=dx {
-                    let i = center_x + ix;
-                    let j = center_y + iy;
-                    let k = center_z + iz;
-
-                    if !self.chunk_map.contains_key(&(i, j, k)) {
-                        let tree_leaf_x_offset: f64 =
-                            (self.x * CHUNK_SIZE + i) as f64 * TREE_LEAF_INCREMENT;
-                        let tree_leaf_y_offset: f64 =
-                            (self.y * CHUNK_SIZE + j) as f64 * TREE_LEAF_INCREMENT;
-                        let tree_leaf_z_offset: f64 = k as f64 * TREE_LEAF_INCREMENT;
-
-                        let tree_leaf_noise: f64 = noise
-                            .get([tree_leaf_x_offset, tree_leaf_y_offset, tree_leaf_z_offset])
-                            .abs();
-
-                        if tree_leaf_noise < 0.85 {
-                            self.chunk_map
-                                .insert((i, j, k), BlockFactory::create_tree_leaf());
-                        }
-                    }
+        for write in &overflow {
+            let (target, _) = write.target();
+            if let Some(target_chunk) = self.loaded_chunks.get_mut(&target) {
+                if target_chunk.apply_write(write) {
+                    self.decorated_blocks.push((write.x, write.y, write.z));
                 }
             }
         }
+        self.pending_writes.insert(key, overflow);
+
+        self.insert(chunk)
     }
-}
 
-pub struct ChunkProvider {
-    seed: u32,
-    noise: Simplex,
-    loaded_chunks: HashMap<(i32, i32), Chunk>,
-}
+    fn insert(&mut self, chunk: Chunk) -> &mut Chunk {
+        for chunk in self.loaded_chunks.evict() {
+            self.save_if_modified(&chunk);
+            self.pending_writes
+                .remove_source((chunk.x, chunk.y, chunk.z));
+            self.unloaded_chunks.push((chunk.x, chunk.y, chunk.z));
+            if let Some(hook) = &mut self.eviction_hook {
+                hook(chunk);
+            }
+        }
 
-impl ChunkProvider {
-    pub fn default() -> Self {
-        Self::new(0)
+        self.loaded_chunks.insert(chunk)
     }
 
-    pub fn new(seed: u32) -> Self {
-        Self {
-            seed,
-            noise: Simplex::new(seed),
-            loaded_chunks: HashMap::new(),
-        }
+    // Replaces freshly generated terrain with its saved state, if any
+    fn restore_saved(&self, generated: Chunk) -> Chunk {
+        let Some(region_store) = &self.region_store else {
+            return generated;
+        };
+
+        let (x, y, z) = (generated.x, generated.y, generated.z);
+        let mut generated = Some(generated);
+        let version = self.pipeline.version();
+        load_chunk(region_store, self.seed, version, x, y, z, || {
+            generated.take().unwrap()
+        })
+        .unwrap_or_else(|error| {
+            log::error!("Can't load chunk, generating it instead: {error:#}");
+            generated
+                .take()
+                .unwrap_or_else(|| self.pipeline.generate(self.seed, x, y, z).0)
+        })
     }
 
-    pub fn get_chunk(&mut self, x: i32, y: i32) -> &Chunk {
-        let chunk = self
-            .loaded_chunks
-            .entry((x, y))
-            .or_insert_with(|| Chunk::generate(&self.noise, x, y));
+    fn save_if_modified(&self, chunk: &Chunk) {
+        if let (Some(region_store), true) = (&self.region_store, chunk.modified) {
+            if let Err(error) = save_chunk(
+                region_store,
+                &self.pipeline,
+                &self.pending_writes,
+                self.seed,
+                chunk,
+            ) {
+                log::error!("Can't save chunk: {error:#}");
+            }
+        }
+    }
+}
 
-        chunk
+fn load_chunk(
+    region_store: &RegionStore,
+    seed: u32,
+    generator_version: u32,
+    x: i32,
+    y: i32,
+    z: i32,
+    generate: impl FnOnce() -> Chunk,
+) -> anyhow::Result<Chunk> {
+    match region_store.load_record(x, y, z)? {
+        Some(ChunkRecord::Blocks(chunk)) => Ok(chunk),
+        Some(ChunkRecord::Delta(delta)) => {
+            if !delta.matches(generator_version, seed) {
+                bail!(
+                    "Edits of chunk ({x}, {y}, {z}) were saved for generator version {} with seed {}, \
+                     but this is version {generator_version} with seed {seed}",
+                    delta.generator_version,
+                    delta.seed
+                );
+            }
+            Ok(delta.apply(&generate()))
+        }
+        None => Ok(generate()),
     }
+}
 
-    pub fn unload_chunk(&mut self, x: i32, y: i32) {
-        self.loaded_chunks.remove(&(x, y));
+// Deltas are taken against the terrain as it loads, with the decoration
+// queued by loaded neighbours, so that only player edits are saved
+fn save_chunk(
+    region_store: &RegionStore,
+    pipeline: &GeneratorPipeline,
+    pending_writes: &PendingWrites,
+    seed: u32,
+    chunk: &Chunk,
+) -> anyhow::Result<()> {
+    match region_store.mode() {
+        SaveMode::Full => region_store.save_chunk(chunk),
+        SaveMode::Deltas => {
+            let (mut generated, _) = pipeline.generate(seed, chunk.x, chunk.y, chunk.z);
+            for write in pending_writes.for_target((chunk.x, chunk.y, chunk.z)) {
+                generated.apply_write(write);
+            }
+            let delta = ChunkDelta::between(&generated, chunk, pipeline.version(), seed);
+            region_store.save_delta(chunk.x, chunk.y, chunk.z, &delta)
+        }
     }
 }
@@ -0,0 +1,100 @@
+use super::block::{Block, BlockId};
+use super::cache::ChunkKey;
+use super::split_coords;
+use std::collections::HashMap;
+
+/// A block placed by a chunk's decoration, in world coordinates.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct BlockWrite {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub id: BlockId,
+}
+
+impl BlockWrite {
+    /// The chunk the block lands in and its coordinates inside that chunk.
+    pub fn target(&self) -> (ChunkKey, (i32, i32, i32)) {
+        split_coords(self.x, self.y, self.z)
+    }
+}
+
+/// Decides whether a decoration block may replace `existing`.
+///
+/// Decorations only fill air, and solid parts of a structure push through
+/// see-through ones, so logs win over leaves. Because the outcome doesn't
+/// depend on which write comes first, structures overlapping each other or a
+/// chunk border come out the same whatever order chunks are generated in.
+pub fn replaces(existing: BlockId, id: BlockId) -> bool {
+    match Block::from_id(existing) {
+        None => true,
+        Some(existing) => {
+            !existing.is_opaque() && Block::from_id(id).is_some_and(|block| block.is_opaque())
+        }
+    }
+}
+
+/// Decoration blocks that landed outside of the chunk that placed them,
+/// queued until the chunk they land in is generated.
+///
+/// Writes are grouped by the chunk that placed them, so that regenerating a
+/// chunk replaces its writes and unloading it drops them.
+#[derive(Default)]
+pub struct PendingWrites {
+    by_target: HashMap<ChunkKey, HashMap<ChunkKey, Vec<BlockWrite>>>,
+    targets_by_source: HashMap<ChunkKey, Vec<ChunkKey>>,
+}
+
+impl PendingWrites {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the writes `source` placed outside of itself, replacing the
+    /// ones it placed before.
+    pub fn insert(&mut self, source: ChunkKey, writes: Vec<BlockWrite>) {
+        self.remove_source(source);
+
+        let mut targets = Vec::new();
+        for write in writes {
+            let (target, _) = write.target();
+            let by_source = self.by_target.entry(target).or_default();
+            if !by_source.contains_key(&source) {
+                targets.push(target);
+            }
+            by_source.entry(source).or_default().push(write);
+        }
+
+        if !targets.is_empty() {
+            self.targets_by_source.insert(source, targets);
+        }
+    }
+
+    pub fn remove_source(&mut self, source: ChunkKey) {
+        for target in self.targets_by_source.remove(&source).unwrap_or_default() {
+            if let Some(by_source) = self.by_target.get_mut(&target) {
+                by_source.remove(&source);
+                if by_source.is_empty() {
+                    self.by_target.remove(&target);
+                }
+            }
+        }
+    }
+
+    /// The writes landing in `target`.
+    pub fn for_target(&self, target: ChunkKey) -> impl Iterator<Item = &BlockWrite> {
+        self.by_target
+            .get(&target)
+            .into_iter()
+            .flat_map(|by_source| by_source.values().flatten())
+    }
+
+    /// Number of chunks with queued writes.
+    pub fn len(&self) -> usize {
+        self.by_target.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.by_target.is_empty()
+    }
+}
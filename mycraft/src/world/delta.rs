@@ -0,0 +1,84 @@
+use super::block::BlockId;
+use super::chunk::Chunk;
+
+/// The blocks of a chunk that differ from its freshly generated terrain,
+/// along with the flow levels of its fluid.
+///
+/// Deltas only make sense on top of the terrain they were taken from, so
+/// they remember the seed and generator version that produced it.
+#[derive(Debug, PartialEq)]
+pub struct ChunkDelta {
+    pub generator_version: u32,
+    pub seed: u32,
+    changes: Vec<(u16, BlockId)>,
+    fluid_levels: Vec<(u16, u8)>,
+}
+
+impl ChunkDelta {
+    pub fn new(generator_version: u32, seed: u32, changes: Vec<(u16, BlockId)>) -> Self {
+        Self {
+            generator_version,
+            seed,
+            changes,
+            fluid_levels: Vec::new(),
+        }
+    }
+
+    /// Adds the flow levels of the flowing fluid blocks, keyed by their index
+    /// in the chunk storage. Generated terrain has none.
+    pub fn with_fluid_levels(mut self, fluid_levels: Vec<(u16, u8)>) -> Self {
+        self.fluid_levels = fluid_levels;
+        self
+    }
+
+    /// Collects the blocks of `edited` that differ from `generated`, which
+    /// must be the same chunk generated from `seed` by the generator with
+    /// `generator_version`, see `GeneratorPipeline::version`.
+    pub fn between(generated: &Chunk, edited: &Chunk, generator_version: u32, seed: u32) -> Self {
+        let changes = generated
+            .storage()
+            .iter()
+            .zip(edited.storage().iter())
+            .enumerate()
+            .filter(|(_, (generated, edited))| generated != edited)
+            .map(|(index, (_, edited))| (index as u16, edited))
+            .collect();
+
+        Self::new(generator_version, seed, changes)
+            .with_fluid_levels(edited.fluid_levels().collect())
+    }
+
+    /// Changed block ids keyed by their index in the chunk storage.
+    pub fn changes(&self) -> &[(u16, BlockId)] {
+        &self.changes
+    }
+
+    pub fn fluid_levels(&self) -> &[(u16, u8)] {
+        &self.fluid_levels
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.fluid_levels.is_empty()
+    }
+
+    /// Returns `true` if the delta was taken from terrain generated by the
+    /// generator with `generator_version` from `seed`.
+    pub fn matches(&self, generator_version: u32, seed: u32) -> bool {
+        self.generator_version == generator_version && self.seed == seed
+    }
+
+    /// Replays the changes on top of a freshly generated chunk. Indices
+    /// outside of the chunk are ignored.
+    pub fn apply(&self, generated: &Chunk) -> Chunk {
+        let mut blocks = generated.storage().clone();
+        for &(index, id) in &self.changes {
+            if (index as usize) < blocks.len() {
+                blocks.set(index as usize, id);
+            }
+        }
+
+        Chunk::from_blocks(generated.x, generated.y, generated.z, blocks)
+            .expect("Chunk storage changed size")
+            .with_fluid_levels(self.fluid_levels.iter().copied())
+    }
+}
@@ -0,0 +1,301 @@
+use super::block::Block;
+use super::cache::ChunkKey;
+use super::chunk::block_position;
+use super::{split_coords, World, CHUNK_SIZE};
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// Caps the fluid updates done in one tick, so that a flood spreads over
+/// several ticks instead of stalling one. Updates over the cap wait for the
+/// next tick.
+pub const MAX_UPDATES_PER_TICK: usize = 2048;
+
+const SIDES: [(i32, i32, i32); 4] = [(1, 0, 0), (-1, 0, 0), (0, 1, 0), (0, -1, 0)];
+const NEIGHBOURS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+type Position = (i32, i32, i32);
+
+/// Pending updates of the fluid blocks in a world, advanced by
+/// [`World::tick`].
+///
+/// Flow levels are kept and saved with the chunks, see
+/// [`World::fluid_level`]. Updates of fluid in sections that get unloaded
+/// are dropped; the flowing fluid of a section wakes up again when it is
+/// loaded, along with fluid that was waiting for it to flow into.
+#[derive(Default)]
+pub struct FluidSimulation {
+    tick: u64,
+    // Blocks that changed or saw a neighbour change since the last tick
+    woken: HashSet<Position>,
+    // Updates by the tick they are due in
+    scheduled: BTreeMap<u64, Vec<Position>>,
+    queued: HashSet<Position>,
+    // Fluid that can't flow on until the section it flows into is loaded
+    waiting: HashMap<ChunkKey, Vec<Position>>,
+}
+
+impl FluidSimulation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Ticks run since the simulation started.
+    pub fn tick_count(&self) -> u64 {
+        self.tick
+    }
+
+    /// Returns `true` if no fluid is waiting to flow.
+    pub fn is_settled(&self) -> bool {
+        self.woken.is_empty() && self.queued.is_empty()
+    }
+
+    /// Records that the block at the world coordinates was replaced, waking
+    /// it and its neighbours.
+    pub(super) fn block_changed(&mut self, position: Position) {
+        self.wake_around(position);
+    }
+
+    fn wake_around(&mut self, (x, y, z): Position) {
+        self.woken.insert((x, y, z));
+        for (dx, dy, dz) in NEIGHBOURS {
+            self.woken.insert((x + dx, y + dy, z + dz));
+        }
+    }
+
+    // Drops the updates of fluid in the sections
+    fn forget(&mut self, sections: &HashSet<ChunkKey>) {
+        let in_sections = |(x, y, z): &Position| sections.contains(&split_coords(*x, *y, *z).0);
+        self.woken.retain(|position| !in_sections(position));
+        self.queued.retain(|position| !in_sections(position));
+        for positions in self.scheduled.values_mut() {
+            positions.retain(|position| !in_sections(position));
+        }
+        self.waiting.retain(|_, positions| {
+            positions.retain(|position| !in_sections(position));
+            !positions.is_empty()
+        });
+    }
+
+    fn schedule(&mut self, position: Position, delay: u32) {
+        if self.queued.insert(position) {
+            self.scheduled
+                .entry(self.tick + delay as u64)
+                .or_default()
+                .push(position);
+        }
+    }
+
+    // Takes the next update that is due, if any
+    fn next_due(&mut self) -> Option<Position> {
+        loop {
+            let mut entry = self.scheduled.first_entry()?;
+            if *entry.key() > self.tick {
+                return None;
+            }
+            match entry.get_mut().pop() {
+                Some(position) => {
+                    self.queued.remove(&position);
+                    return Some(position);
+                }
+                None => {
+                    entry.remove();
+                }
+            }
+        }
+    }
+}
+
+impl World {
+    /// Advances the fluid simulation by one tick.
+    ///
+    /// Fluid that was woken by an edit flows once the tick rate of its block
+    /// has passed. Changes mark their chunks dirty like any other edit, so
+    /// meshes are rebuilt once for everything that flowed between two calls
+    /// to [`World::take_dirty_chunks`].
+    pub fn tick(&mut self) {
+        self.fluids.tick += 1;
+
+        for position in std::mem::take(&mut self.fluids.woken) {
+            let (x, y, z) = position;
+            if let Some(fluid) = self.get_block(x, y, z).and_then(|block| block.get_fluid()) {
+                self.fluids.schedule(position, fluid.tick_rate);
+            }
+        }
+
+        for _ in 0..MAX_UPDATES_PER_TICK {
+            let Some(position) = self.fluids.next_due() else {
+                break;
+            };
+            self.update_fluid(position);
+        }
+    }
+
+    pub fn fluids(&self) -> &FluidSimulation {
+        &self.fluids
+    }
+
+    /// Flow level of the fluid block at the world coordinates, 0 for sources.
+    /// `None` if there's no fluid there.
+    ///
+    /// Levels count the blocks between a flowing fluid block and its source.
+    /// Fluid blocks without a level, like generated water or fluid placed by
+    /// hand, are sources.
+    pub fn fluid_level(&self, world_x: i32, world_y: i32, world_z: i32) -> Option<u8> {
+        let block = self.get_block(world_x, world_y, world_z)?;
+        block
+            .is_fluid()
+            .then(|| self.level((world_x, world_y, world_z)))
+    }
+
+    /// Drops the fluid updates of the sections that were unloaded, and wakes
+    /// the flowing fluid of newly loaded sections along with the fluid that
+    /// was waiting for them.
+    pub(super) fn fluids_loaded(&mut self, keys: &[ChunkKey]) {
+        let unloaded: HashSet<_> = self
+            .chunk_provider
+            .take_unloaded_chunks()
+            .into_iter()
+            .collect();
+        if !unloaded.is_empty() {
+            self.fluids.forget(&unloaded);
+        }
+
+        for &(x, y, z) in keys {
+            if let Some(waiting) = self.fluids.waiting.remove(&(x, y, z)) {
+                self.fluids.woken.extend(waiting);
+            }
+            let Some(chunk) = self.get_chunk(x, y, z) else {
+                continue;
+            };
+            let origin = (x * CHUNK_SIZE, y * CHUNK_SIZE, z * CHUNK_SIZE);
+            let flowing: Vec<_> = chunk
+                .fluid_levels()
+                .map(|(index, _)| {
+                    let (i, j, k) = block_position(index as usize);
+                    (origin.0 + i, origin.1 + j, origin.2 + k)
+                })
+                .collect();
+            self.fluids.woken.extend(flowing);
+        }
+    }
+
+    fn level(&self, (x, y, z): Position) -> u8 {
+        let (chunk, (i, j, k)) = split_coords(x, y, z);
+        self.get_chunk(chunk.0, chunk.1, chunk.2)
+            .map_or(0, |chunk| chunk.fluid_level(i, j, k))
+    }
+
+    fn set_level(&mut self, (x, y, z): Position, level: u8) {
+        let (chunk, (i, j, k)) = split_coords(x, y, z);
+        if let Some(chunk) = self
+            .chunk_provider
+            .get_loaded_chunk_mut(chunk.0, chunk.1, chunk.2)
+        {
+            chunk.set_fluid_level(i, j, k, level);
+        }
+    }
+
+    fn update_fluid(&mut self, (x, y, z): Position) {
+        let Some(block) = self.get_block(x, y, z) else {
+            return;
+        };
+        let Some(fluid) = block.get_fluid() else {
+            return;
+        };
+
+        // Touching another fluid may turn this one into a solid block
+        for (dx, dy, dz) in NEIGHBOURS {
+            let Some(neighbour) = self.get_block(x + dx, y + dy, z + dz) else {
+                continue;
+            };
+            if let Some(product) = fluid.reactions.get(neighbour.get_name()) {
+                let product = Block::from_name(product).expect("Reaction products are registered");
+                self.set_block(x, y, z, product);
+                return;
+            }
+        }
+
+        // Flowing fluid takes its level from the neighbour feeding it, and
+        // dries up without one
+        let mut level = self.level((x, y, z));
+        if level > 0 {
+            let fed_level = if self.is_fluid_at((x, y, z + 1), block) {
+                Some(1)
+            } else {
+                SIDES
+                    .iter()
+                    .map(|(dx, dy, _)| (x + dx, y + dy, z))
+                    .filter(|&side| self.is_fluid_at(side, block))
+                    .map(|side| self.level(side).saturating_add(1))
+                    .min()
+            };
+            match fed_level {
+                Some(fed_level) if fed_level <= fluid.flow_distance => {
+                    if fed_level != level {
+                        level = fed_level;
+                        self.set_level((x, y, z), level);
+                        self.fluids.wake_around((x, y, z));
+                    }
+                }
+                _ => {
+                    self.remove_block(x, y, z);
+                    return;
+                }
+            }
+        }
+
+        // Fluid falls before it spreads sideways
+        if self.flow_into((x, y, z), (x, y, z - 1), block, 1) {
+            return;
+        }
+        if level < fluid.flow_distance {
+            for (dx, dy, _) in SIDES {
+                self.flow_into((x, y, z), (x + dx, y + dy, z), block, level + 1);
+            }
+        }
+    }
+
+    // Lets fluid flow from `from` into a block at the given level. Returns
+    // `true` if the fluid can go there, that is if the block is air or fluid
+    // of the same kind, or if its chunk isn't loaded to tell. Fluid next to a
+    // chunk that isn't loaded waits for it, and is woken when it loads.
+    fn flow_into(&mut self, from: Position, position: Position, fluid: Block, level: u8) -> bool {
+        let (x, y, z) = position;
+        let (chunk, _) = split_coords(x, y, z);
+        if self.get_chunk(chunk.0, chunk.1, chunk.2).is_none() {
+            let waiting = self.fluids.waiting.entry(chunk).or_default();
+            if !waiting.contains(&from) {
+                waiting.push(from);
+            }
+            return true;
+        }
+
+        match self.get_block(x, y, z) {
+            None => {
+                self.set_block(x, y, z, fluid);
+                self.set_level(position, level);
+                true
+            }
+            Some(block) if block.get_id() == fluid.get_id() => {
+                let current = self.level(position);
+                if current > level {
+                    self.set_level(position, level);
+                    self.fluids.wake_around(position);
+                }
+                true
+            }
+            Some(_) => false,
+        }
+    }
+
+    fn is_fluid_at(&self, (x, y, z): Position, fluid: Block) -> bool {
+        self.get_block(x, y, z)
+            .is_some_and(|block| block.get_id() == fluid.get_id())
+    }
+}
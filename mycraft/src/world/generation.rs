@@ -0,0 +1,173 @@
+use super::cache::{distance_squared, ChunkKey};
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Condvar, Mutex};
+use std::thread::JoinHandle;
+
+type Generate<T> = dyn Fn(i32, i32, i32) -> T + Send + Sync;
+
+/// Worker threads generating chunks in the background.
+///
+/// Requested chunks are generated nearest to the focus first and handed back
+/// through a channel, see `try_recv`. `T` is whatever the generator returns
+/// for a chunk.
+pub struct GenerationPool<T> {
+    shared: Arc<Shared>,
+    results: Receiver<T>,
+    workers: Vec<JoinHandle<()>>,
+}
+
+struct Shared {
+    queue: Mutex<Queue>,
+    available: Condvar,
+}
+
+struct Queue {
+    jobs: BinaryHeap<Job>,
+    focus: ChunkKey,
+    shutdown: bool,
+}
+
+#[derive(PartialEq, Eq)]
+struct Job {
+    distance: u64,
+    key: ChunkKey,
+}
+
+// Nearest job first in the max-heap
+impl Ord for Job {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .distance
+            .cmp(&self.distance)
+            .then_with(|| other.key.cmp(&self.key))
+    }
+}
+
+impl PartialOrd for Job {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl<T: Send + 'static> GenerationPool<T> {
+    pub fn new(
+        worker_count: usize,
+        generate: impl Fn(i32, i32, i32) -> T + Send + Sync + 'static,
+    ) -> Self {
+        let shared = Arc::new(Shared {
+            queue: Mutex::new(Queue {
+                jobs: BinaryHeap::new(),
+                focus: (0, 0, 0),
+                shutdown: false,
+            }),
+            available: Condvar::new(),
+        });
+        let generate: Arc<Generate<T>> = Arc::new(generate);
+        let (sender, results) = mpsc::channel();
+
+        let workers = (0..worker_count.max(1))
+            .map(|index| {
+                let shared = shared.clone();
+                let generate = generate.clone();
+                let sender = sender.clone();
+                std::thread::Builder::new()
+                    .name(format!("chunk-generation-{index}"))
+                    .spawn(move || run_worker(&shared, &*generate, &sender))
+                    .expect("Can't spawn chunk generation thread!")
+            })
+            .collect();
+
+        Self {
+            shared,
+            results,
+            workers,
+        }
+    }
+
+    pub fn request(&self, x: i32, y: i32, z: i32) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        let distance = distance_squared(queue.focus, (x, y, z));
+        queue.jobs.push(Job {
+            distance,
+            key: (x, y, z),
+        });
+        self.shared.available.notify_one();
+    }
+
+    /// Re-prioritises the queued jobs around a new focus.
+    pub fn set_focus(&self, focus: ChunkKey) {
+        let mut queue = self.shared.queue.lock().unwrap();
+        if queue.focus == focus {
+            return;
+        }
+
+        queue.focus = focus;
+        let jobs = std::mem::take(&mut queue.jobs);
+        queue.jobs = jobs
+            .into_iter()
+            .map(|job| Job {
+                distance: distance_squared(focus, job.key),
+                key: job.key,
+            })
+            .collect();
+    }
+
+    /// Drops the queued jobs for which `keep` returns `false`. Jobs already
+    /// picked up by a worker still complete.
+    pub fn retain(&self, mut keep: impl FnMut(&ChunkKey) -> bool) {
+        self.shared
+            .queue
+            .lock()
+            .unwrap()
+            .jobs
+            .retain(|job| keep(&job.key));
+    }
+
+    pub fn queued(&self) -> usize {
+        self.shared.queue.lock().unwrap().jobs.len()
+    }
+
+    /// Returns a generated chunk if one is ready, without blocking.
+    pub fn try_recv(&self) -> Option<T> {
+        self.results.try_recv().ok()
+    }
+}
+
+/// One worker per core, leaving a core for the render thread.
+pub fn default_worker_count() -> usize {
+    std::thread::available_parallelism().map_or(1, |cores| cores.get().saturating_sub(1).max(1))
+}
+
+impl<T> Drop for GenerationPool<T> {
+    fn drop(&mut self) {
+        self.shared.queue.lock().unwrap().shutdown = true;
+        self.shared.available.notify_all();
+        for worker in self.workers.drain(..) {
+            let _ = worker.join();
+        }
+    }
+}
+
+fn run_worker<T>(shared: &Shared, generate: &Generate<T>, results: &Sender<T>) {
+    loop {
+        let job = {
+            let mut queue = shared.queue.lock().unwrap();
+            loop {
+                if queue.shutdown {
+                    return;
+                }
+                if let Some(job) = queue.jobs.pop() {
+                    break job;
+                }
+                queue = shared.available.wait(queue).unwrap();
+            }
+        };
+
+        let (x, y, z) = job.key;
+        if results.send(generate(x, y, z)).is_err() {
+            return;
+        }
+    }
+}
@@ -0,0 +1,290 @@
+use super::{ChunkRng, GenerationContext, GeneratorStage};
+use crate::world::chunk::{CHUNK_SIZE, WORLD_BOTTOM};
+use noise::{NoiseFn, Simplex};
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+use std::f64::consts::TAU;
+
+// Noise slices of the turns of a tunnel, apart so that yaw and pitch don't
+// follow each other
+const PITCH_SLICE: f64 = 57.1;
+// Cavities close up over this many layers below their highest point
+const CHEESE_FADE: i32 = 8;
+
+/// Digs winding worm tunnels, large cheese cavities and ravines into the
+/// terrain.
+///
+/// Tunnels and ravines start in chunk columns picked by their own random
+/// streams, and follow their noise in world coordinates. Each section carves
+/// the parts of every tunnel passing through it, wherever it started, so
+/// tunnels run on across chunk borders.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CaveStage {
+    pub worms: TunnelCarver,
+    pub cheese: CheeseCaves,
+    pub ravines: TunnelCarver,
+    /// Layers left below the floor of seas and lakes, so that they don't
+    /// open into caves.
+    pub water_cover: i32,
+}
+
+impl Default for CaveStage {
+    fn default() -> Self {
+        Self {
+            worms: TunnelCarver::default(),
+            cheese: CheeseCaves::default(),
+            ravines: TunnelCarver {
+                per_chunk: 0.02,
+                start_height: (0, 32),
+                length: (64, 128),
+                radius: (1.5, 3.0),
+                vertical_scale: 5.0,
+                turn_increment: 0.01,
+                max_pitch: 0.1,
+            },
+            water_cover: 4,
+        }
+    }
+}
+
+/// Digs tunnels as chains of ellipsoids along Perlin worms, paths turned by
+/// noise at every step.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TunnelCarver {
+    /// Tunnels started per chunk column. Fractions are the chance of one
+    /// more.
+    pub per_chunk: f64,
+    /// Lowest and highest world z coordinate tunnels start at.
+    pub start_height: (i32, i32),
+    /// Shortest and longest tunnel, in steps of one block.
+    pub length: (i32, i32),
+    /// Smallest and largest horizontal radius in the middle of a tunnel,
+    /// which narrows towards its ends.
+    pub radius: (f64, f64),
+    /// Vertical radius over horizontal radius.
+    pub vertical_scale: f64,
+    /// Noise step per block of tunnel. Higher values wind more.
+    pub turn_increment: f64,
+    /// Steepest slope of a tunnel, in radians.
+    pub max_pitch: f64,
+}
+
+impl Default for TunnelCarver {
+    fn default() -> Self {
+        Self {
+            per_chunk: 0.5,
+            start_height: (-24, 56),
+            length: (48, 112),
+            radius: (1.5, 3.5),
+            vertical_scale: 0.8,
+            turn_increment: 0.03,
+            max_pitch: 0.6,
+        }
+    }
+}
+
+/// Hollows out cavities wherever the 3D cave noise is high enough.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct CheeseCaves {
+    pub increment_xy: f64,
+    pub increment_z: f64,
+    /// Noise values above this hollow out a cavity.
+    pub threshold: f64,
+    /// Highest world z coordinate of cavities.
+    pub max_height: i32,
+}
+
+impl Default for CheeseCaves {
+    fn default() -> Self {
+        Self {
+            increment_xy: 0.02,
+            increment_z: 0.04,
+            threshold: 0.6,
+            max_height: 8,
+        }
+    }
+}
+
+impl GeneratorStage for CaveStage {
+    fn name(&self) -> &str {
+        "caves"
+    }
+
+    fn settings(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn generate(&self, context: &mut GenerationContext) {
+        if context.is_empty() {
+            return;
+        }
+
+        self.carve_cheese(context);
+        self.carve_tunnels(context, &self.worms, "caves.worms");
+        self.carve_tunnels(context, &self.ravines, "caves.ravines");
+    }
+}
+
+impl CaveStage {
+    fn carve_cheese(&self, context: &mut GenerationContext) {
+        let cheese = &self.cheese;
+        let bottom = context.bottom();
+        if bottom > cheese.max_height {
+            return;
+        }
+
+        let noise = context.noise("caves.cheese");
+        for (i, j, k) in context.block_positions() {
+            let z = bottom + k;
+            if z > cheese.max_height {
+                continue;
+            }
+
+            let fade = (z - (cheese.max_height - CHEESE_FADE)).max(0) as f64 / CHEESE_FADE as f64;
+            let threshold = cheese.threshold + (1.0 - cheese.threshold) * fade;
+            let value = noise.get([
+                context.world_x(i) as f64 * cheese.increment_xy,
+                context.world_y(j) as f64 * cheese.increment_xy,
+                z as f64 * cheese.increment_z,
+            ]);
+            if value > threshold {
+                self.carve(context, i, j, k);
+            }
+        }
+    }
+
+    fn carve_tunnels(&self, context: &mut GenerationContext, carver: &TunnelCarver, channel: &str) {
+        let noise = context.noise(channel);
+        // Chunk columns whose tunnels may reach the section
+        let reach_blocks =
+            carver.length.1.max(0) as f64 + carver.radius.1 * carver.vertical_scale.max(1.0);
+        let reach = (reach_blocks / CHUNK_SIZE as f64).ceil() as i32;
+
+        for x in context.x - reach..=context.x + reach {
+            for y in context.y - reach..=context.y + reach {
+                let mut rng = ChunkRng::for_chunk(context.seed, channel, x, y, 0);
+                let expected = carver.per_chunk.max(0.0);
+                let count = expected.floor() as usize + rng.chance(expected.fract()) as usize;
+                for _ in 0..count {
+                    let tunnel = Tunnel::start(carver, &mut rng, x, y);
+                    if tunnel.may_reach(context, reach_blocks) {
+                        self.carve_tunnel(context, carver, &noise, tunnel);
+                    }
+                }
+            }
+        }
+    }
+
+    fn carve_tunnel(
+        &self,
+        context: &mut GenerationContext,
+        carver: &TunnelCarver,
+        noise: &Simplex,
+        mut tunnel: Tunnel,
+    ) {
+        for step in 0..tunnel.length {
+            let along = step as f64 / tunnel.length.max(1) as f64;
+            // Tunnels narrow towards their ends
+            let radius = tunnel.radius * (0.4 + 0.6 * (along * std::f64::consts::PI).sin());
+            self.carve_ellipsoid(
+                context,
+                tunnel.position,
+                radius,
+                radius * carver.vertical_scale,
+            );
+
+            let t = step as f64 * carver.turn_increment;
+            let yaw = tunnel.yaw + TAU * noise.get([t, tunnel.offset, 0.0]);
+            let pitch = carver.max_pitch * noise.get([t, tunnel.offset, PITCH_SLICE]);
+            let (x, y, z) = tunnel.position;
+            tunnel.position = (
+                x + yaw.cos() * pitch.cos(),
+                y + yaw.sin() * pitch.cos(),
+                z + pitch.sin(),
+            );
+        }
+    }
+
+    fn carve_ellipsoid(
+        &self,
+        context: &mut GenerationContext,
+        (x, y, z): (f64, f64, f64),
+        radius: f64,
+        vertical_radius: f64,
+    ) {
+        let local = |center: f64, origin: i32, radius: f64| {
+            let lowest = max(0, (center - radius).floor() as i32 - origin);
+            let highest = min(CHUNK_SIZE - 1, (center + radius).ceil() as i32 - origin);
+            lowest..=highest
+        };
+        let xs = local(x, context.world_x(0), radius);
+        let ys = local(y, context.world_y(0), radius);
+        let zs = local(z, context.bottom(), vertical_radius);
+
+        for i in xs {
+            let dx = (context.world_x(i) as f64 + 0.5 - x) / radius;
+            for j in ys.clone() {
+                let dy = (context.world_y(j) as f64 + 0.5 - y) / radius;
+                for k in zs.clone() {
+                    let dz = (context.bottom() as f64 + k as f64 + 0.5 - z) / vertical_radius;
+                    if dx * dx + dy * dy + dz * dz <= 1.0 {
+                        self.carve(context, i, j, k);
+                    }
+                }
+            }
+        }
+    }
+
+    // Removes a block, keeping the bottom layer of the world and the ground
+    // under water whole
+    fn carve(&self, context: &mut GenerationContext, x: i32, y: i32, z: i32) {
+        let world_z = context.bottom() + z;
+        let surface = context.surface_height(x, y);
+        let covers_water = context.is_underwater(x, y) && world_z > surface - self.water_cover;
+        if world_z > WORLD_BOTTOM && world_z <= surface && !covers_water {
+            context.remove(x, y, z);
+        }
+    }
+}
+
+// A tunnel being dug, in world coordinates
+struct Tunnel {
+    position: (f64, f64, f64),
+    yaw: f64,
+    radius: f64,
+    length: i32,
+    // Where along the noise the turns of the tunnel are read
+    offset: f64,
+}
+
+impl Tunnel {
+    fn start(carver: &TunnelCarver, rng: &mut ChunkRng, chunk_x: i32, chunk_y: i32) -> Self {
+        let (lowest, highest) = carver.start_height;
+        let (shortest, longest) = carver.length;
+        let (smallest, largest) = carver.radius;
+        Self {
+            position: (
+                (chunk_x * CHUNK_SIZE) as f64 + rng.next_f64() * CHUNK_SIZE as f64,
+                (chunk_y * CHUNK_SIZE) as f64 + rng.next_f64() * CHUNK_SIZE as f64,
+                rng.range(lowest, highest + 1) as f64,
+            ),
+            yaw: rng.next_f64() * TAU,
+            radius: smallest + rng.next_f64() * (largest - smallest).max(0.0),
+            length: rng.range(shortest, longest + 1),
+            offset: rng.next_f64() * 1000.0,
+        }
+    }
+
+    // Whether the tunnel may come within reach of the section
+    fn may_reach(&self, context: &GenerationContext, reach: f64) -> bool {
+        let (x, y, z) = self.position;
+        let half = CHUNK_SIZE as f64 / 2.0;
+        let center = |origin: i32| origin as f64 + half;
+        (x - center(context.world_x(0))).abs() <= reach + half
+            && (y - center(context.world_y(0))).abs() <= reach + half
+            && (z - center(context.bottom())).abs() <= reach + half
+    }
+}
@@ -0,0 +1,80 @@
+use super::{block_id, GenerationContext, GeneratorStage};
+use crate::world::chunk::{CHUNK_SIZE, WORLD_BOTTOM};
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FlatLayer {
+    pub block: String,
+    pub thickness: i32,
+}
+
+impl FlatLayer {
+    pub fn new(block: &str, thickness: i32) -> Self {
+        Self {
+            block: block.to_string(),
+            thickness,
+        }
+    }
+}
+
+/// Stacks the same layers in every column, starting at the bottom of the
+/// world.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FlatStage {
+    /// Layers from the bottom up.
+    pub layers: Vec<FlatLayer>,
+}
+
+impl Default for FlatStage {
+    // Grass at z = -1, so that the surface is walked on at z = 0
+    fn default() -> Self {
+        Self {
+            layers: vec![
+                FlatLayer::new("stone", -WORLD_BOTTOM - 4),
+                FlatLayer::new("dirt", 3),
+                FlatLayer::new("grass", 1),
+            ],
+        }
+    }
+}
+
+impl GeneratorStage for FlatStage {
+    fn name(&self) -> &str {
+        "flat"
+    }
+
+    fn settings(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn generate(&self, context: &mut GenerationContext) {
+        let bottom = context.bottom();
+        let mut layer_bottom = WORLD_BOTTOM;
+
+        for layer in &self.layers {
+            let layer_top = layer_bottom + layer.thickness.max(0);
+            let lowest = max(layer_bottom, bottom);
+            let highest = min(layer_top, bottom + CHUNK_SIZE);
+            if lowest < highest {
+                let id = block_id(&layer.block);
+                for k in lowest..highest {
+                    for i in 0..CHUNK_SIZE {
+                        for j in 0..CHUNK_SIZE {
+                            context.set(i, j, k - bottom, id);
+                        }
+                    }
+                }
+            }
+            layer_bottom = layer_top;
+        }
+
+        for i in 0..CHUNK_SIZE {
+            for j in 0..CHUNK_SIZE {
+                context.set_surface_height(i, j, layer_bottom - 1);
+            }
+        }
+    }
+}
@@ -0,0 +1,94 @@
+use noise::{NoiseFn, Simplex};
+use serde::{Deserialize, Serialize};
+
+// Octaves and warp offsets sample their own slices of the 3D noise, so that
+// they don't repeat each other's features
+const OCTAVE_SLICE: f64 = 31.7;
+const WARP_SLICE: f64 = -97.3;
+
+/// 2D fractal noise made of several octaves of the Simplex noise, optionally
+/// folded into ridges and sampled through a warped domain.
+///
+/// Each octave adds finer and, with a `persistence` below 1, weaker detail.
+/// Samples are in `0.0..=1.0`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct FractalNoise {
+    /// Noise step between neighbouring blocks in the first octave.
+    pub increment: f64,
+    pub octaves: u32,
+    /// Frequency multiplier from one octave to the next.
+    pub lacunarity: f64,
+    /// Amplitude multiplier from one octave to the next.
+    pub persistence: f64,
+    /// Folds every octave along its zero crossings into sharp ridges with
+    /// wide valleys between them, as in mountain ranges.
+    pub ridged: bool,
+    /// Stretches samples away from the middle of the range. Octaves average
+    /// each other out, so the more there are, the flatter the noise gets
+    /// without it.
+    pub contrast: f64,
+    /// How many blocks the warp noise shifts sample positions by. Zero
+    /// disables domain warping.
+    pub warp_strength: f64,
+    /// Noise step between neighbouring blocks of the warp noise.
+    pub warp_increment: f64,
+}
+
+impl Default for FractalNoise {
+    fn default() -> Self {
+        Self {
+            increment: 0.01,
+            octaves: 1,
+            lacunarity: 2.0,
+            persistence: 0.5,
+            ridged: false,
+            contrast: 1.0,
+            warp_strength: 0.0,
+            warp_increment: 0.01,
+        }
+    }
+}
+
+impl FractalNoise {
+    /// Samples the noise at world block coordinates.
+    pub fn sample(&self, noise: &Simplex, x: f64, y: f64) -> f64 {
+        let (x, y) = self.warp(noise, x, y);
+
+        let mut frequency = self.increment;
+        let mut amplitude = 1.0;
+        let mut total = 0.0;
+        let mut total_amplitude = 0.0;
+        for octave in 0..self.octaves.max(1) {
+            let value = noise.get([x * frequency, y * frequency, octave as f64 * OCTAVE_SLICE]);
+            let value = if self.ridged {
+                let ridge = 1.0 - value.abs();
+                ridge * ridge
+            } else {
+                (value + 1.0) / 2.0
+            };
+
+            total += value * amplitude;
+            total_amplitude += amplitude;
+            frequency *= self.lacunarity;
+            amplitude *= self.persistence;
+        }
+
+        if total_amplitude > 0.0 {
+            (0.5 + (total / total_amplitude - 0.5) * self.contrast).clamp(0.0, 1.0)
+        } else {
+            0.0
+        }
+    }
+
+    fn warp(&self, noise: &Simplex, x: f64, y: f64) -> (f64, f64) {
+        if self.warp_strength == 0.0 {
+            return (x, y);
+        }
+
+        let (warp_x, warp_y) = (x * self.warp_increment, y * self.warp_increment);
+        let dx = noise.get([warp_x, warp_y, WARP_SLICE]);
+        let dy = noise.get([warp_x, warp_y, WARP_SLICE - OCTAVE_SLICE]);
+        (x + dx * self.warp_strength, y + dy * self.warp_strength)
+    }
+}
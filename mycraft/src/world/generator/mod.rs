@@ -0,0 +1,342 @@
+mod caves;
+mod flat;
+mod fractal;
+mod ores;
+mod preset;
+mod random;
+mod surface;
+mod terrain;
+mod trees;
+mod water;
+
+pub use caves::{CaveStage, CheeseCaves, TunnelCarver};
+pub use flat::{FlatLayer, FlatStage};
+pub use fractal::FractalNoise;
+pub use ores::{OreStage, OreVein};
+pub use preset::{GeneratorConfig, GeneratorPreset};
+pub use random::{sub_seed, ChunkRng};
+pub use surface::SurfaceStage;
+pub use terrain::{ColumnCache, TerrainStage};
+pub use trees::TreeStage;
+pub use water::WaterStage;
+
+use super::block::{BlockId, AIR_ID};
+use super::biome::Biome;
+use super::chunk::{self, Chunk, CHUNK_SIZE, CHUNK_VOLUME, GENERATOR_VERSION, WORLD_BOTTOM};
+use super::decoration::{self, BlockWrite};
+use super::palette::PaletteStorage;
+use super::registry::registry;
+use noise::Simplex;
+
+/// One step of chunk generation, such as shaping the terrain or growing
+/// trees. Stages run in order on the same section, each seeing what the
+/// previous ones left behind.
+///
+/// Stages are shared between the generation threads, so they must not keep
+/// per-chunk state of their own, other than caches of what they would
+/// generate anyway.
+pub trait GeneratorStage: Send + Sync {
+    /// Identifies the stage in its pipeline.
+    fn name(&self) -> &str;
+
+    /// The settings the stage generates with, in any stable format. Saved
+    /// edits are tied to the names and settings of the stages that generated
+    /// the terrain below them, see `GeneratorPipeline::version`.
+    fn settings(&self) -> String {
+        String::new()
+    }
+
+    fn generate(&self, context: &mut GenerationContext);
+
+    /// Biome of the world column at `x`, `y`, for stages deciding biomes.
+    fn biome_at(&self, _seed: u32, _x: i32, _y: i32) -> Option<Biome> {
+        None
+    }
+}
+
+/// The section being generated, as seen by the stages of a pipeline.
+///
+/// Coordinates are local to the section unless noted otherwise.
+pub struct GenerationContext {
+    pub x: i32,
+    pub y: i32,
+    pub z: i32,
+    pub seed: u32,
+    blocks: PaletteStorage,
+    surface_heights: Vec<i32>,
+    biomes: Vec<Biome>,
+    water_levels: Vec<i32>,
+    overflow: Vec<BlockWrite>,
+}
+
+impl GenerationContext {
+    pub fn new(seed: u32, x: i32, y: i32, z: i32) -> Self {
+        Self {
+            x,
+            y,
+            z,
+            seed,
+            blocks: PaletteStorage::new(CHUNK_VOLUME),
+            surface_heights: vec![WORLD_BOTTOM - 1; (CHUNK_SIZE * CHUNK_SIZE) as usize],
+            biomes: vec![Biome::Plains; (CHUNK_SIZE * CHUNK_SIZE) as usize],
+            water_levels: vec![WORLD_BOTTOM - 1; (CHUNK_SIZE * CHUNK_SIZE) as usize],
+            overflow: Vec::new(),
+        }
+    }
+
+    /// The noise of a generation layer. Every channel gets its own noise
+    /// derived from the world seed, so that layers don't line up with each
+    /// other. Fetch it once per section, it isn't free.
+    pub fn noise(&self, channel: &str) -> Simplex {
+        Simplex::new(sub_seed(self.seed, channel))
+    }
+
+    /// The random stream of a channel in this section, for discrete
+    /// decisions that shouldn't follow noise contours.
+    pub fn rng(&self, channel: &str) -> ChunkRng {
+        ChunkRng::for_chunk(self.seed, channel, self.x, self.y, self.z)
+    }
+
+    /// World x coordinate of the given local one.
+    pub fn world_x(&self, x: i32) -> i32 {
+        self.x * CHUNK_SIZE + x
+    }
+
+    /// World y coordinate of the given local one.
+    pub fn world_y(&self, y: i32) -> i32 {
+        self.y * CHUNK_SIZE + y
+    }
+
+    /// World z coordinate of the lowest block layer in the section.
+    pub fn bottom(&self) -> i32 {
+        self.z * CHUNK_SIZE
+    }
+
+    /// Returns the block id at the local coordinates, air outside of the
+    /// section.
+    pub fn get(&self, x: i32, y: i32, z: i32) -> BlockId {
+        chunk::block_index(x, y, z).map_or(AIR_ID, |index| self.blocks.get(index))
+    }
+
+    pub fn contains(&self, x: i32, y: i32, z: i32) -> bool {
+        self.get(x, y, z) != AIR_ID
+    }
+
+    /// Writes outside of the section bounds are dropped.
+    pub fn set(&mut self, x: i32, y: i32, z: i32, id: BlockId) {
+        if let Some(index) = chunk::block_index(x, y, z) {
+            self.blocks.set(index, id);
+        }
+    }
+
+    pub fn remove(&mut self, x: i32, y: i32, z: i32) {
+        self.set(x, y, z, AIR_ID);
+    }
+
+    /// Places a decoration block, following `decoration::replaces`. Blocks
+    /// outside of the section are handed to the sections they land in.
+    pub fn place(&mut self, x: i32, y: i32, z: i32, id: BlockId) {
+        match chunk::block_index(x, y, z) {
+            Some(index) => {
+                if decoration::replaces(self.blocks.get(index), id) {
+                    self.blocks.set(index, id);
+                }
+            }
+            None => self.overflow.push(BlockWrite {
+                x: self.world_x(x),
+                y: self.world_y(y),
+                z: self.bottom() + z,
+                id,
+            }),
+        }
+    }
+
+    /// Returns `true` if the section holds nothing but air.
+    pub fn is_empty(&self) -> bool {
+        self.blocks.is_uniform() && self.blocks.get(0) == AIR_ID
+    }
+
+    /// Local coordinates of every non-air block.
+    pub fn block_positions(&self) -> Vec<(i32, i32, i32)> {
+        self.blocks
+            .iter_non_air()
+            .map(|(index, _)| chunk::block_position(index))
+            .collect()
+    }
+
+    /// World z coordinate of the top terrain block of a column, which may lie
+    /// outside of the section. Set by the stage shaping the terrain; columns
+    /// without terrain are below the world.
+    pub fn surface_height(&self, x: i32, y: i32) -> i32 {
+        self.surface_heights[(x + y * CHUNK_SIZE) as usize]
+    }
+
+    pub fn set_surface_height(&mut self, x: i32, y: i32, height: i32) {
+        self.surface_heights[(x + y * CHUNK_SIZE) as usize] = height;
+    }
+
+    /// Biome of a column, set by the stage shaping the terrain. Columns
+    /// default to plains.
+    pub fn biome(&self, x: i32, y: i32) -> Biome {
+        self.biomes[(x + y * CHUNK_SIZE) as usize]
+    }
+
+    pub fn set_biome(&mut self, x: i32, y: i32, biome: Biome) {
+        self.biomes[(x + y * CHUNK_SIZE) as usize] = biome;
+    }
+
+    /// World z coordinate of the top water layer of a column, the sea level
+    /// or the level of a lake. Set by the stage shaping the terrain; columns
+    /// without terrain have their water below the world.
+    pub fn water_level(&self, x: i32, y: i32) -> i32 {
+        self.water_levels[(x + y * CHUNK_SIZE) as usize]
+    }
+
+    pub fn set_water_level(&mut self, x: i32, y: i32, level: i32) {
+        self.water_levels[(x + y * CHUNK_SIZE) as usize] = level;
+    }
+
+    /// Whether the surface of a column is below its water level.
+    pub fn is_underwater(&self, x: i32, y: i32) -> bool {
+        self.surface_height(x, y) < self.water_level(x, y)
+    }
+
+    /// Finishes generation, returning the section and the decoration blocks
+    /// that landed outside of it.
+    pub fn finish(self) -> (Chunk, Vec<BlockWrite>) {
+        let chunk = Chunk::from_blocks(self.x, self.y, self.z, self.blocks)
+            .expect("Generated storage is the size of a section");
+        (chunk, self.overflow)
+    }
+}
+
+/// An ordered list of generator stages.
+///
+/// The default pipeline shapes the terrain, digs caves, covers the surface,
+/// places ores, fills seas and lakes and grows trees. Stages can be
+/// reordered, replaced or removed by name, and custom ones added anywhere in
+/// between.
+pub struct GeneratorPipeline {
+    stages: Vec<Box<dyn GeneratorStage>>,
+}
+
+impl Default for GeneratorPipeline {
+    fn default() -> Self {
+        GeneratorConfig::default().pipeline()
+    }
+}
+
+impl GeneratorPipeline {
+    /// A pipeline without any stages, generating nothing but air.
+    pub fn empty() -> Self {
+        Self { stages: Vec::new() }
+    }
+
+    pub fn with_stage(mut self, stage: impl GeneratorStage + 'static) -> Self {
+        self.push(stage);
+        self
+    }
+
+    /// Appends a stage, to run after the existing ones.
+    pub fn push(&mut self, stage: impl GeneratorStage + 'static) {
+        self.stages.push(Box::new(stage));
+    }
+
+    /// Inserts a stage to run before the one at `index`.
+    pub fn insert(&mut self, index: usize, stage: impl GeneratorStage + 'static) {
+        self.stages.insert(index, Box::new(stage));
+    }
+
+    /// Inserts a stage to run right after the stage called `name`. Returns
+    /// `false` and leaves the pipeline unchanged if there's no such stage.
+    pub fn insert_after(&mut self, name: &str, stage: impl GeneratorStage + 'static) -> bool {
+        match self.position(name) {
+            Some(index) => {
+                self.insert(index + 1, stage);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Replaces the stage called `name`, keeping its place in the pipeline.
+    pub fn replace(
+        &mut self,
+        name: &str,
+        stage: impl GeneratorStage + 'static,
+    ) -> Option<Box<dyn GeneratorStage>> {
+        let index = self.position(name)?;
+        Some(std::mem::replace(&mut self.stages[index], Box::new(stage)))
+    }
+
+    /// Takes the stage called `name` out of the pipeline, disabling it.
+    pub fn remove(&mut self, name: &str) -> Option<Box<dyn GeneratorStage>> {
+        let index = self.position(name)?;
+        Some(self.stages.remove(index))
+    }
+
+    /// Moves the stage called `name` so that it runs at `index`. Returns
+    /// `false` if there's no such stage.
+    pub fn move_stage(&mut self, name: &str, index: usize) -> bool {
+        let Some(stage) = self.remove(name) else {
+            return false;
+        };
+        self.stages.insert(index.min(self.stages.len()), stage);
+        true
+    }
+
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.stages.iter().position(|stage| stage.name() == name)
+    }
+
+    pub fn stage_names(&self) -> impl Iterator<Item = &str> {
+        self.stages.iter().map(|stage| stage.name())
+    }
+
+    /// Identifies the terrain this pipeline generates, for tagging saved
+    /// edits. Changes with `GENERATOR_VERSION` and the names, settings and
+    /// order of the stages.
+    pub fn version(&self) -> u32 {
+        // FNV-1a, which stays the same across builds unlike `DefaultHasher`
+        let mut hash: u32 = 0x811c_9dc5;
+        let mut write = |bytes: &[u8]| {
+            for &byte in bytes {
+                hash = (hash ^ byte as u32).wrapping_mul(0x0100_0193);
+            }
+        };
+
+        write(&GENERATOR_VERSION.to_le_bytes());
+        for stage in &self.stages {
+            write(stage.name().as_bytes());
+            write(&[0]);
+            write(stage.settings().as_bytes());
+            write(&[0]);
+        }
+        hash
+    }
+
+    /// Generates a section, returning it together with the decoration blocks
+    /// that landed outside of it.
+    pub fn generate(&self, seed: u32, x: i32, y: i32, z: i32) -> (Chunk, Vec<BlockWrite>) {
+        let mut context = GenerationContext::new(seed, x, y, z);
+        for stage in &self.stages {
+            stage.generate(&mut context);
+        }
+        context.finish()
+    }
+
+    /// Biome of a world column, from the last stage that decides biomes.
+    /// `None` if no stage does, as in superflat and void worlds.
+    pub fn biome_at(&self, seed: u32, x: i32, y: i32) -> Option<Biome> {
+        self.stages
+            .iter()
+            .rev()
+            .find_map(|stage| stage.biome_at(seed, x, y))
+    }
+}
+
+pub(crate) fn block_id(name: &str) -> BlockId {
+    registry()
+        .id_of(name)
+        .unwrap_or_else(|| panic!("Block {name} is missing from the block definitions!"))
+}
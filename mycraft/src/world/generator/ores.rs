@@ -0,0 +1,181 @@
+use super::{block_id, ChunkRng, GenerationContext, GeneratorStage};
+use crate::world::block::BlockId;
+use crate::world::chunk::{CHUNK_SIZE, WORLD_BOTTOM};
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+use std::collections::HashSet;
+
+const DIRECTIONS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    (0, 0, -1),
+];
+
+// Tries at growing a vein by one block, per block of its size, before giving
+// up on a vein boxed in by itself
+const GROWTH_ATTEMPTS: usize = 8;
+
+/// One kind of ore in the ore table, and where its veins form.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct OreVein {
+    pub block: String,
+    /// Lowest world z coordinate the ore is found at.
+    pub min_height: i32,
+    /// Highest world z coordinate the ore is found at.
+    pub max_height: i32,
+    /// Blocks in one vein, some of which may land outside of the height
+    /// range or on blocks the ore doesn't replace.
+    pub vein_size: usize,
+    /// Veins started in a section lying within the height range. Sections
+    /// only partly within it get their share, and fractions are the chance
+    /// of one more vein.
+    pub veins_per_chunk: f64,
+    /// Blocks the ore replaces.
+    pub hosts: Vec<String>,
+}
+
+impl OreVein {
+    /// An ore replacing stone.
+    pub fn new(
+        block: &str,
+        (min_height, max_height): (i32, i32),
+        vein_size: usize,
+        veins_per_chunk: f64,
+    ) -> Self {
+        Self {
+            block: block.to_string(),
+            min_height,
+            max_height,
+            vein_size,
+            veins_per_chunk,
+            hosts: vec!["stone".to_string()],
+        }
+    }
+
+    /// World coordinates of the blocks of every vein started in the section
+    /// at the chunk coordinates. Veins grow as blobs from their first block,
+    /// and may reach into the sections around.
+    pub fn veins(&self, seed: u32, x: i32, y: i32, z: i32) -> Vec<Vec<(i32, i32, i32)>> {
+        let bottom = z * CHUNK_SIZE;
+        let lowest = max(self.min_height, bottom);
+        let highest = min(self.max_height, bottom + CHUNK_SIZE - 1);
+        if lowest > highest || self.vein_size == 0 {
+            return Vec::new();
+        }
+
+        // Ores draw from their own streams, so that adding one to the table
+        // leaves the veins of the others where they were
+        let mut rng = ChunkRng::for_chunk(seed, &format!("ores.{}", self.block), x, y, z);
+        let share = (highest - lowest + 1) as f64 / CHUNK_SIZE as f64;
+        let expected = self.veins_per_chunk.max(0.0) * share;
+        let count = expected.floor() as usize + rng.chance(expected.fract()) as usize;
+
+        (0..count)
+            .map(|_| {
+                let start = (
+                    x * CHUNK_SIZE + rng.range(0, CHUNK_SIZE),
+                    y * CHUNK_SIZE + rng.range(0, CHUNK_SIZE),
+                    rng.range(lowest, highest + 1),
+                );
+                let mut vein = vec![start];
+                let mut placed = HashSet::from([start]);
+                for _ in 0..self.vein_size * GROWTH_ATTEMPTS {
+                    if vein.len() >= self.vein_size {
+                        break;
+                    }
+                    let (x, y, z) = vein[rng.range(0, vein.len() as i32) as usize];
+                    let (dx, dy, dz) = DIRECTIONS[rng.range(0, DIRECTIONS.len() as i32) as usize];
+                    let block = (x + dx, y + dy, z + dz);
+                    if placed.insert(block) {
+                        vein.push(block);
+                    }
+                }
+                vein
+            })
+            .collect()
+    }
+}
+
+/// Places veins of ore from an ore table in the terrain.
+///
+/// Veins belong to the section they start in but reach into the sections
+/// around it. Each section places the parts of the veins around it that land
+/// inside of it, so veins run on across section borders. Blocks of a vein
+/// further away than the next section are dropped.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OreStage {
+    /// Ores placed earlier in the table win where veins overlap.
+    pub ores: Vec<OreVein>,
+}
+
+impl Default for OreStage {
+    fn default() -> Self {
+        Self {
+            ores: vec![
+                OreVein::new("coal", (WORLD_BOTTOM, 96), 14, 5.0),
+                OreVein::new("copper_ore", (-16, 48), 10, 3.0),
+                OreVein::new("iron_ore", (WORLD_BOTTOM, 40), 8, 4.0),
+                OreVein::new("redstone_ore", (WORLD_BOTTOM, 0), 7, 2.0),
+                OreVein::new("gold_ore", (WORLD_BOTTOM, 8), 7, 1.0),
+                OreVein::new("diamond_ore", (WORLD_BOTTOM, -16), 5, 0.5),
+            ],
+        }
+    }
+}
+
+impl GeneratorStage for OreStage {
+    fn name(&self) -> &str {
+        "ores"
+    }
+
+    fn settings(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn generate(&self, context: &mut GenerationContext) {
+        if context.is_empty() {
+            return;
+        }
+
+        let ores: Vec<(BlockId, Vec<BlockId>)> = self
+            .ores
+            .iter()
+            .map(|ore| {
+                let hosts = ore.hosts.iter().map(|host| block_id(host)).collect();
+                (block_id(&ore.block), hosts)
+            })
+            .collect();
+
+        // Veins started in the section or next to it. Ores aren't hosts of
+        // other ores, so the first vein placed wins where two overlap
+        let neighbours = (-1..=1)
+            .flat_map(|dx| (-1..=1).flat_map(move |dy| (-1..=1).map(move |dz| (dx, dy, dz))));
+        for (ore, (id, hosts)) in self.ores.iter().zip(&ores) {
+            for (dx, dy, dz) in neighbours.clone() {
+                let (x, y, z) = (context.x + dx, context.y + dy, context.z + dz);
+                for (x, y, z) in ore.veins(context.seed, x, y, z).into_iter().flatten() {
+                    let (i, j, k) = (
+                        x - context.world_x(0),
+                        y - context.world_y(0),
+                        z - context.bottom(),
+                    );
+                    let inside = [i, j, k].iter().all(|c| (0..CHUNK_SIZE).contains(c));
+                    let in_range = (ore.min_height..=ore.max_height).contains(&z);
+                    // The bottom layer of the world stays whole
+                    if inside
+                        && in_range
+                        && z > WORLD_BOTTOM
+                        && hosts.contains(&context.get(i, j, k))
+                    {
+                        context.set(i, j, k, *id);
+                    }
+                }
+            }
+        }
+    }
+}
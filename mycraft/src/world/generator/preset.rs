@@ -0,0 +1,141 @@
+use super::{
+    CaveStage, FlatStage, GeneratorPipeline, OreStage, SurfaceStage, TerrainStage, TreeStage,
+    WaterStage,
+};
+use crate::world::registry::registry;
+use anyhow::{bail, Context};
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+
+/// Settings of every built-in stage of the default pipeline. Settings
+/// missing from a world config keep their default value, except within a
+/// noise table given in part, whose missing settings are those of
+/// `FractalNoise::default`.
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct GeneratorConfig {
+    pub terrain: TerrainStage,
+    pub caves: CaveStage,
+    pub surface: SurfaceStage,
+    pub ores: OreStage,
+    pub water: WaterStage,
+    pub trees: TreeStage,
+}
+
+impl GeneratorConfig {
+    /// Mountains more than three times as high as the default terrain.
+    pub fn amplified() -> Self {
+        Self {
+            terrain: TerrainStage {
+                height_amplifier: 4.0,
+                ..TerrainStage::default()
+            },
+            ..Self::default()
+        }
+    }
+
+    pub fn pipeline(&self) -> GeneratorPipeline {
+        GeneratorPipeline::empty()
+            .with_stage(self.terrain.clone())
+            .with_stage(self.caves.clone())
+            .with_stage(self.surface.clone())
+            .with_stage(self.ores.clone())
+            .with_stage(self.water.clone())
+            .with_stage(self.trees.clone())
+    }
+}
+
+/// The kind of world a `ChunkProvider` generates.
+///
+/// World configs name the preset in a `preset` field, next to the settings
+/// of `superflat` and `custom` worlds:
+///
+/// ```toml
+/// preset = "custom"
+///
+/// [terrain]
+/// height_amplifier = 2.0
+/// ```
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(tag = "preset", rename_all = "snake_case")]
+pub enum GeneratorPreset {
+    #[default]
+    Default,
+    Amplified,
+    /// The same layers everywhere, without caves or trees.
+    Superflat(FlatStage),
+    /// Nothing but air.
+    Void,
+    Custom(Box<GeneratorConfig>),
+}
+
+impl GeneratorPreset {
+    /// Reads a world config, as JSON or TOML depending on the extension.
+    pub fn load(path: impl AsRef<Path>) -> anyhow::Result<Self> {
+        let path = path.as_ref();
+        let text = std::fs::read_to_string(path)
+            .with_context(|| format!("Can't read world config {}", path.display()))?;
+
+        let preset = match path.extension().and_then(|extension| extension.to_str()) {
+            Some("json") => Self::from_json(&text),
+            Some("toml") => Self::from_toml(&text),
+            _ => bail!("World config {} is neither JSON nor TOML", path.display()),
+        };
+        preset.with_context(|| format!("Invalid world config {}", path.display()))
+    }
+
+    pub fn from_json(text: &str) -> anyhow::Result<Self> {
+        let preset: Self = serde_json::from_str(text)?;
+        preset.validate()?;
+        Ok(preset)
+    }
+
+    pub fn from_toml(text: &str) -> anyhow::Result<Self> {
+        let preset: Self = toml::from_str(text)?;
+        preset.validate()?;
+        Ok(preset)
+    }
+
+    pub fn pipeline(&self) -> GeneratorPipeline {
+        match self {
+            Self::Default => GeneratorPipeline::default(),
+            Self::Amplified => GeneratorConfig::amplified().pipeline(),
+            Self::Superflat(flat) => GeneratorPipeline::empty().with_stage(flat.clone()),
+            Self::Void => GeneratorPipeline::empty(),
+            Self::Custom(config) => config.pipeline(),
+        }
+    }
+
+    // Stages look blocks up while generating, so unknown ones are caught here
+    fn validate(&self) -> anyhow::Result<()> {
+        if let Self::Custom(config) = self {
+            if registry().id_of(&config.water.block).is_none() {
+                bail!("Water of unknown block {}", config.water.block);
+            }
+            for ore in &config.ores.ores {
+                for block in std::iter::once(&ore.block).chain(&ore.hosts) {
+                    if registry().id_of(block).is_none() {
+                        bail!("Ore {} with unknown block {block}", ore.block);
+                    }
+                }
+                if ore.min_height > ore.max_height {
+                    bail!("Ore {} has an empty height range", ore.block);
+                }
+            }
+        }
+        if let Self::Superflat(flat) = self {
+            for layer in &flat.layers {
+                if registry().id_of(&layer.block).is_none() {
+                    bail!("Superflat layer of unknown block {}", layer.block);
+                }
+                if layer.thickness < 0 {
+                    bail!(
+                        "Superflat layer of {} has a negative thickness",
+                        layer.block
+                    );
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,70 @@
+/// Derives the seed of a noise channel or random stream from the world seed,
+/// so that layers sampling the same coordinates don't see the same values.
+///
+/// Built on FNV-1a and the MurmurHash3 finalizer, which stay the same across
+/// builds and platforms unlike `DefaultHasher`.
+pub fn sub_seed(seed: u32, channel: &str) -> u32 {
+    let mut hash: u32 = 0x811c_9dc5;
+    for &byte in seed.to_le_bytes().iter().chain(channel.as_bytes()) {
+        hash = (hash ^ byte as u32).wrapping_mul(0x0100_0193);
+    }
+
+    hash ^= hash >> 16;
+    hash = hash.wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash = hash.wrapping_mul(0xc2b2_ae35);
+    hash ^ (hash >> 16)
+}
+
+/// A small deterministic random number generator (SplitMix64) for the
+/// discrete decisions of generation, like where trees grow.
+///
+/// Seeded per section and channel, so a section gets the same numbers
+/// whatever order the world is generated in.
+#[derive(Clone, Debug)]
+pub struct ChunkRng {
+    state: u64,
+}
+
+impl ChunkRng {
+    pub fn new(seed: u64) -> Self {
+        Self { state: seed }
+    }
+
+    /// The random stream of `channel` in the section at the chunk
+    /// coordinates.
+    pub fn for_chunk(seed: u32, channel: &str, x: i32, y: i32, z: i32) -> Self {
+        let mut rng = Self::new(sub_seed(seed, channel) as u64);
+        for coordinate in [x, y, z] {
+            rng.state ^= rng.next_u64() ^ coordinate as u32 as u64;
+        }
+        rng
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e37_79b9_7f4a_7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+        z ^ (z >> 31)
+    }
+
+    /// A number in `0.0..1.0`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Returns `true` with the given probability.
+    pub fn chance(&mut self, probability: f64) -> bool {
+        self.next_f64() < probability
+    }
+
+    /// A number in `low..high`, or `low` if the range is empty.
+    pub fn range(&mut self, low: i32, high: i32) -> i32 {
+        if high <= low {
+            return low;
+        }
+        let span = (high as i64 - low as i64) as u64;
+        (low as i64 + (self.next_u64() % span) as i64) as i32
+    }
+}
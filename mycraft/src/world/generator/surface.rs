@@ -0,0 +1,106 @@
+use super::{block_id, GenerationContext, GeneratorStage};
+use crate::world::biome::Biome;
+use crate::world::block::BlockId;
+use crate::world::chunk::CHUNK_SIZE;
+use noise::NoiseFn;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Covers the top layers of the terrain with the blocks of their biome.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct SurfaceStage {
+    pub block_increment: f64,
+    /// Layers below the surface that take the biome's blocks.
+    pub surface_depth: i32,
+    /// Block noise values below this cover the surface; the rest stays
+    /// stone.
+    pub surface_threshold: f64,
+}
+
+impl Default for SurfaceStage {
+    fn default() -> Self {
+        Self {
+            block_increment: 0.2,
+            surface_depth: 5,
+            surface_threshold: 0.95,
+        }
+    }
+}
+
+impl GeneratorStage for SurfaceStage {
+    fn name(&self) -> &str {
+        "surface"
+    }
+
+    fn settings(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn generate(&self, context: &mut GenerationContext) {
+        let block_noise = context.noise("blocks");
+        let mut biome_blocks = HashMap::new();
+
+        for (x_coord, y_coord, local_z) in context.block_positions() {
+            let world_x = context.world_x(x_coord);
+            let world_y = context.world_y(y_coord);
+            let z_coord: i32 = context.bottom() + local_z;
+            if z_coord <= context.surface_height(x_coord, y_coord) - self.surface_depth {
+                continue;
+            }
+
+            let block_x_offset: f64 = world_x as f64 * self.block_increment;
+            let block_y_offset: f64 = world_y as f64 * self.block_increment;
+            let block_z_offset: f64 = z_coord as f64 * self.block_increment;
+
+            let block_noise_value: f64 = block_noise
+                .get([block_x_offset, block_y_offset, block_z_offset])
+                .abs();
+
+            if block_noise_value < self.surface_threshold {
+                let biome = context.biome(x_coord, y_coord);
+                let blocks = *biome_blocks
+                    .entry(biome)
+                    .or_insert_with(|| BiomeBlocks::of(biome));
+                let id = if is_covered(context, x_coord, y_coord, local_z) {
+                    blocks.filler
+                } else if context.is_underwater(x_coord, y_coord) {
+                    blocks.underwater
+                } else {
+                    blocks.surface
+                };
+                context.set(x_coord, y_coord, local_z, id);
+            }
+        }
+    }
+}
+
+// The block ids of a biome's surface blocks
+#[derive(Clone, Copy)]
+struct BiomeBlocks {
+    surface: BlockId,
+    filler: BlockId,
+    underwater: BlockId,
+}
+
+impl BiomeBlocks {
+    fn of(biome: Biome) -> Self {
+        let properties = biome.properties();
+        Self {
+            surface: block_id(properties.surface_block),
+            filler: block_id(properties.filler_block),
+            underwater: block_id(properties.underwater_block),
+        }
+    }
+}
+
+// Whether a block sits on top of the given one, falling back to the height
+// field for the top layer of the section, since the blocks above it are not
+// available while it generates
+fn is_covered(context: &GenerationContext, x: i32, y: i32, z: i32) -> bool {
+    if z + 1 < CHUNK_SIZE {
+        context.contains(x, y, z + 1)
+    } else {
+        context.bottom() + z < context.surface_height(x, y)
+    }
+}
@@ -0,0 +1,451 @@
+use super::{block_id, sub_seed, ChunkRng, FractalNoise, GenerationContext, GeneratorStage};
+use crate::world::biome::{self, Biome, BiomeProperties};
+use crate::world::chunk::{CHUNK_SIZE, WORLD_BOTTOM};
+use noise::Simplex;
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::sync::{Arc, Mutex};
+
+// Blocks between the columns whose biomes are blended into the height of
+// the columns around them
+const BLEND_STEP: i32 = 4;
+// Columns around a lake checked for being higher than its middle
+const LAKE_RIM_SAMPLES: i32 = 16;
+// Columns per side of a lake cell searched for its lowest point
+const LAKE_SEARCH_STEPS: i32 = 8;
+// Chunk columns whose terrain is kept for their other sections
+const CACHED_COLUMNS: usize = 1024;
+
+/// Fills every column with stone up to a height taken from the noise and
+/// shaped by the biomes around it, and records the heights and biomes for
+/// the stages after it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TerrainStage {
+    pub height_noise: FractalNoise,
+    pub temperature_noise: FractalNoise,
+    pub humidity_noise: FractalNoise,
+    pub terrain_height: i32,
+    pub height_amplifier: f64,
+    pub min_height: i32,
+    /// World z coordinate of the top layer of the sea.
+    pub sea_level: i32,
+    /// Distance in blocks over which the heights of neighbouring biomes
+    /// fade into each other. Zero gives cliffs along biome borders.
+    pub biome_blend_radius: i32,
+    /// Layers above sea level up to which the shore is a beach.
+    pub beach_height: i32,
+    /// Size of the grid cells that get at most one lake each.
+    pub lake_spacing: i32,
+    /// Chance of a cell trying to place a lake, which only forms if the
+    /// lowest spot of the cell is a depression in the terrain.
+    pub lake_chance: f64,
+    /// Smallest and largest lake radius.
+    pub lake_radius: (i32, i32),
+    /// Layers dug out of the middle of a lake.
+    pub lake_depth: i32,
+    /// The terrain of the chunk columns sampled last.
+    #[serde(skip)]
+    pub column_cache: ColumnCache,
+}
+
+impl Default for TerrainStage {
+    fn default() -> Self {
+        Self {
+            height_noise: FractalNoise {
+                increment: 0.004,
+                octaves: 5,
+                contrast: 2.5,
+                warp_strength: 24.0,
+                warp_increment: 0.004,
+                ..FractalNoise::default()
+            },
+            temperature_noise: FractalNoise {
+                increment: 0.004,
+                octaves: 3,
+                contrast: 2.0,
+                warp_strength: 16.0,
+                ..FractalNoise::default()
+            },
+            humidity_noise: FractalNoise {
+                increment: 0.0025,
+                octaves: 2,
+                contrast: 2.0,
+                ..FractalNoise::default()
+            },
+            terrain_height: 50,
+            height_amplifier: 1.2,
+            min_height: 1,
+            sea_level: 20,
+            biome_blend_radius: 8,
+            beach_height: 2,
+            lake_spacing: 64,
+            lake_chance: 0.6,
+            lake_radius: (5, 12),
+            lake_depth: 3,
+            column_cache: ColumnCache::default(),
+        }
+    }
+}
+
+impl GeneratorStage for TerrainStage {
+    fn name(&self) -> &str {
+        "terrain"
+    }
+
+    fn settings(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn generate(&self, context: &mut GenerationContext) {
+        let stone = block_id("stone");
+        let columns = self.chunk_columns(context.seed, context.x, context.y);
+        let bottom = context.bottom();
+
+        for i in 0..CHUNK_SIZE {
+            for j in 0..CHUNK_SIZE {
+                let column = columns[(i + j * CHUNK_SIZE) as usize];
+
+                for k in max(WORLD_BOTTOM, bottom)..min(bottom + CHUNK_SIZE, column.height) {
+                    context.set(i, j, k - bottom, stone);
+                }
+                context.set_surface_height(i, j, column.height - 1);
+                context.set_biome(i, j, column.biome);
+                context.set_water_level(i, j, column.water_level);
+            }
+        }
+    }
+
+    // Columns that weren't generated are sampled on their own, which is
+    // cheaper for queries scattered over the world
+    fn biome_at(&self, seed: u32, x: i32, y: i32) -> Option<Biome> {
+        let key = (seed, x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+        let biome = match self.column_cache.get(key) {
+            Some(columns) => {
+                let (i, j) = (x.rem_euclid(CHUNK_SIZE), y.rem_euclid(CHUNK_SIZE));
+                columns[(i + j * CHUNK_SIZE) as usize].biome
+            }
+            None => TerrainSampler::new(self, seed).column_biome(x, y),
+        };
+        Some(biome)
+    }
+}
+
+impl TerrainStage {
+    // The columns of a chunk column, x first, sampled once for all of its
+    // sections
+    fn chunk_columns(&self, seed: u32, chunk_x: i32, chunk_y: i32) -> Arc<[Column]> {
+        let key = (seed, chunk_x, chunk_y);
+        if let Some(columns) = self.column_cache.get(key) {
+            return columns;
+        }
+
+        let mut sampler = TerrainSampler::new(self, seed);
+        let columns: Arc<[Column]> = (0..CHUNK_SIZE)
+            .flat_map(|j| (0..CHUNK_SIZE).map(move |i| (i, j)))
+            .map(|(i, j)| sampler.column(chunk_x * CHUNK_SIZE + i, chunk_y * CHUNK_SIZE + j))
+            .collect();
+        self.column_cache.insert(key, columns.clone());
+        columns
+    }
+}
+
+/// The terrain of recently sampled chunk columns, shared by the sections of
+/// each column and by biome queries. Clones start out empty.
+#[derive(Default)]
+pub struct ColumnCache {
+    columns: Mutex<CachedColumns>,
+}
+
+#[derive(Default)]
+struct CachedColumns {
+    by_key: HashMap<(u32, i32, i32), Arc<[Column]>>,
+    // Oldest first
+    order: VecDeque<(u32, i32, i32)>,
+}
+
+impl ColumnCache {
+    fn get(&self, key: (u32, i32, i32)) -> Option<Arc<[Column]>> {
+        self.columns.lock().unwrap().by_key.get(&key).cloned()
+    }
+
+    // Another thread may have sampled the same column in the meantime, which
+    // gives the same terrain
+    fn insert(&self, key: (u32, i32, i32), columns: Arc<[Column]>) {
+        let mut cached = self.columns.lock().unwrap();
+        if cached.by_key.insert(key, columns).is_none() {
+            cached.order.push_back(key);
+        }
+        while cached.order.len() > CACHED_COLUMNS {
+            if let Some(oldest) = cached.order.pop_front() {
+                cached.by_key.remove(&oldest);
+            }
+        }
+    }
+}
+
+impl Clone for ColumnCache {
+    fn clone(&self) -> Self {
+        Self::default()
+    }
+}
+
+impl fmt::Debug for ColumnCache {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ColumnCache").finish_non_exhaustive()
+    }
+}
+
+// A lake filling a terrain depression up to `level`
+#[derive(Clone, Copy)]
+struct Lake {
+    x: i32,
+    y: i32,
+    radius: i32,
+    level: i32,
+}
+
+#[derive(Clone, Copy)]
+struct Column {
+    // World z coordinate right above the top terrain block
+    height: i32,
+    biome: Biome,
+    water_level: i32,
+}
+
+// Samples the terrain of single columns, remembering the biomes and lakes
+// shared by neighbouring columns
+struct TerrainSampler<'a> {
+    stage: &'a TerrainStage,
+    seed: u32,
+    height_noise: Simplex,
+    temperature_noise: Simplex,
+    humidity_noise: Simplex,
+    blend_points: HashMap<(i32, i32), BiomeProperties>,
+    lakes: HashMap<(i32, i32), Option<Lake>>,
+}
+
+impl<'a> TerrainSampler<'a> {
+    fn new(stage: &'a TerrainStage, seed: u32) -> Self {
+        Self {
+            stage,
+            seed,
+            height_noise: Simplex::new(sub_seed(seed, "height")),
+            temperature_noise: Simplex::new(sub_seed(seed, "temperature")),
+            humidity_noise: Simplex::new(sub_seed(seed, "humidity")),
+            blend_points: HashMap::new(),
+            lakes: HashMap::new(),
+        }
+    }
+
+    fn column(&mut self, x: i32, y: i32) -> Column {
+        let stage = self.stage;
+        let base_height = self.base_height(x, y);
+        let biome = self.biome(x, y, base_height);
+        let height = self.height(x, y, base_height, biome);
+
+        match self.lake_at(x, y) {
+            Some((depth, level)) => Column {
+                height: height - depth,
+                biome,
+                water_level: level,
+            },
+            None => Column {
+                height,
+                biome: self.shore_biome(biome, height),
+                water_level: stage.sea_level,
+            },
+        }
+    }
+
+    // Biome of a column, skipping the lakes unless they decide it
+    fn column_biome(&mut self, x: i32, y: i32) -> Biome {
+        let base_height = self.base_height(x, y);
+        let biome = self.biome(x, y, base_height);
+        let height = self.height(x, y, base_height, biome);
+        match self.shore_biome(biome, height) {
+            Biome::Beach if self.lake_at(x, y).is_some() => biome,
+            shore_biome => shore_biome,
+        }
+    }
+
+    // Land meeting the sea turns into a beach
+    fn shore_biome(&self, biome: Biome, height: i32) -> Biome {
+        let stage = self.stage;
+        let shore = stage.sea_level - 2..=stage.sea_level + stage.beach_height;
+        if biome.has_beaches() && shore.contains(&(height - 1)) {
+            Biome::Beach
+        } else {
+            biome
+        }
+    }
+
+    // Depth dug into a column by the highest lake covering it, and the level
+    // of that lake
+    fn lake_at(&mut self, x: i32, y: i32) -> Option<(i32, i32)> {
+        let stage = self.stage;
+        let mut deepest: Option<(i32, i32)> = None;
+        for lake in self.lakes_around(x, y) {
+            let distance_squared = (x - lake.x).pow(2) + (y - lake.y).pow(2);
+            let level = deepest.map_or(stage.sea_level, |(_, level)| level);
+            if distance_squared < lake.radius.pow(2) && lake.level > level {
+                let depth = stage.lake_depth as f64
+                    * (1.0 - distance_squared as f64 / lake.radius.pow(2) as f64);
+                deepest = Some((depth.round() as i32, lake.level));
+            }
+        }
+        deepest
+    }
+
+    // Height of a column before its biome shapes it
+    fn base_height(&self, x: i32, y: i32) -> f64 {
+        let stage = self.stage;
+        let value = stage
+            .height_noise
+            .sample(&self.height_noise, x as f64, y as f64);
+        stage.terrain_height as f64 * stage.height_amplifier * value
+    }
+
+    fn biome(&self, x: i32, y: i32, base_height: f64) -> Biome {
+        let stage = self.stage;
+        let (world_x, world_y) = (x as f64, y as f64);
+        let temperature = stage
+            .temperature_noise
+            .sample(&self.temperature_noise, world_x, world_y);
+        let humidity = stage
+            .humidity_noise
+            .sample(&self.humidity_noise, world_x, world_y);
+        let elevation = base_height.floor() as i32 - stage.sea_level;
+        biome::get_biome_by_params(temperature, humidity, elevation)
+    }
+
+    // World z coordinate right above the top terrain block of a column,
+    // without lakes. Biomes are sampled on a grid shared by every section,
+    // and weighted by their distance to the column, so that heights blend
+    // the same way on both sides of a section border.
+    fn height(&mut self, x: i32, y: i32, base_height: f64, biome: Biome) -> i32 {
+        let stage = self.stage;
+        let radius = stage.biome_blend_radius.max(0);
+        let mut total_weight = 0.0;
+        let (mut scale, mut offset) = (0.0, 0.0);
+
+        for grid_x in (x - radius).div_euclid(BLEND_STEP)..=(x + radius).div_euclid(BLEND_STEP) {
+            for grid_y in (y - radius).div_euclid(BLEND_STEP)..=(y + radius).div_euclid(BLEND_STEP)
+            {
+                let (point_x, point_y) = (grid_x * BLEND_STEP, grid_y * BLEND_STEP);
+                let weight = ((radius + 1 - (point_x - x).abs()).max(0)
+                    * (radius + 1 - (point_y - y).abs()).max(0))
+                    as f64;
+                if weight == 0.0 {
+                    continue;
+                }
+
+                let properties = match self.blend_points.get(&(point_x, point_y)) {
+                    Some(properties) => *properties,
+                    None => {
+                        let base_height = self.base_height(point_x, point_y);
+                        let properties = self.biome(point_x, point_y, base_height).properties();
+                        self.blend_points.insert((point_x, point_y), properties);
+                        properties
+                    }
+                };
+                scale += properties.height_scale * weight;
+                offset += properties.height_offset * weight;
+                total_weight += weight;
+            }
+        }
+
+        if total_weight == 0.0 {
+            let properties = biome.properties();
+            (scale, offset) = (properties.height_scale, properties.height_offset);
+        } else {
+            (scale, offset) = (scale / total_weight, offset / total_weight);
+        }
+
+        let sea_level = stage.sea_level as f64;
+        let height = sea_level + (base_height - sea_level) * scale + offset;
+        max(stage.min_height, height.floor() as i32)
+    }
+
+    // Top terrain block of a column, without lakes
+    fn surface_height(&mut self, x: i32, y: i32) -> i32 {
+        let base_height = self.base_height(x, y);
+        let biome = self.biome(x, y, base_height);
+        self.height(x, y, base_height, biome) - 1
+    }
+
+    // Lakes that may reach the column, from the cells of the lake grid around
+    // it
+    fn lakes_around(&mut self, x: i32, y: i32) -> Vec<Lake> {
+        let spacing = self.stage.lake_spacing.max(1);
+        let reach = self.stage.lake_radius.1;
+        let mut lakes = Vec::new();
+
+        for cell_x in (x - reach).div_euclid(spacing)..=(x + reach).div_euclid(spacing) {
+            for cell_y in (y - reach).div_euclid(spacing)..=(y + reach).div_euclid(spacing) {
+                let lake = match self.lakes.get(&(cell_x, cell_y)) {
+                    Some(lake) => *lake,
+                    None => {
+                        let lake = self.lake(cell_x, cell_y);
+                        self.lakes.insert((cell_x, cell_y), lake);
+                        lake
+                    }
+                };
+                lakes.extend(lake);
+            }
+        }
+        lakes
+    }
+
+    // The lake of a grid cell, if the cell has one. Lakes form in the lowest
+    // spot of their cell, if the terrain around it is high enough to hold
+    // water above the dug out middle, and fill up to below the lowest point
+    // of that rim.
+    fn lake(&mut self, cell_x: i32, cell_y: i32) -> Option<Lake> {
+        let stage = self.stage;
+        let spacing = stage.lake_spacing.max(1);
+        let mut rng = ChunkRng::for_chunk(self.seed, "lakes", cell_x, cell_y, 0);
+        if !rng.chance(stage.lake_chance) {
+            return None;
+        }
+
+        // The lowest point of the cell is the likeliest to be a depression
+        let step = (spacing / LAKE_SEARCH_STEPS).max(1);
+        let (offset_x, offset_y) = (rng.range(0, step), rng.range(0, step));
+        let mut lowest = None;
+        for i in 0..spacing / step {
+            for j in 0..spacing / step {
+                let x = cell_x * spacing + i * step + offset_x;
+                let y = cell_y * spacing + j * step + offset_y;
+                let height = self.surface_height(x, y);
+                if lowest.is_none_or(|(_, _, lowest)| height < lowest) {
+                    lowest = Some((x, y, height));
+                }
+            }
+        }
+        let (x, y, center) = lowest?;
+        let (smallest, largest) = stage.lake_radius;
+        let radius = rng.range(smallest.max(1), largest.max(1) + 1);
+
+        let rim = (0..LAKE_RIM_SAMPLES)
+            .map(|sample| {
+                let angle = sample as f64 / LAKE_RIM_SAMPLES as f64 * std::f64::consts::TAU;
+                let distance = (radius + 1) as f64;
+                self.surface_height(
+                    x + (angle.cos() * distance).round() as i32,
+                    y + (angle.sin() * distance).round() as i32,
+                )
+            })
+            .min()?;
+
+        let level = rim - 1;
+        (center - stage.lake_depth < level && level > stage.sea_level).then_some(Lake {
+            x,
+            y,
+            radius,
+            level,
+        })
+    }
+}
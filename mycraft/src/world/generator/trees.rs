@@ -0,0 +1,194 @@
+use super::{block_id, GenerationContext, GeneratorStage};
+use crate::world::block::{Block, BlockId};
+use crate::world::chunk::CHUNK_SIZE;
+use noise::NoiseFn;
+use serde::{Deserialize, Serialize};
+
+/// Grows trees on the terrain surface. Trees belong to the section holding
+/// the block they grow on, and only grow where earlier stages left that block
+/// solid; the blocks reaching into other sections are handed to them as
+/// decoration.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct TreeStage {
+    /// Multiplies the tree density of every biome.
+    pub density_scale: f64,
+    pub branch_increment: f64,
+    pub leaf_increment: f64,
+    pub trunk_height: i32,
+    /// Branch noise values below this keep the trunk growing, and grow
+    /// branches to the sides.
+    pub branch_threshold: f64,
+    /// Leaf noise values below this grow a leaf.
+    pub leaf_threshold: f64,
+}
+
+impl Default for TreeStage {
+    fn default() -> Self {
+        Self {
+            density_scale: 1.0,
+            branch_increment: 0.9,
+            leaf_increment: 0.6,
+            trunk_height: 4,
+            branch_threshold: 0.4,
+            leaf_threshold: 0.85,
+        }
+    }
+}
+
+impl GeneratorStage for TreeStage {
+    fn name(&self) -> &str {
+        "trees"
+    }
+
+    fn settings(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn generate(&self, context: &mut GenerationContext) {
+        let mut rng = context.rng("trees");
+
+        for i in 0..CHUNK_SIZE {
+            for j in 0..CHUNK_SIZE {
+                // Every column draws, so that one tree doesn't shift the
+                // numbers of the columns after it
+                let density = context.biome(i, j).properties().tree_density;
+                let grows = rng.chance(density * self.density_scale);
+
+                // Caves and ravines may have dug the surface away
+                let ground = context.surface_height(i, j) - context.bottom();
+                let solid = Block::from_id(context.get(i, j, ground))
+                    .is_some_and(|block| block.is_opaque());
+
+                if grows && !context.is_underwater(i, j) && solid {
+                    self.generate_tree(context, i, j, ground + 1);
+                }
+            }
+        }
+    }
+}
+
+impl TreeStage {
+    fn generate_tree(&self, context: &mut GenerationContext, x: i32, y: i32, mut height: i32) {
+        let tree_log = block_id("tree_log");
+
+        for _ in 0..self.trunk_height {
+            context.place(x, y, height, tree_log);
+            height += 1;
+        }
+
+        let increment = self.branch_increment;
+        let tree_branch_x_offset: f64 = context.world_x(x) as f64 * increment;
+        let tree_branch_y_offset: f64 = context.world_y(y) as f64 * increment;
+        let mut tree_branch_z_offset: f64 = (context.bottom() + height) as f64 * increment;
+
+        let noise = context.noise("branches");
+        let branch_noise = |x_offset: f64, y_offset: f64, z_offset: f64| {
+            noise.get([x_offset, y_offset, z_offset]).abs()
+        };
+        let mut tree_branch_noise_value: f64 = branch_noise(
+            tree_branch_x_offset,
+            tree_branch_y_offset,
+            tree_branch_z_offset,
+        );
+
+        while tree_branch_noise_value < self.branch_threshold {
+            let left_noise: f64 = branch_noise(
+                tree_branch_x_offset + increment,
+                tree_branch_y_offset,
+                tree_branch_z_offset,
+            );
+            let right_noise: f64 = branch_noise(
+                tree_branch_x_offset - increment,
+                tree_branch_y_offset,
+                tree_branch_z_offset,
+            );
+            let up_noise: f64 = branch_noise(
+                tree_branch_x_offset,
+                tree_branch_y_offset + increment,
+                tree_branch_z_offset,
+            );
+            let down_noise: f64 = branch_noise(
+                tree_branch_x_offset,
+                tree_branch_y_offset - increment,
+                tree_branch_z_offset,
+            );
+
+            context.place(x, y, height, tree_log);
+
+            if left_noise < self.branch_threshold {
+                self.generate_branch(context, x - 1, y, height, tree_log);
+            }
+
+            if right_noise < self.branch_threshold {
+                self.generate_branch(context, x + 1, y, height, tree_log);
+            }
+
+            if down_noise < self.branch_threshold {
+                self.generate_branch(context, x, y - 1, height, tree_log);
+            }
+
+            if up_noise < self.branch_threshold {
+                self.generate_branch(context, x, y + 1, height, tree_log);
+            }
+
+            height += 1;
+            tree_branch_z_offset += increment;
+            tree_branch_noise_value = branch_noise(
+                tree_branch_x_offset,
+                tree_branch_y_offset,
+                tree_branch_z_offset,
+            );
+        }
+
+        self.generate_leaves(context, x, y, height - 1, 2);
+    }
+
+    fn generate_branch(
+        &self,
+        context: &mut GenerationContext,
+        x: i32,
+        y: i32,
+        z: i32,
+        tree_log: BlockId,
+    ) {
+        context.place(x, y, z, tree_log);
+        self.generate_leaves(context, x, y, z, 1);
+    }
+
+    fn generate_leaves(
+        &self,
+        context: &mut GenerationContext,
+        center_x: i32,
+        center_y: i32,
+        center_z: i32,
+        radius: i32,
+    ) {
+        let tree_leaf = block_id("tree_leaf");
+        let noise = context.noise("leaves");
+        for iz in -radius..=radius {
+            let r: i32 = radius - iz.abs();
+            for iy in -r..=r {
+                let dx: i32 = (r * r - iy * iy).abs().isqrt();
+                for ix in -dx..=dx {
+                    let i = center_x + ix;
+                    let j = center_y + iy;
+                    let k = center_z + iz;
+
+                    let tree_leaf_x_offset: f64 = context.world_x(i) as f64 * self.leaf_increment;
+                    let tree_leaf_y_offset: f64 = context.world_y(j) as f64 * self.leaf_increment;
+                    let tree_leaf_z_offset: f64 =
+                        (context.bottom() + k) as f64 * self.leaf_increment;
+
+                    let tree_leaf_noise: f64 = noise
+                        .get([tree_leaf_x_offset, tree_leaf_y_offset, tree_leaf_z_offset])
+                        .abs();
+
+                    if tree_leaf_noise < self.leaf_threshold {
+                        context.place(i, j, k, tree_leaf);
+                    }
+                }
+            }
+        }
+    }
+}
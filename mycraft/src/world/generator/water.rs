@@ -0,0 +1,48 @@
+use super::{block_id, GenerationContext, GeneratorStage};
+use crate::world::chunk::{CHUNK_SIZE, WORLD_BOTTOM};
+use serde::{Deserialize, Serialize};
+use std::cmp::{max, min};
+
+/// Fills the air above the surface of every column with water, up to the
+/// water level of the column, the sea level or the level of its lake.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct WaterStage {
+    /// The block seas and lakes are made of.
+    pub block: String,
+}
+
+impl Default for WaterStage {
+    fn default() -> Self {
+        Self {
+            block: "water".to_string(),
+        }
+    }
+}
+
+impl GeneratorStage for WaterStage {
+    fn name(&self) -> &str {
+        "water"
+    }
+
+    fn settings(&self) -> String {
+        serde_json::to_string(self).unwrap_or_default()
+    }
+
+    fn generate(&self, context: &mut GenerationContext) {
+        let water = block_id(&self.block);
+        let bottom = context.bottom();
+
+        for i in 0..CHUNK_SIZE {
+            for j in 0..CHUNK_SIZE {
+                let lowest = max(context.surface_height(i, j) + 1, max(WORLD_BOTTOM, bottom));
+                let highest = min(context.water_level(i, j), bottom + CHUNK_SIZE - 1);
+                for k in lowest..=highest {
+                    if !context.contains(i, j, k - bottom) {
+                        context.set(i, j, k - bottom, water);
+                    }
+                }
+            }
+        }
+    }
+}
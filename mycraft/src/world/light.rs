@@ -0,0 +1,545 @@
+use super::block::Block;
+use super::cache::ChunkKey;
+use super::chunk::{block_index, block_position, Chunk, CHUNK_SIZE, CHUNK_VOLUME, MAX_CHUNK_Z};
+use super::{is_local, split_coords, World};
+use std::collections::{HashSet, VecDeque};
+
+/// Brightest light level, of the open sky and the brightest blocks.
+pub const MAX_LIGHT: u8 = 15;
+
+// World z coordinate of the lowest layer above the world, which is lit by the
+// open sky
+const SKY_BOTTOM: i32 = (MAX_CHUNK_Z + 1) * CHUNK_SIZE;
+
+const DOWN: (i32, i32, i32) = (0, 0, -1);
+const NEIGHBOURS: [(i32, i32, i32); 6] = [
+    (1, 0, 0),
+    (-1, 0, 0),
+    (0, 1, 0),
+    (0, -1, 0),
+    (0, 0, 1),
+    DOWN,
+];
+
+type Position = (i32, i32, i32);
+
+/// Light levels of one block, from 0 for darkness to [`MAX_LIGHT`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct Light {
+    /// Light of the open sky, which falls straight down through air without
+    /// dimming.
+    pub sky: u8,
+    /// Light of emissive blocks like lava.
+    pub block: u8,
+}
+
+impl Light {
+    /// Both levels in one byte, sky light in the high four bits.
+    pub fn packed(self) -> u8 {
+        self.sky << 4 | self.block
+    }
+
+    fn unpack(packed: u8) -> Self {
+        Self {
+            sky: packed >> 4,
+            block: packed & 0xf,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Channel {
+    Sky,
+    Block,
+}
+
+impl Channel {
+    const ALL: [Channel; 2] = [Channel::Sky, Channel::Block];
+
+    fn get(self, light: Light) -> u8 {
+        match self {
+            Channel::Sky => light.sky,
+            Channel::Block => light.block,
+        }
+    }
+
+    fn set(self, light: &mut Light, level: u8) {
+        match self {
+            Channel::Sky => light.sky = level,
+            Channel::Block => light.block = level,
+        }
+    }
+}
+
+/// Packed light levels of the blocks of a section. Sections lit evenly, like
+/// open sky or solid rock, take a single byte.
+#[derive(Clone, Debug)]
+pub enum LightStorage {
+    Uniform(u8),
+    Full(Box<[u8]>),
+}
+
+impl LightStorage {
+    fn get(&self, index: usize) -> Light {
+        match self {
+            LightStorage::Uniform(packed) => Light::unpack(*packed),
+            LightStorage::Full(levels) => Light::unpack(levels[index]),
+        }
+    }
+
+    fn set(&mut self, index: usize, light: Light) {
+        let packed = light.packed();
+        match self {
+            LightStorage::Uniform(uniform) if *uniform == packed => {}
+            LightStorage::Uniform(uniform) => {
+                let mut levels = vec![*uniform; CHUNK_VOLUME].into_boxed_slice();
+                levels[index] = packed;
+                *self = LightStorage::Full(levels);
+            }
+            LightStorage::Full(levels) => levels[index] = packed,
+        }
+    }
+
+    // Goes back to a single byte if every block has the same light
+    fn compact(&mut self) {
+        if let LightStorage::Full(levels) = self {
+            if levels.iter().all(|&packed| packed == levels[0]) {
+                *self = LightStorage::Uniform(levels[0]);
+            }
+        }
+    }
+
+    /// Approximate heap usage in bytes.
+    pub fn heap_size(&self) -> usize {
+        match self {
+            LightStorage::Uniform(_) => 0,
+            LightStorage::Full(levels) => levels.len(),
+        }
+    }
+}
+
+impl World {
+    /// Light at the world coordinates. Blocks in chunks that aren't loaded
+    /// or lit yet are dark, and so are opaque blocks that don't glow.
+    pub fn get_light(&self, world_x: i32, world_y: i32, world_z: i32) -> Light {
+        if world_z >= SKY_BOTTOM {
+            return Light {
+                sky: MAX_LIGHT,
+                block: 0,
+            };
+        }
+
+        let ((chunk_x, chunk_y, chunk_z), (x, y, z)) = split_coords(world_x, world_y, world_z);
+        self.get_chunk(chunk_x, chunk_y, chunk_z)
+            .map(|chunk| chunk.get_light(x, y, z))
+            .unwrap_or_default()
+    }
+
+    /// Like [`World::get_light`], but takes coordinates relative to `chunk`.
+    /// Coordinates inside the chunk skip the chunk lookup.
+    pub fn get_light_relative(&self, chunk: &Chunk, x: i32, y: i32, z: i32) -> Light {
+        if is_local(x) && is_local(y) && is_local(z) {
+            return chunk.get_light(x, y, z);
+        }
+
+        self.get_light(
+            chunk.x * CHUNK_SIZE + x,
+            chunk.y * CHUNK_SIZE + y,
+            chunk.z * CHUNK_SIZE + z,
+        )
+    }
+
+    /// Light falling on each face of the block at `(x, y, z)` in `chunk`,
+    /// which is the light of the neighbour the face looks at, in the face
+    /// order of [`World::visible_faces`].
+    pub fn face_light(&self, chunk: &Chunk, x: i32, y: i32, z: i32) -> [Light; 6] {
+        [
+            (x, y + 1, z),
+            (x, y - 1, z),
+            (x, y, z + 1),
+            (x, y, z - 1),
+            (x + 1, y, z),
+            (x - 1, y, z),
+        ]
+        .map(|(x, y, z)| self.get_light_relative(chunk, x, y, z))
+    }
+
+    /// Lights the sections that were just loaded, along with the loaded
+    /// sections below them that were waiting for them, and returns the keys
+    /// of every section lit.
+    ///
+    /// Sections are lit once the section above them is, so that the sky
+    /// shines into them from the start. Light flows in from the lit sections
+    /// around and out into them; those that get brighter are marked dirty,
+    /// the newly lit sections themselves are not.
+    pub(super) fn light_sections(&mut self, keys: &[ChunkKey]) -> Vec<ChunkKey> {
+        let mut lit = Vec::new();
+        for &(x, y, z) in keys {
+            let mut z = z;
+            while self.light_section((x, y, z)) {
+                lit.push((x, y, z));
+                z -= 1;
+            }
+        }
+        lit
+    }
+
+    // Lights a loaded section whose section above is lit. Returns `false` if
+    // the section can't be lit yet or already is.
+    fn light_section(&mut self, key: ChunkKey) -> bool {
+        let (x, y, z) = key;
+        let Some(chunk) = self.get_chunk(x, y, z) else {
+            return false;
+        };
+        if chunk.is_lit() {
+            return false;
+        }
+
+        // Sky light of the layer above the section
+        let mut sky_above = [MAX_LIGHT; (CHUNK_SIZE * CHUNK_SIZE) as usize];
+        if z < MAX_CHUNK_Z {
+            let Some(above) = self.get_chunk(x, y, z + 1).filter(|above| above.is_lit()) else {
+                return false;
+            };
+            for (index, sky) in sky_above.iter_mut().enumerate() {
+                let (i, j, _) = block_position(index);
+                *sky = above.get_light(i, j, 0).sky;
+            }
+        }
+
+        // Sky falls straight down each column, and blocks glow
+        let mut levels = vec![Light::default(); CHUNK_VOLUME];
+        let mut emitters = Vec::new();
+        for (index, &sky) in sky_above.iter().enumerate() {
+            let (i, j, _) = block_position(index);
+            let mut sky = sky;
+            for k in (0..CHUNK_SIZE).rev() {
+                sky = propagated(sky, Channel::Sky, DOWN, chunk.get_block(i, j, k));
+                if sky == 0 {
+                    break;
+                }
+                levels[block_index(i, j, k).unwrap()].sky = sky;
+            }
+        }
+        for ((i, j, k), block) in chunk.blocks() {
+            let emission = block.get_light_emission();
+            if emission > 0 {
+                levels[block_index(i, j, k).unwrap()].block = emission;
+                emitters.push((i, j, k));
+            }
+        }
+
+        // Only blocks that light up a darker neighbour spread their light
+        let origin = (x * CHUNK_SIZE, y * CHUNK_SIZE, z * CHUNK_SIZE);
+        let world_position = |(i, j, k): Position| (origin.0 + i, origin.1 + j, origin.2 + k);
+        let lit_around: Vec<_> = NEIGHBOURS
+            .iter()
+            .filter(|(dx, dy, dz)| {
+                self.get_chunk(x + dx, y + dy, z + dz)
+                    .is_some_and(|neighbour| neighbour.is_lit())
+            })
+            .copied()
+            .collect();
+        let mut spread = LightSpread::quiet(key);
+        for index in 0..CHUNK_VOLUME {
+            let (i, j, k) = block_position(index);
+            let sky = levels[index].sky;
+            if sky <= 1 {
+                continue;
+            }
+            let brightens = NEIGHBOURS.iter().any(|&direction| {
+                let (dx, dy, dz) = direction;
+                let (ni, nj, nk) = (i + dx, j + dy, k + dz);
+                match block_index(ni, nj, nk) {
+                    Some(next) => {
+                        let block = chunk.get_block(ni, nj, nk);
+                        propagated(sky, Channel::Sky, direction, block) > levels[next].sky
+                    }
+                    None => lit_around.contains(&direction),
+                }
+            });
+            if brightens {
+                spread.add(world_position((i, j, k)), Channel::Sky);
+            }
+        }
+        for position in emitters {
+            spread.add(world_position(position), Channel::Block);
+        }
+
+        // Light of the lit sections around flowing in. Sky light from above
+        // already fell in with the columns
+        for &(dx, dy, dz) in &lit_around {
+            let neighbour = self.get_chunk(x + dx, y + dy, z + dz).unwrap();
+            for (i, j, k) in face_blocks((dx, dy, dz)) {
+                let (ni, nj, nk) = (
+                    i - dx * CHUNK_SIZE,
+                    j - dy * CHUNK_SIZE,
+                    k - dz * CHUNK_SIZE,
+                );
+                let light = neighbour.get_light(ni, nj, nk);
+                if light.sky > 1 && dz != 1 {
+                    spread.add(world_position((i, j, k)), Channel::Sky);
+                }
+                if light.block > 1 {
+                    spread.add(world_position((i, j, k)), Channel::Block);
+                }
+            }
+        }
+
+        let mut storage = LightStorage::Full(levels.iter().map(|light| light.packed()).collect());
+        storage.compact();
+        self.chunk_provider
+            .get_loaded_chunk_mut(x, y, z)
+            .unwrap()
+            .set_light_storage(storage);
+        self.spread_light(spread);
+        true
+    }
+
+    /// Updates the light around a block that was replaced.
+    pub(super) fn relight_block(&mut self, position: Position) {
+        let (x, y, z) = position;
+        let block = self.get_block(x, y, z);
+        let mut spread = LightSpread::default();
+
+        for channel in Channel::ALL {
+            let Some(level) = self.light_level(position, channel) else {
+                return;
+            };
+
+            // Take away the light that went through the block, then let the
+            // light around flow back in
+            self.set_light_level(position, channel, 0, &mut spread);
+            let mut removed = VecDeque::from([(position, level)]);
+            self.unspread_light(channel, &mut removed, &mut spread);
+
+            let emission = block.map_or(0, |block| block.get_light_emission());
+            if channel == Channel::Block && emission > 0 {
+                self.set_light_level(position, channel, emission, &mut spread);
+                spread.add(position, channel);
+            }
+            for (dx, dy, dz) in NEIGHBOURS {
+                spread.add((x + dx, y + dy, z + dz), channel);
+            }
+        }
+
+        self.spread_light(spread);
+    }
+
+    // Light level of a channel, or `None` if the block's section isn't lit
+    fn light_level(&self, (x, y, z): Position, channel: Channel) -> Option<u8> {
+        if z >= SKY_BOTTOM {
+            return Some(channel.get(self.get_light(x, y, z)));
+        }
+
+        let ((chunk_x, chunk_y, chunk_z), (i, j, k)) = split_coords(x, y, z);
+        let chunk = self.get_chunk(chunk_x, chunk_y, chunk_z)?;
+        chunk
+            .is_lit()
+            .then(|| channel.get(chunk.get_light(i, j, k)))
+    }
+
+    fn set_light_level(
+        &mut self,
+        (x, y, z): Position,
+        channel: Channel,
+        level: u8,
+        spread: &mut LightSpread,
+    ) {
+        let ((chunk_x, chunk_y, chunk_z), (i, j, k)) = split_coords(x, y, z);
+        let Some(chunk) = self
+            .chunk_provider
+            .get_loaded_chunk_mut(chunk_x, chunk_y, chunk_z)
+        else {
+            return;
+        };
+        let mut light = chunk.get_light(i, j, k);
+        channel.set(&mut light, level);
+        chunk.set_light(i, j, k, light);
+
+        // Blocks on the faces of the section also change the faces of the
+        // sections around
+        let key = (chunk_x, chunk_y, chunk_z);
+        let first_change = spread.changed.insert(key);
+        let on_face = [i, j, k].iter().any(|&c| c == 0 || c == CHUNK_SIZE - 1);
+        if Some(key) != spread.quiet && (first_change || on_face) {
+            self.mark_dirty(x, y, z);
+        }
+    }
+
+    // Flood fills light from the queued blocks into every block it reaches
+    // brighter than it is
+    fn spread_light(&mut self, mut spread: LightSpread) {
+        let mut brightened = Vec::with_capacity(NEIGHBOURS.len());
+        while let Some((position, channel)) = spread.queue.pop_front() {
+            self.brightened_neighbours(position, channel, &mut brightened);
+            for (next, level) in brightened.drain(..) {
+                self.set_light_level(next, channel, level, &mut spread);
+                spread.add(next, channel);
+            }
+        }
+
+        for (x, y, z) in std::mem::take(&mut spread.changed) {
+            if let Some(storage) = self
+                .chunk_provider
+                .get_loaded_chunk_mut(x, y, z)
+                .and_then(|chunk| chunk.light_storage_mut())
+            {
+                storage.compact();
+            }
+        }
+    }
+
+    // Neighbours that the light of a block makes brighter, with their new
+    // levels. Neighbours in the same section are read without looking the
+    // section up again
+    fn brightened_neighbours(
+        &self,
+        position: Position,
+        channel: Channel,
+        brightened: &mut Vec<(Position, u8)>,
+    ) {
+        let (x, y, z) = position;
+        let ((chunk_x, chunk_y, chunk_z), (i, j, k)) = split_coords(x, y, z);
+        let chunk = self
+            .get_chunk(chunk_x, chunk_y, chunk_z)
+            .filter(|chunk| chunk.is_lit());
+        let Some(level) = self.light_level(position, channel) else {
+            return;
+        };
+        if level <= 1 {
+            return;
+        }
+
+        for direction in NEIGHBOURS {
+            let (dx, dy, dz) = direction;
+            let next = (x + dx, y + dy, z + dz);
+            let (ni, nj, nk) = (i + dx, j + dy, k + dz);
+            let (current, block) = match chunk {
+                Some(chunk) if is_local(ni) && is_local(nj) && is_local(nk) => (
+                    channel.get(chunk.get_light(ni, nj, nk)),
+                    chunk.get_block(ni, nj, nk),
+                ),
+                _ => {
+                    let Some(current) = self.light_level(next, channel) else {
+                        continue;
+                    };
+                    (current, self.get_block(next.0, next.1, next.2))
+                }
+            };
+            let level = propagated(level, channel, direction, block);
+            if level > current {
+                brightened.push((next, level));
+            }
+        }
+    }
+
+    // Darkens the blocks lit through the removed blocks, queueing the blocks
+    // around lit by other sources to spread their light back in
+    fn unspread_light(
+        &mut self,
+        channel: Channel,
+        removed: &mut VecDeque<(Position, u8)>,
+        spread: &mut LightSpread,
+    ) {
+        while let Some(((x, y, z), level)) = removed.pop_front() {
+            for direction in NEIGHBOURS {
+                let (dx, dy, dz) = direction;
+                let next = (x + dx, y + dy, z + dz);
+                let Some(current) = self.light_level(next, channel) else {
+                    continue;
+                };
+                if current == 0 {
+                    continue;
+                }
+
+                // Sky light falling straight down came through the removed
+                // block, even at the same level
+                let fell = channel == Channel::Sky && direction == DOWN && current == MAX_LIGHT;
+                if current < level || (fell && level == MAX_LIGHT) {
+                    self.set_light_level(next, channel, 0, spread);
+                    removed.push_back((next, current));
+
+                    let emission = self
+                        .get_block(next.0, next.1, next.2)
+                        .map_or(0, |block| block.get_light_emission());
+                    if channel == Channel::Block && emission > 0 {
+                        self.set_light_level(next, channel, emission, spread);
+                        spread.add(next, channel);
+                    }
+                } else {
+                    spread.add(next, channel);
+                }
+            }
+        }
+    }
+}
+
+// Light level a block gets from a neighbour, given the direction from the
+// neighbour to it
+fn propagated(level: u8, channel: Channel, direction: Position, block: Option<Block>) -> u8 {
+    match block {
+        Some(block) if block.is_opaque() => 0,
+        None if channel == Channel::Sky && direction == DOWN && level == MAX_LIGHT => MAX_LIGHT,
+        _ => level.saturating_sub(1),
+    }
+}
+
+// Local coordinates of the layer of blocks just outside of a section in
+// the direction
+fn face_blocks((dx, dy, dz): Position) -> impl Iterator<Item = Position> {
+    let layer = |d: i32, a: i32| match d {
+        1 => CHUNK_SIZE,
+        -1 => -1,
+        _ => a,
+    };
+    (0..CHUNK_SIZE).flat_map(move |a| {
+        (0..CHUNK_SIZE).map(move |b| {
+            // The two coordinates along the face run over a and b
+            match (dx, dy) {
+                (0, 0) => (a, b, layer(dz, 0)),
+                (0, _) => (a, layer(dy, 0), b),
+                _ => (layer(dx, 0), a, b),
+            }
+        })
+    })
+}
+
+// Blocks waiting to spread their light, and the sections whose light changed
+#[derive(Default)]
+struct LightSpread {
+    queue: VecDeque<(Position, Channel)>,
+    changed: HashSet<ChunkKey>,
+    // Section being lit for the first time, which isn't meshed yet
+    quiet: Option<ChunkKey>,
+}
+
+impl LightSpread {
+    fn quiet(key: ChunkKey) -> Self {
+        Self {
+            quiet: Some(key),
+            ..Self::default()
+        }
+    }
+
+    fn add(&mut self, position: Position, channel: Channel) {
+        self.queue.push_back((position, channel));
+    }
+}
+
+impl Chunk {
+    /// Light at the local coordinates. Sections that aren't lit yet are
+    /// dark.
+    pub fn get_light(&self, x: i32, y: i32, z: i32) -> Light {
+        match (self.light_storage(), block_index(x, y, z)) {
+            (Some(storage), Some(index)) => storage.get(index),
+            _ => Light::default(),
+        }
+    }
+
+    fn set_light(&mut self, x: i32, y: i32, z: i32, light: Light) {
+        if let (Some(storage), Some(index)) = (self.light_storage_mut(), block_index(x, y, z)) {
+            storage.set(index, light);
+        }
+    }
+}
@@ -0,0 +1,235 @@
+use super::block::{Block, Face};
+use super::chunk::{Chunk, CHUNK_SIZE};
+use super::light::Light;
+use super::World;
+
+const LAYER_AREA: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+/// A rectangle of block faces that look the same way, show the same material
+/// and get the same light, merged by [`World::greedy_quads`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Quad {
+    pub face: Face,
+    /// Chunk-local position of the lowest block the quad covers.
+    pub position: (i32, i32, i32),
+    /// Blocks covered along x, y and z. The extent along the face's normal is
+    /// 1, or the scale of the cells of a [`World::lod_quads`] mesh.
+    pub extent: (i32, i32, i32),
+    pub material: usize,
+    pub light: Light,
+}
+
+impl World {
+    /// Merges the visible faces of the blocks in `chunk` into as few quads as
+    /// it can. Faces are merged within each layer of the chunk when they
+    /// look the same way and match in material and light, first along a row
+    /// and then across the rows below it.
+    pub fn greedy_quads(&self, chunk: &Chunk) -> Vec<Quad> {
+        let mut layers = Layers::new();
+        for ((x, y, z), block) in chunk.blocks() {
+            let visible_faces = self.visible_faces(chunk, x, y, z);
+            if !visible_faces.contains(&true) {
+                continue;
+            }
+
+            let light = self.face_light(chunk, x, y, z);
+            for face in Face::ALL {
+                if visible_faces[face as usize] {
+                    let shown = (block.get_material_id(face), light[face as usize]);
+                    layers.set(face, (x, y, z), shown);
+                }
+            }
+        }
+        layers.merge(CHUNK_SIZE, 1)
+    }
+
+    /// Like [`World::greedy_quads`], but for a coarser copy of `chunk` made
+    /// of cubes `scale` blocks wide, for chunks far enough away that the
+    /// difference doesn't show. `scale` has to divide the chunk size.
+    ///
+    /// A cube is filled when at least half of its blocks are, with the
+    /// highest of them, so that the surface keeps its look. Cubes show their
+    /// faces to empty cubes, including those of the sections above and below.
+    /// Faces on the sides of the chunk are all kept, since the chunks next to
+    /// it may be meshed at another scale.
+    pub fn lod_quads(&self, chunk: &Chunk, scale: i32) -> Vec<Quad> {
+        let size = CHUNK_SIZE / scale;
+        let index = |(x, y, z): (i32, i32, i32)| ((z * size + y) * size + x) as usize;
+        let is_inside = |coord: i32| (0..size).contains(&coord);
+
+        let mut cells = Vec::with_capacity((size * size * size) as usize);
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    cells.push(self.lod_cell(chunk, (x, y, z), scale));
+                }
+            }
+        }
+
+        let mut layers = Layers::new();
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let Some(block) = cells[index((x, y, z))] else {
+                        continue;
+                    };
+
+                    for face in Face::ALL {
+                        let (dx, dy, dz) = face.normal();
+                        let next = (x + dx, y + dy, z + dz);
+                        // Faces on the sides of the chunk are always kept, as
+                        // skirts over the gaps to chunks meshed at other scales
+                        let filled = if !is_inside(next.0) || !is_inside(next.1) {
+                            false
+                        } else if is_inside(next.2) {
+                            cells[index(next)].is_some()
+                        } else {
+                            self.lod_cell(chunk, next, scale).is_some()
+                        };
+                        if filled {
+                            continue;
+                        }
+
+                        // The light in front of the middle of the face
+                        let [i, j, k] = [(x, dx), (y, dy), (z, dz)].map(|(cell, d)| match d {
+                            1 => (cell + 1) * scale,
+                            -1 => cell * scale - 1,
+                            _ => cell * scale + scale / 2,
+                        });
+                        let light = self.get_light_relative(chunk, i, j, k);
+                        layers.set(face, (x, y, z), (block.get_material_id(face), light));
+                    }
+                }
+            }
+        }
+        layers.merge(size, scale)
+    }
+
+    // The block standing in for a cube of a coarser copy of `chunk`, in cube
+    // coordinates, or `None` if the cube is mostly empty
+    fn lod_cell(&self, chunk: &Chunk, (x, y, z): (i32, i32, i32), scale: i32) -> Option<Block> {
+        let mut filled = 0;
+        let mut highest = None;
+        for k in (z * scale..(z + 1) * scale).rev() {
+            for j in y * scale..(y + 1) * scale {
+                for i in x * scale..(x + 1) * scale {
+                    if let Some(block) = self.get_block_relative(chunk, i, j, k) {
+                        filled += 1;
+                        highest.get_or_insert(block);
+                    }
+                }
+            }
+        }
+        highest.filter(|_| filled * 2 >= scale * scale * scale)
+    }
+}
+
+// What each face of each block or cube shows, by face and then by layer along
+// the face's normal
+struct Layers(Vec<[Option<(usize, Light)>; LAYER_AREA]>);
+
+impl Layers {
+    fn new() -> Self {
+        Self(vec![
+            [None; LAYER_AREA];
+            Face::ALL.len() * CHUNK_SIZE as usize
+        ])
+    }
+
+    fn set(&mut self, face: Face, position: (i32, i32, i32), shown: (usize, Light)) {
+        let (layer, u, v) = layer_coords(face, position);
+        let index = face as usize * CHUNK_SIZE as usize + layer as usize;
+        self.0[index][(v * CHUNK_SIZE + u) as usize] = Some(shown);
+    }
+
+    // Merges the faces of a grid `size` cells wide, whose cells are `scale`
+    // blocks wide
+    fn merge(mut self, size: i32, scale: i32) -> Vec<Quad> {
+        let mut quads = Vec::new();
+        for face in Face::ALL {
+            for layer in 0..size {
+                let index = face as usize * CHUNK_SIZE as usize + layer as usize;
+                merge_layer(face, layer, size, &mut self.0[index], &mut quads);
+            }
+        }
+        for quad in &mut quads {
+            let (x, y, z) = quad.position;
+            let (dx, dy, dz) = quad.extent;
+            quad.position = (x * scale, y * scale, z * scale);
+            quad.extent = (dx * scale, dy * scale, dz * scale);
+        }
+        quads
+    }
+}
+
+// Greedily covers the faces of one layer with quads, taking the faces out of
+// the layer as they are covered
+fn merge_layer(
+    face: Face,
+    layer: i32,
+    size: i32,
+    cells: &mut [Option<(usize, Light)>; LAYER_AREA],
+    quads: &mut Vec<Quad>,
+) {
+    let cell = |u: i32, v: i32| (v * CHUNK_SIZE + u) as usize;
+
+    for v in 0..size {
+        let mut u = 0;
+        while u < size {
+            let Some((material, light)) = cells[cell(u, v)] else {
+                u += 1;
+                continue;
+            };
+            let same = Some((material, light));
+
+            let mut width = 1;
+            while u + width < size && cells[cell(u + width, v)] == same {
+                width += 1;
+            }
+            let mut height = 1;
+            while v + height < size
+                && (u..u + width).all(|next| cells[cell(next, v + height)] == same)
+            {
+                height += 1;
+            }
+
+            for covered_v in v..v + height {
+                for covered_u in u..u + width {
+                    cells[cell(covered_u, covered_v)] = None;
+                }
+            }
+            let (position, extent) = block_box(face, layer, (u, v), (width, height));
+            quads.push(Quad {
+                face,
+                position,
+                extent,
+                material,
+                light,
+            });
+            u += width;
+        }
+    }
+}
+
+// The layer along the face's normal, and the two coordinates within it
+fn layer_coords(face: Face, (x, y, z): (i32, i32, i32)) -> (i32, i32, i32) {
+    match face {
+        Face::Front | Face::Back => (y, x, z),
+        Face::Top | Face::Bottom => (z, x, y),
+        Face::Right | Face::Left => (x, y, z),
+    }
+}
+
+// Lowest block and extent of the blocks covered by a rectangle in a layer
+fn block_box(
+    face: Face,
+    layer: i32,
+    (u, v): (i32, i32),
+    (width, height): (i32, i32),
+) -> ((i32, i32, i32), (i32, i32, i32)) {
+    match face {
+        Face::Front | Face::Back => ((u, layer, v), (width, 1, height)),
+        Face::Top | Face::Bottom => ((u, v, layer), (width, height, 1)),
+        Face::Right | Face::Left => ((layer, u, v), (1, width, height)),
+    }
+}
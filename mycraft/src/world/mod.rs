@@ -1,3 +1,268 @@
 pub mod block;
+pub mod cache;
 pub mod chunk;
+pub mod decoration;
+pub mod delta;
+pub mod fluid;
+pub mod generation;
+pub mod generator;
 pub mod biome;
+pub mod light;
+pub mod mesh;
+pub mod palette;
+pub mod region;
+pub mod registry;
+pub mod visibility;
+
+use biome::Biome;
+use block::Block;
+use cache::{CacheMetrics, ChunkKey};
+use chunk::{Chunk, ChunkProvider, CHUNK_SIZE};
+use fluid::FluidSimulation;
+use std::collections::HashSet;
+
+/// Block access in world coordinates on top of a [`ChunkProvider`].
+///
+/// Lookups only read chunks that are already loaded, so queries that cross
+/// into a chunk which was never loaded see air. Edits remember which chunks
+/// need their meshes rebuilt until [`World::take_dirty_chunks`] is called,
+/// and wake the fluid around them, see [`World::tick`]. Sections loaded
+/// through the world are lit, and edits update the light around them, see
+/// [`World::get_light`].
+pub struct World {
+    chunk_provider: ChunkProvider,
+    dirty_chunks: HashSet<(i32, i32, i32)>,
+    fluids: FluidSimulation,
+}
+
+impl World {
+    pub fn new(chunk_provider: ChunkProvider) -> Self {
+        Self {
+            chunk_provider,
+            dirty_chunks: HashSet::new(),
+            fluids: FluidSimulation::new(),
+        }
+    }
+
+    /// Loads (generating if needed) and returns the chunk at the given chunk coordinates.
+    pub fn load_chunk(&mut self, x: i32, y: i32, z: i32) -> &Chunk {
+        let loaded = self.get_chunk(x, y, z).is_none();
+        self.chunk_provider.get_chunk(x, y, z);
+        if loaded {
+            self.fluids_loaded(&[(x, y, z)]);
+        }
+        self.light_loaded(&[(x, y, z)]);
+        self.chunk_provider.get_chunk(x, y, z)
+    }
+
+    /// Queues a chunk for background generation, see `receive_chunks`.
+    pub fn request_chunk(&mut self, x: i32, y: i32, z: i32) {
+        self.chunk_provider.request_chunk(x, y, z);
+    }
+
+    /// Drops the queued requests for which `keep` returns `false`.
+    pub fn cancel_requests(&mut self, keep: impl FnMut(&ChunkKey) -> bool) {
+        self.chunk_provider.cancel_requests(keep);
+    }
+
+    /// Loads the requested chunks that are ready and returns the keys of the
+    /// sections that got lit, which can include sections loaded earlier that
+    /// were waiting for the ones above them.
+    pub fn receive_chunks(&mut self) -> Vec<ChunkKey> {
+        let keys = self.chunk_provider.receive_chunks();
+        self.fluids_loaded(&keys);
+        self.light_loaded(&keys)
+    }
+
+    pub fn get_chunk(&self, x: i32, y: i32, z: i32) -> Option<&Chunk> {
+        self.chunk_provider.get_loaded_chunk(x, y, z)
+    }
+
+    pub fn get_block(&self, world_x: i32, world_y: i32, world_z: i32) -> Option<Block> {
+        let ((chunk_x, chunk_y, chunk_z), (x, y, z)) = split_coords(world_x, world_y, world_z);
+        self.get_chunk(chunk_x, chunk_y, chunk_z)
+            .and_then(|chunk| chunk.get_block(x, y, z))
+    }
+
+    /// Whether the block at the world coordinates hides the faces next to it.
+    /// Blocks in unloaded chunks count as air.
+    pub fn is_opaque(&self, world_x: i32, world_y: i32, world_z: i32) -> bool {
+        self.get_block(world_x, world_y, world_z)
+            .is_some_and(|block| block.is_opaque())
+    }
+
+    /// Like [`World::get_block`], but takes coordinates relative to `chunk`.
+    /// Coordinates inside the chunk skip the chunk lookup.
+    pub fn get_block_relative(&self, chunk: &Chunk, x: i32, y: i32, z: i32) -> Option<Block> {
+        if is_local(x) && is_local(y) && is_local(z) {
+            return chunk.get_block(x, y, z);
+        }
+
+        self.get_block(
+            chunk.x * CHUNK_SIZE + x,
+            chunk.y * CHUNK_SIZE + y,
+            chunk.z * CHUNK_SIZE + z,
+        )
+    }
+
+    /// Like [`World::is_opaque`], but takes coordinates relative to `chunk`.
+    /// Coordinates inside the chunk skip the chunk lookup.
+    pub fn is_opaque_relative(&self, chunk: &Chunk, x: i32, y: i32, z: i32) -> bool {
+        if is_local(x) && is_local(y) && is_local(z) {
+            return chunk.is_opaque(x, y, z);
+        }
+
+        self.is_opaque(
+            chunk.x * CHUNK_SIZE + x,
+            chunk.y * CHUNK_SIZE + y,
+            chunk.z * CHUNK_SIZE + z,
+        )
+    }
+
+    /// Places `block` at the world coordinates, loading its chunk if needed,
+    /// and returns the block it replaced.
+    pub fn set_block(
+        &mut self,
+        world_x: i32,
+        world_y: i32,
+        world_z: i32,
+        block: Block,
+    ) -> Option<Block> {
+        self.replace_block(world_x, world_y, world_z, Some(block))
+    }
+
+    /// Replaces the block at the world coordinates with air and returns it.
+    pub fn remove_block(&mut self, world_x: i32, world_y: i32, world_z: i32) -> Option<Block> {
+        self.replace_block(world_x, world_y, world_z, None)
+    }
+
+    /// Chunk the player is in. Chunks far from it are evicted first.
+    pub fn set_focus(&mut self, chunk_x: i32, chunk_y: i32, chunk_z: i32) {
+        self.chunk_provider.set_focus(chunk_x, chunk_y, chunk_z);
+    }
+
+    /// Biome of the world column at the block coordinates, or `None` if the
+    /// world has no biomes.
+    pub fn biome_at(&self, world_x: i32, world_y: i32) -> Option<Biome> {
+        self.chunk_provider.biome_at(world_x, world_y)
+    }
+
+    pub fn cache_metrics(&self) -> CacheMetrics {
+        self.chunk_provider.cache_metrics()
+    }
+
+    /// Writes every modified chunk to disk, if the world has a save location.
+    pub fn save(&mut self) {
+        self.chunk_provider.save_all();
+    }
+
+    /// Returns the chunks edited or relit since the last call, including
+    /// neighbours whose faces along the shared seam may have changed and
+    /// chunks that the decoration of a newly generated neighbour reached.
+    pub fn take_dirty_chunks(&mut self) -> Vec<(i32, i32, i32)> {
+        self.dirty_chunks.drain().collect()
+    }
+
+    // Lights newly loaded sections, and relights the blocks that decoration
+    // placed into the sections around them. Returns the sections lit
+    fn light_loaded(&mut self, keys: &[ChunkKey]) -> Vec<ChunkKey> {
+        for (x, y, z) in self.chunk_provider.take_decorated_blocks() {
+            self.mark_dirty(x, y, z);
+            self.relight_block((x, y, z));
+        }
+        self.light_sections(keys)
+    }
+
+    fn replace_block(
+        &mut self,
+        world_x: i32,
+        world_y: i32,
+        world_z: i32,
+        block: Option<Block>,
+    ) -> Option<Block> {
+        let ((chunk_x, chunk_y, chunk_z), (x, y, z)) = split_coords(world_x, world_y, world_z);
+        if self.get_chunk(chunk_x, chunk_y, chunk_z).is_none() {
+            self.chunk_provider.get_chunk(chunk_x, chunk_y, chunk_z);
+            self.fluids_loaded(&[(chunk_x, chunk_y, chunk_z)]);
+        }
+        let chunk = self.chunk_provider.get_chunk_mut(chunk_x, chunk_y, chunk_z);
+        let previous = match block {
+            Some(block) => chunk.set_block(x, y, z, block),
+            None => chunk.remove_block(x, y, z),
+        };
+        self.light_loaded(&[(chunk_x, chunk_y, chunk_z)]);
+
+        if previous.map(|block| block.get_id()) != block.map(|block| block.get_id()) {
+            self.fluids.block_changed((world_x, world_y, world_z));
+            self.mark_dirty(world_x, world_y, world_z);
+            self.relight_block((world_x, world_y, world_z));
+        }
+
+        previous
+    }
+
+    // Marks the chunk of a changed block dirty, along with the neighbours
+    // whose faces along the shared seam may have changed
+    fn mark_dirty(&mut self, world_x: i32, world_y: i32, world_z: i32) {
+        let ((chunk_x, chunk_y, chunk_z), (x, y, z)) = split_coords(world_x, world_y, world_z);
+        self.dirty_chunks.insert((chunk_x, chunk_y, chunk_z));
+        for (local, (dx, dy, dz)) in [(x, (1, 0, 0)), (y, (0, 1, 0)), (z, (0, 0, 1))] {
+            let step = match local {
+                0 => -1,
+                local if local == CHUNK_SIZE - 1 => 1,
+                _ => continue,
+            };
+            self.dirty_chunks.insert((
+                chunk_x + dx * step,
+                chunk_y + dy * step,
+                chunk_z + dz * step,
+            ));
+        }
+    }
+
+    /// Faces of the block at `(x, y, z)` in `chunk` that border a see-through
+    /// neighbour, in the face order used by the block renderer. Faces between
+    /// blocks that cull against their own kind, like water, stay hidden.
+    pub fn visible_faces(&self, chunk: &Chunk, x: i32, y: i32, z: i32) -> [bool; 6] {
+        let block = chunk.get_block(x, y, z);
+        [
+            (x, y + 1, z),
+            (x, y - 1, z),
+            (x, y, z + 1),
+            (x, y, z - 1),
+            (x + 1, y, z),
+            (x - 1, y, z),
+        ]
+        .map(|(x, y, z)| {
+            let neighbour = self.get_block_relative(chunk, x, y, z);
+            match block {
+                Some(block) => block.shows_face_to(neighbour),
+                None => neighbour.is_none_or(|neighbour| neighbour.is_transparent()),
+            }
+        })
+    }
+}
+
+/// Splits world block coordinates into chunk coordinates and coordinates local to that chunk.
+pub fn split_coords(
+    world_x: i32,
+    world_y: i32,
+    world_z: i32,
+) -> ((i32, i32, i32), (i32, i32, i32)) {
+    (
+        (
+            world_x.div_euclid(CHUNK_SIZE),
+            world_y.div_euclid(CHUNK_SIZE),
+            world_z.div_euclid(CHUNK_SIZE),
+        ),
+        (
+            world_x.rem_euclid(CHUNK_SIZE),
+            world_y.rem_euclid(CHUNK_SIZE),
+            world_z.rem_euclid(CHUNK_SIZE),
+        ),
+    )
+}
+
+fn is_local(coord: i32) -> bool {
+    (0..CHUNK_SIZE).contains(&coord)
+}
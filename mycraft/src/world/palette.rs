@@ -0,0 +1,252 @@
+use super::block::{BlockId, AIR_ID};
+use anyhow::{bail, ensure};
+
+const WORD_BITS: u32 = u64::BITS;
+
+/// Fixed-size dense storage of block ids, compressed with a palette.
+///
+/// Every voxel stores an index into `palette`, packed into `u64` words with
+/// as few bits as the palette size allows. A storage holding a single block
+/// id (an empty or completely filled chunk) uses no data words at all.
+#[derive(Clone)]
+pub struct PaletteStorage {
+    palette: Vec<BlockId>,
+    bits_per_entry: u32,
+    data: Vec<u64>,
+    len: usize,
+}
+
+impl PaletteStorage {
+    pub fn new(len: usize) -> Self {
+        Self::filled(len, AIR_ID)
+    }
+
+    pub fn filled(len: usize, id: BlockId) -> Self {
+        Self {
+            palette: vec![id],
+            bits_per_entry: 0,
+            data: Vec::new(),
+            len,
+        }
+    }
+
+    /// Rebuilds a storage from the parts returned by `palette`,
+    /// `bits_per_entry` and `data`, validating that they fit together.
+    pub fn from_raw(
+        palette: Vec<BlockId>,
+        bits_per_entry: u32,
+        data: Vec<u64>,
+        len: usize,
+    ) -> anyhow::Result<Self> {
+        ensure!(!palette.is_empty(), "Palette is empty");
+        if bits_per_entry != bits_for(palette.len()) {
+            bail!(
+                "{} bits per entry don't match a palette of {} ids",
+                bits_per_entry,
+                palette.len()
+            );
+        }
+
+        let expected_words = words_for(bits_per_entry, len);
+        ensure!(
+            data.len() == expected_words,
+            "Expected {} data words, found {}",
+            expected_words,
+            data.len()
+        );
+
+        let storage = Self {
+            palette,
+            bits_per_entry,
+            data,
+            len,
+        };
+        if let Some(index) =
+            (0..len).find(|index| storage.get_palette_index(*index) >= storage.palette.len())
+        {
+            bail!("Entry {} points outside of the palette", index);
+        }
+
+        Ok(storage)
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn palette(&self) -> &[BlockId] {
+        &self.palette
+    }
+
+    pub fn bits_per_entry(&self) -> u32 {
+        self.bits_per_entry
+    }
+
+    /// The packed palette indices, `bits_per_entry` bits each.
+    pub fn data(&self) -> &[u64] {
+        &self.data
+    }
+
+    pub fn get(&self, index: usize) -> BlockId {
+        self.palette[self.get_palette_index(index)]
+    }
+
+    pub fn set(&mut self, index: usize, id: BlockId) {
+        let palette_index = match self.palette.iter().position(|entry| *entry == id) {
+            Some(palette_index) => palette_index,
+            None => {
+                self.palette.push(id);
+                let required_bits = bits_for(self.palette.len());
+                if required_bits > self.bits_per_entry {
+                    self.repack(required_bits);
+                }
+                self.palette.len() - 1
+            }
+        };
+
+        self.set_palette_index(index, palette_index);
+    }
+
+    /// Returns `true` if every voxel holds the same block id.
+    pub fn is_uniform(&self) -> bool {
+        self.bits_per_entry == 0
+    }
+
+    /// Iterates over every voxel in index order.
+    pub fn iter(&self) -> impl Iterator<Item = BlockId> + '_ {
+        (0..self.len).map(|index| self.get(index))
+    }
+
+    /// Iterates over the index and id of every voxel that is not air.
+    ///
+    /// Words that only hold air are skipped without being decoded.
+    pub fn iter_non_air(&self) -> NonAirIter<'_> {
+        NonAirIter {
+            storage: self,
+            word: 0,
+            entry: 0,
+            index: 0,
+        }
+    }
+
+    /// Approximate heap usage in bytes.
+    pub fn heap_size(&self) -> usize {
+        self.palette.capacity() * size_of::<BlockId>() + self.data.capacity() * size_of::<u64>()
+    }
+
+    fn get_palette_index(&self, index: usize) -> usize {
+        if self.bits_per_entry == 0 {
+            return 0;
+        }
+
+        let (word, shift) = self.locate(index);
+        ((self.data[word] >> shift) & self.mask()) as usize
+    }
+
+    fn set_palette_index(&mut self, index: usize, palette_index: usize) {
+        if self.bits_per_entry == 0 {
+            return;
+        }
+
+        let (word, shift) = self.locate(index);
+        let mask = self.mask();
+        self.data[word] = (self.data[word] & !(mask << shift)) | ((palette_index as u64) << shift);
+    }
+
+    fn locate(&self, index: usize) -> (usize, u32) {
+        let entries_per_word = (WORD_BITS / self.bits_per_entry) as usize;
+        let word = index / entries_per_word;
+        let shift = (index % entries_per_word) as u32 * self.bits_per_entry;
+        (word, shift)
+    }
+
+    fn mask(&self) -> u64 {
+        (1u64 << self.bits_per_entry) - 1
+    }
+
+    fn repack(&mut self, bits_per_entry: u32) {
+        let old_indices: Vec<usize> = (0..self.len)
+            .map(|index| self.get_palette_index(index))
+            .collect();
+
+        self.bits_per_entry = bits_per_entry;
+        self.data = vec![0; words_for(bits_per_entry, self.len)];
+
+        for (index, palette_index) in old_indices.into_iter().enumerate() {
+            self.set_palette_index(index, palette_index);
+        }
+    }
+}
+
+/// Data words of a storage of `len` entries packed with `bits_per_entry`
+/// bits each. Entries never straddle two words.
+pub fn words_for(bits_per_entry: u32, len: usize) -> usize {
+    match bits_per_entry {
+        0 => 0,
+        bits => len.div_ceil((WORD_BITS / bits) as usize),
+    }
+}
+
+fn bits_for(palette_len: usize) -> u32 {
+    match palette_len {
+        0 | 1 => 0,
+        len => usize::BITS - (len - 1).leading_zeros(),
+    }
+}
+
+pub struct NonAirIter<'a> {
+    storage: &'a PaletteStorage,
+    word: usize,
+    entry: u32,
+    index: usize,
+}
+
+impl Iterator for NonAirIter<'_> {
+    type Item = (usize, BlockId);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let storage = self.storage;
+
+        if storage.bits_per_entry == 0 {
+            if storage.palette[0] == AIR_ID || self.index >= storage.len {
+                return None;
+            }
+            self.index += 1;
+            return Some((self.index - 1, storage.palette[0]));
+        }
+
+        let entries_per_word = WORD_BITS / storage.bits_per_entry;
+        let air_is_zero = storage.palette[0] == AIR_ID;
+        let mask = storage.mask();
+
+        while self.index < storage.len {
+            let word = storage.data[self.word];
+            if air_is_zero && word == 0 {
+                self.index += (entries_per_word - self.entry) as usize;
+                self.word += 1;
+                self.entry = 0;
+                continue;
+            }
+
+            let palette_index = (word >> (self.entry * storage.bits_per_entry)) & mask;
+            let index = self.index;
+            self.index += 1;
+            self.entry += 1;
+            if self.entry == entries_per_word {
+                self.word += 1;
+                self.entry = 0;
+            }
+
+            let id = storage.palette[palette_index as usize];
+            if id != AIR_ID {
+                return Some((index, id));
+            }
+        }
+
+        None
+    }
+}
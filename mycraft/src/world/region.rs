@@ -0,0 +1,338 @@
+use super::block::BlockId;
+use super::chunk::{Chunk, CHUNK_VOLUME};
+use super::delta::ChunkDelta;
+use super::palette::{self, PaletteStorage};
+use anyhow::{bail, ensure, Context};
+use flate2::read::ZlibDecoder;
+use flate2::write::ZlibEncoder;
+use flate2::Compression;
+use std::fs::{File, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+/// Chunks per region along each axis
+pub const REGION_SIZE: i32 = 8;
+
+const REGION_VOLUME: usize = (REGION_SIZE * REGION_SIZE * REGION_SIZE) as usize;
+const SECTOR_SIZE: u64 = 4096;
+const HEADER_ENTRY_SIZE: usize = 8;
+const HEADER_SIZE: usize = REGION_VOLUME * HEADER_ENTRY_SIZE;
+const HEADER_SECTORS: u32 = (HEADER_SIZE as u64).div_ceil(SECTOR_SIZE) as u32;
+
+/// Chunk storage split into region files of `REGION_SIZE`³ chunks.
+///
+/// A region file starts with a header holding one `(sector, length)` pair
+/// per chunk, both little-endian `u32`. `sector` is the offset of the payload
+/// in 4 KiB sectors (0 for chunks that were never saved) and `length` its size
+/// in bytes. Each payload is a zlib-compressed `ChunkRecord`.
+/// A payload that outgrows its sectors is moved to the end of the file.
+pub struct RegionStore {
+    directory: PathBuf,
+    mode: SaveMode,
+}
+
+/// How `ChunkProvider` writes modified chunks.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SaveMode {
+    /// Every block of the chunk.
+    Full,
+    /// Only the blocks that differ from freshly generated terrain.
+    Deltas,
+}
+
+/// A saved chunk, as read back from a region file.
+pub enum ChunkRecord {
+    Blocks(Chunk),
+    Delta(ChunkDelta),
+}
+
+const BLOCKS_TAG: u8 = 0;
+const DELTA_TAG: u8 = 1;
+
+impl RegionStore {
+    pub fn new(directory: impl Into<PathBuf>) -> Self {
+        Self::with_mode(directory, SaveMode::Full)
+    }
+
+    pub fn with_mode(directory: impl Into<PathBuf>, mode: SaveMode) -> Self {
+        Self {
+            directory: directory.into(),
+            mode,
+        }
+    }
+
+    pub fn directory(&self) -> &Path {
+        &self.directory
+    }
+
+    pub fn mode(&self) -> SaveMode {
+        self.mode
+    }
+
+    /// Reads a fully saved chunk back, or returns `None` if it was never
+    /// saved. Fails if the chunk was saved as a delta.
+    pub fn load_chunk(&self, x: i32, y: i32, z: i32) -> anyhow::Result<Option<Chunk>> {
+        match self.load_record(x, y, z)? {
+            Some(ChunkRecord::Blocks(chunk)) => Ok(Some(chunk)),
+            Some(ChunkRecord::Delta(_)) => {
+                bail!("Chunk ({x}, {y}, {z}) was saved as a delta")
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Reads whatever was saved for a chunk, or returns `None` if it was
+    /// never saved.
+    pub fn load_record(&self, x: i32, y: i32, z: i32) -> anyhow::Result<Option<ChunkRecord>> {
+        let path = self.region_path(x, y, z);
+        if !path.exists() {
+            return Ok(None);
+        }
+
+        let mut file =
+            File::open(&path).with_context(|| format!("Can't open region {}", path.display()))?;
+        let (sector, length) = read_header_entry(&mut file, header_slot(x, y, z))?;
+        if sector == 0 {
+            return Ok(None);
+        }
+
+        let mut payload = vec![0; length as usize];
+        file.seek(SeekFrom::Start(sector as u64 * SECTOR_SIZE))?;
+        file.read_exact(&mut payload)
+            .with_context(|| format!("Truncated chunk ({x}, {y}, {z}) in {}", path.display()))?;
+
+        let record = decode_record(x, y, z, &payload)
+            .with_context(|| format!("Corrupted chunk ({x}, {y}, {z}) in {}", path.display()))?;
+
+        Ok(Some(record))
+    }
+
+    /// Saves every block of the chunk.
+    pub fn save_chunk(&self, chunk: &Chunk) -> anyhow::Result<()> {
+        self.write_payload(chunk.x, chunk.y, chunk.z, &encode_blocks(chunk)?)
+    }
+
+    /// Saves only the changes of a chunk, to be replayed on generated terrain.
+    pub fn save_delta(&self, x: i32, y: i32, z: i32, delta: &ChunkDelta) -> anyhow::Result<()> {
+        self.write_payload(x, y, z, &encode_delta(delta)?)
+    }
+
+    fn write_payload(&self, x: i32, y: i32, z: i32, payload: &[u8]) -> anyhow::Result<()> {
+        let path = self.region_path(x, y, z);
+        std::fs::create_dir_all(&self.directory)?;
+
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Can't open region {}", path.display()))?;
+        if file.metadata()?.len() < HEADER_SECTORS as u64 * SECTOR_SIZE {
+            file.set_len(HEADER_SECTORS as u64 * SECTOR_SIZE)?;
+        }
+
+        let slot = header_slot(x, y, z);
+        let (old_sector, old_length) = read_header_entry(&mut file, slot)?;
+        let sector =
+            if old_sector != 0 && sectors_for(payload.len()) <= sectors_for(old_length as usize) {
+                old_sector
+            } else {
+                file.metadata()?.len().div_ceil(SECTOR_SIZE) as u32
+            };
+
+        file.seek(SeekFrom::Start(sector as u64 * SECTOR_SIZE))?;
+        file.write_all(payload)?;
+
+        let mut entry = [0; HEADER_ENTRY_SIZE];
+        entry[..4].copy_from_slice(&sector.to_le_bytes());
+        entry[4..].copy_from_slice(&(payload.len() as u32).to_le_bytes());
+        file.seek(SeekFrom::Start((slot * HEADER_ENTRY_SIZE) as u64))?;
+        file.write_all(&entry)?;
+
+        Ok(())
+    }
+
+    fn region_path(&self, x: i32, y: i32, z: i32) -> PathBuf {
+        self.directory.join(format!(
+            "r.{}.{}.{}.region",
+            x.div_euclid(REGION_SIZE),
+            y.div_euclid(REGION_SIZE),
+            z.div_euclid(REGION_SIZE)
+        ))
+    }
+}
+
+fn header_slot(x: i32, y: i32, z: i32) -> usize {
+    let (x, y, z) = (
+        x.rem_euclid(REGION_SIZE),
+        y.rem_euclid(REGION_SIZE),
+        z.rem_euclid(REGION_SIZE),
+    );
+    (x + y * REGION_SIZE + z * REGION_SIZE * REGION_SIZE) as usize
+}
+
+fn read_header_entry(file: &mut File, slot: usize) -> anyhow::Result<(u32, u32)> {
+    let mut entry = [0; HEADER_ENTRY_SIZE];
+    file.seek(SeekFrom::Start((slot * HEADER_ENTRY_SIZE) as u64))?;
+    file.read_exact(&mut entry)
+        .context("Truncated region header")?;
+
+    let sector = u32::from_le_bytes(entry[..4].try_into().unwrap());
+    let length = u32::from_le_bytes(entry[4..].try_into().unwrap());
+    if sector != 0 && sector < HEADER_SECTORS {
+        bail!("Chunk payload overlaps the region header");
+    }
+
+    Ok((sector, length))
+}
+
+fn sectors_for(length: usize) -> u64 {
+    (length as u64).div_ceil(SECTOR_SIZE)
+}
+
+// Payload layout before compression: a record tag (u8) followed by
+//  - blocks: palette length (u16), palette ids (u16 each), bits per entry (u8)
+//    and packed data words (u64 each)
+//  - delta: generator version (u32), seed (u32), change count (u16) and
+//    `(index, id)` pairs (u16 each)
+// and then by the fluid level count (u16) and `(index, level)` pairs (u16 and
+// u8). Chunks saved before fluid levels were stored end without them.
+fn encode_blocks(chunk: &Chunk) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+    let blocks = chunk.storage();
+
+    encoder.write_all(&[BLOCKS_TAG])?;
+    encoder.write_all(&(blocks.palette().len() as u16).to_le_bytes())?;
+    for id in blocks.palette() {
+        encoder.write_all(&id.to_le_bytes())?;
+    }
+    encoder.write_all(&[blocks.bits_per_entry() as u8])?;
+    for word in blocks.data() {
+        encoder.write_all(&word.to_le_bytes())?;
+    }
+    let fluid_levels: Vec<_> = chunk.fluid_levels().collect();
+    encode_fluid_levels(&mut encoder, &fluid_levels)?;
+
+    Ok(encoder.finish()?)
+}
+
+fn encode_delta(delta: &ChunkDelta) -> anyhow::Result<Vec<u8>> {
+    let mut encoder = ZlibEncoder::new(Vec::new(), Compression::default());
+
+    encoder.write_all(&[DELTA_TAG])?;
+    encoder.write_all(&delta.generator_version.to_le_bytes())?;
+    encoder.write_all(&delta.seed.to_le_bytes())?;
+    encoder.write_all(&(delta.changes().len() as u16).to_le_bytes())?;
+    for (index, id) in delta.changes() {
+        encoder.write_all(&index.to_le_bytes())?;
+        encoder.write_all(&id.to_le_bytes())?;
+    }
+    encode_fluid_levels(&mut encoder, delta.fluid_levels())?;
+
+    Ok(encoder.finish()?)
+}
+
+fn encode_fluid_levels(encoder: &mut impl Write, fluid_levels: &[(u16, u8)]) -> anyhow::Result<()> {
+    encoder.write_all(&(fluid_levels.len() as u16).to_le_bytes())?;
+    for (index, level) in fluid_levels {
+        encoder.write_all(&index.to_le_bytes())?;
+        encoder.write_all(&[*level])?;
+    }
+    Ok(())
+}
+
+fn decode_record(x: i32, y: i32, z: i32, payload: &[u8]) -> anyhow::Result<ChunkRecord> {
+    let mut bytes = Vec::new();
+    ZlibDecoder::new(payload).read_to_end(&mut bytes)?;
+
+    let mut reader = bytes.as_slice();
+    match read_u8(&mut reader)? {
+        BLOCKS_TAG => {
+            let blocks = decode_blocks(&mut reader)?;
+            let fluid_levels = decode_fluid_levels(reader)?;
+            let chunk = Chunk::from_blocks(x, y, z, blocks)
+                .context("Chunk has the wrong size")?
+                .with_fluid_levels(fluid_levels);
+            Ok(ChunkRecord::Blocks(chunk))
+        }
+        DELTA_TAG => Ok(ChunkRecord::Delta(decode_delta(reader)?)),
+        tag => bail!("Unknown record tag {tag}"),
+    }
+}
+
+fn decode_blocks(reader: &mut &[u8]) -> anyhow::Result<PaletteStorage> {
+    let mut palette: Vec<BlockId> = Vec::new();
+    for _ in 0..read_u16(reader)? {
+        palette.push(read_u16(reader)?);
+    }
+
+    let bits_per_entry = read_u8(reader)?;
+
+    let words = palette::words_for(bits_per_entry as u32, CHUNK_VOLUME);
+    ensure!(reader.len() >= words * 8, "Data words are truncated");
+    let (data, rest) = reader.split_at(words * 8);
+    *reader = rest;
+    let data = data
+        .chunks_exact(8)
+        .map(|word| u64::from_le_bytes(word.try_into().unwrap()))
+        .collect();
+
+    PaletteStorage::from_raw(palette, bits_per_entry as u32, data, CHUNK_VOLUME)
+}
+
+fn decode_delta(mut reader: &[u8]) -> anyhow::Result<ChunkDelta> {
+    let generator_version = read_u32(&mut reader)?;
+    let seed = read_u32(&mut reader)?;
+
+    let mut changes = Vec::new();
+    for _ in 0..read_u16(&mut reader)? {
+        let index = read_u16(&mut reader)?;
+        ensure!(
+            (index as usize) < CHUNK_VOLUME,
+            "Change {index} is outside of the chunk"
+        );
+        changes.push((index, read_u16(&mut reader)?));
+    }
+    let fluid_levels = decode_fluid_levels(reader)?;
+
+    Ok(ChunkDelta::new(generator_version, seed, changes).with_fluid_levels(fluid_levels))
+}
+
+// Chunks saved before fluid levels were stored have none
+fn decode_fluid_levels(mut reader: &[u8]) -> anyhow::Result<Vec<(u16, u8)>> {
+    let mut fluid_levels = Vec::new();
+    if reader.is_empty() {
+        return Ok(fluid_levels);
+    }
+
+    for _ in 0..read_u16(&mut reader)? {
+        let index = read_u16(&mut reader)?;
+        ensure!(
+            (index as usize) < CHUNK_VOLUME,
+            "Fluid level {index} is outside of the chunk"
+        );
+        fluid_levels.push((index, read_u8(&mut reader)?));
+    }
+    ensure!(reader.is_empty(), "Trailing bytes after the fluid levels");
+
+    Ok(fluid_levels)
+}
+
+fn read_u8(reader: &mut &[u8]) -> anyhow::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+fn read_u16(reader: &mut &[u8]) -> anyhow::Result<u16> {
+    let mut bytes = [0; 2];
+    reader.read_exact(&mut bytes)?;
+    Ok(u16::from_le_bytes(bytes))
+}
+
+fn read_u32(reader: &mut &[u8]) -> anyhow::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
@@ -0,0 +1,209 @@
+use super::block::{BlockId, Face, AIR_ID};
+use anyhow::{bail, Context};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::sync::OnceLock;
+
+const DEFINITIONS_FILE: &str = "blocks.json";
+
+static REGISTRY: OnceLock<BlockRegistry> = OnceLock::new();
+
+/// The block registry loaded from `res/blocks.json` on first use.
+pub fn registry() -> &'static BlockRegistry {
+    REGISTRY.get_or_init(|| {
+        let path = std::path::Path::new(env!("OUT_DIR"))
+            .join("res")
+            .join(DEFINITIONS_FILE);
+        BlockRegistry::load(&path).expect("Can't load block definitions!")
+    })
+}
+
+#[derive(Deserialize)]
+struct DefinitionsFile {
+    blocks: Vec<BlockDefinition>,
+}
+
+/// Texture files per face. More specific entries win over `sides` and `all`.
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct FaceTextures {
+    all: Option<String>,
+    sides: Option<String>,
+    top: Option<String>,
+    bottom: Option<String>,
+    front: Option<String>,
+    back: Option<String>,
+    right: Option<String>,
+    left: Option<String>,
+}
+
+impl FaceTextures {
+    fn get(&self, face: Face) -> Option<&String> {
+        let specific = match face {
+            Face::Top => return self.top.as_ref().or(self.all.as_ref()),
+            Face::Bottom => return self.bottom.as_ref().or(self.all.as_ref()),
+            Face::Front => &self.front,
+            Face::Back => &self.back,
+            Face::Right => &self.right,
+            Face::Left => &self.left,
+        };
+
+        specific
+            .as_ref()
+            .or(self.sides.as_ref())
+            .or(self.all.as_ref())
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct BlockDefinition {
+    pub name: String,
+    pub id: BlockId,
+    pub textures: FaceTextures,
+    #[serde(default = "default_opaque")]
+    pub opaque: bool,
+    /// Hides the faces between two blocks of this kind, like between water
+    /// blocks, even though they are see-through.
+    #[serde(default)]
+    pub cull_same: bool,
+    #[serde(default)]
+    pub hardness: f32,
+    #[serde(default)]
+    pub light_emission: u8,
+    /// Makes the block a fluid that flows, see `world::fluid`.
+    #[serde(default)]
+    pub fluid: Option<FluidDefinition>,
+    #[serde(skip)]
+    material_ids: [usize; 6],
+}
+
+/// How a fluid block flows.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct FluidDefinition {
+    /// Blocks the fluid spreads sideways from a source.
+    pub flow_distance: u8,
+    /// Ticks between two steps of the flow.
+    pub tick_rate: u32,
+    /// Blocks the fluid turns into when touching other fluids, by the names
+    /// of those fluids.
+    #[serde(default)]
+    pub reactions: HashMap<String, String>,
+}
+
+impl BlockDefinition {
+    pub fn get_material_id(&self, face: Face) -> usize {
+        self.material_ids[face as usize]
+    }
+}
+
+fn default_opaque() -> bool {
+    true
+}
+
+pub struct BlockRegistry {
+    definitions: Vec<Option<BlockDefinition>>,
+    ids_by_name: HashMap<String, BlockId>,
+    textures: Vec<String>,
+}
+
+impl BlockRegistry {
+    pub fn load(path: &std::path::Path) -> anyhow::Result<Self> {
+        let source =
+            std::fs::read_to_string(path).with_context(|| format!("reading {}", path.display()))?;
+        Self::from_json(&source).with_context(|| format!("parsing {}", path.display()))
+    }
+
+    pub fn from_json(source: &str) -> anyhow::Result<Self> {
+        let file: DefinitionsFile = serde_json::from_str(source)?;
+
+        let mut registry = BlockRegistry {
+            definitions: Vec::new(),
+            ids_by_name: HashMap::new(),
+            textures: Vec::new(),
+        };
+
+        for mut definition in file.blocks {
+            if definition.id == AIR_ID {
+                bail!(
+                    "block id {AIR_ID} is reserved for air ({})",
+                    definition.name
+                );
+            }
+            if registry.ids_by_name.contains_key(&definition.name) {
+                bail!("duplicate block name {}", definition.name);
+            }
+
+            for face in Face::ALL {
+                let texture = definition.textures.get(face).with_context(|| {
+                    format!("block {} has no {:?} texture", definition.name, face)
+                })?;
+                definition.material_ids[face as usize] = registry.material_id(texture);
+            }
+
+            if let Some(fluid) = &definition.fluid {
+                if fluid.tick_rate == 0 {
+                    bail!("fluid {} has a tick rate of 0", definition.name);
+                }
+            }
+
+            let index = definition.id as usize;
+            if registry.definitions.len() <= index {
+                registry.definitions.resize_with(index + 1, || None);
+            }
+            if registry.definitions[index].is_some() {
+                bail!("duplicate block id {}", definition.id);
+            }
+
+            registry
+                .ids_by_name
+                .insert(definition.name.clone(), definition.id);
+            registry.definitions[index] = Some(definition);
+        }
+
+        for definition in registry.definitions() {
+            let reactions = definition.fluid.iter().flat_map(|fluid| &fluid.reactions);
+            for (fluid, product) in reactions {
+                for name in [fluid, product] {
+                    if !registry.ids_by_name.contains_key(name) {
+                        bail!("block {} reacts with unknown block {name}", definition.name);
+                    }
+                }
+            }
+        }
+
+        Ok(registry)
+    }
+
+    pub fn get(&self, id: BlockId) -> Option<&BlockDefinition> {
+        self.definitions.get(id as usize)?.as_ref()
+    }
+
+    pub fn get_by_name(&self, name: &str) -> Option<&BlockDefinition> {
+        self.get(self.id_of(name)?)
+    }
+
+    pub fn id_of(&self, name: &str) -> Option<BlockId> {
+        self.ids_by_name.get(name).copied()
+    }
+
+    pub fn definitions(&self) -> impl Iterator<Item = &BlockDefinition> {
+        self.definitions.iter().flatten()
+    }
+
+    /// Texture files referenced by the definitions, indexed by material id.
+    pub fn textures(&self) -> &[String] {
+        &self.textures
+    }
+
+    fn material_id(&mut self, texture: &str) -> usize {
+        match self.textures.iter().position(|known| known == texture) {
+            Some(material_id) => material_id,
+            None => {
+                self.textures.push(texture.to_string());
+                self.textures.len() - 1
+            }
+        }
+    }
+}
@@ -0,0 +1,117 @@
+use super::block::Face;
+use super::cache::ChunkKey;
+use super::chunk::{block_index, block_position, Chunk, CHUNK_VOLUME};
+use std::collections::{HashSet, VecDeque};
+
+/// Which faces of a section can see each other through the see-through
+/// blocks inside it, as a six by six matrix of bits.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct FaceConnections(u64);
+
+impl FaceConnections {
+    pub const NONE: FaceConnections = FaceConnections(0);
+    pub const ALL: FaceConnections = FaceConnections((1 << 36) - 1);
+
+    pub fn connects(self, a: Face, b: Face) -> bool {
+        self.0 & bit(a, b) != 0
+    }
+
+    fn connect(&mut self, a: Face, b: Face) {
+        self.0 |= bit(a, b) | bit(b, a);
+    }
+}
+
+fn bit(a: Face, b: Face) -> u64 {
+    1 << (a as usize * Face::ALL.len() + b as usize)
+}
+
+impl Chunk {
+    /// Flood fills the see-through blocks of the section to find the faces
+    /// that connect through them.
+    pub fn face_connections(&self) -> FaceConnections {
+        if self.is_empty() {
+            return FaceConnections::ALL;
+        }
+
+        let see_through = |(x, y, z): (i32, i32, i32)| {
+            self.get_block(x, y, z)
+                .is_none_or(|block| block.is_transparent())
+        };
+        let mut connections = FaceConnections::NONE;
+        let mut visited = vec![false; CHUNK_VOLUME];
+        let mut stack = Vec::new();
+        for start in 0..CHUNK_VOLUME {
+            if visited[start] || !see_through(block_position(start)) {
+                continue;
+            }
+
+            // Every face that one pocket of see-through blocks touches can
+            // see every other
+            let mut faces = Vec::new();
+            visited[start] = true;
+            stack.push(start);
+            while let Some(index) = stack.pop() {
+                let (x, y, z) = block_position(index);
+                for face in Face::ALL {
+                    let (dx, dy, dz) = face.normal();
+                    let next = (x + dx, y + dy, z + dz);
+                    let Some(next_index) = block_index(next.0, next.1, next.2) else {
+                        if !faces.contains(&face) {
+                            faces.push(face);
+                        }
+                        continue;
+                    };
+                    if !visited[next_index] && see_through(next) {
+                        visited[next_index] = true;
+                        stack.push(next_index);
+                    }
+                }
+            }
+
+            for &a in &faces {
+                for &b in &faces {
+                    connections.connect(a, b);
+                }
+            }
+            if connections == FaceConnections::ALL {
+                break;
+            }
+        }
+        connections
+    }
+}
+
+/// Sections that can be seen from the section `start`, found by walking
+/// from section to section through faces that connect inside them. The walk
+/// never turns back towards `start`, so that it doesn't wind through caves
+/// into sections that can't be seen. Sections for which `in_range` returns
+/// `false` are left out.
+pub fn visible_sections(
+    start: ChunkKey,
+    mut in_range: impl FnMut(ChunkKey) -> bool,
+    mut connections: impl FnMut(ChunkKey) -> FaceConnections,
+) -> HashSet<ChunkKey> {
+    let mut visible = HashSet::from([start]);
+    // Sections to leave, the face they were entered through and the
+    // directions walked to reach them
+    let mut queue = VecDeque::from([(start, None, 0u8)]);
+    while let Some(((x, y, z), entered, directions)) = queue.pop_front() {
+        let section_connections = connections((x, y, z));
+        for face in Face::ALL {
+            if directions & 1 << face.opposite() as usize != 0 {
+                continue;
+            }
+            if entered.is_some_and(|entered| !section_connections.connects(entered, face)) {
+                continue;
+            }
+
+            let (dx, dy, dz) = face.normal();
+            let next = (x + dx, y + dy, z + dz);
+            if !in_range(next) || !visible.insert(next) {
+                continue;
+            }
+            queue.push_back((next, Some(face.opposite()), directions | 1 << face as usize));
+        }
+    }
+    visible
+}
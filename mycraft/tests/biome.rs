@@ -0,0 +1,157 @@
+use mycraft::world::biome::{get_biome_by_params, Biome};
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE};
+use mycraft::world::generator::{GeneratorPipeline, GeneratorPreset, TerrainStage};
+use std::collections::{HashMap, HashSet};
+
+const SEED: u32 = 5;
+
+// Top terrain block of every column in the area, by world coordinates
+fn heights(terrain: TerrainStage, chunks: i32) -> HashMap<(i32, i32), i32> {
+    let pipeline = GeneratorPipeline::empty().with_stage(terrain);
+    let mut heights = HashMap::new();
+    for x in 0..chunks {
+        for y in 0..chunks {
+            for z in -2..8 {
+                let (chunk, _) = pipeline.generate(SEED, x, y, z);
+                for ((i, j, k), _) in chunk.blocks() {
+                    let column = (x * CHUNK_SIZE + i, y * CHUNK_SIZE + j);
+                    let height = heights.entry(column).or_insert(i32::MIN);
+                    *height = (*height).max(z * CHUNK_SIZE + k);
+                }
+            }
+        }
+    }
+    heights
+}
+
+#[test]
+fn biomes_follow_climate_and_elevation() {
+    assert_eq!(get_biome_by_params(0.5, 0.5, -4), Biome::Ocean);
+    assert_eq!(get_biome_by_params(0.9, 0.1, 1), Biome::Beach);
+    assert_eq!(get_biome_by_params(0.5, 0.5, 30), Biome::Mountains);
+    assert_eq!(get_biome_by_params(0.1, 0.8, 10), Biome::Taiga);
+    assert_eq!(get_biome_by_params(0.1, 0.2, 10), Biome::Snow);
+    assert_eq!(get_biome_by_params(0.9, 0.2, 10), Biome::Desert);
+    assert_eq!(get_biome_by_params(0.5, 0.9, 3), Biome::Swamp);
+    assert_eq!(get_biome_by_params(0.5, 0.9, 10), Biome::Forest);
+    assert_eq!(get_biome_by_params(0.5, 0.2, 10), Biome::Plains);
+}
+
+#[test]
+fn biome_blocks_are_registered() {
+    for biome in Biome::ALL {
+        let properties = biome.properties();
+        for name in [
+            properties.surface_block,
+            properties.filler_block,
+            properties.underwater_block,
+        ] {
+            assert!(Block::from_name(name).is_some(), "{biome:?} uses {name}");
+        }
+        assert!((0.0..1.0).contains(&properties.tree_density));
+    }
+}
+
+#[test]
+fn worlds_span_many_biomes() {
+    let provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let biomes: HashSet<_> = (0..64)
+        .flat_map(|x| (0..64).map(move |y| (x * 64, y * 64)))
+        .filter_map(|(x, y)| provider.biome_at(x, y))
+        .collect();
+    assert!(biomes.len() >= 7, "{biomes:?}");
+
+    let flat = ChunkProvider::new(SEED, GeneratorPreset::Superflat(Default::default()));
+    assert_eq!(flat.biome_at(0, 0), None);
+}
+
+#[test]
+fn biomes_stay_the_same_once_their_columns_generate() {
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let biomes = |provider: &ChunkProvider| -> Vec<_> {
+        (-CHUNK_SIZE..CHUNK_SIZE)
+            .step_by(3)
+            .flat_map(|x| (-CHUNK_SIZE..CHUNK_SIZE).step_by(3).map(move |y| (x, y)))
+            .map(|(x, y)| provider.biome_at(x, y))
+            .collect()
+    };
+    let sampled = biomes(&provider);
+
+    for (x, y) in [(-1, -1), (-1, 0), (0, -1), (0, 0)] {
+        provider.get_chunk(x, y, 0);
+    }
+    assert_eq!(biomes(&provider), sampled);
+}
+
+#[test]
+fn surfaces_take_the_blocks_of_their_biome() {
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let terrain = TerrainStage::default();
+    let heights = heights(terrain.clone(), 2);
+
+    let (mut covered, mut matching) = (0, 0);
+    for (&(x, y), &height) in &heights {
+        let properties = provider.biome_at(x, y).unwrap().properties();
+        let expected = if height < terrain.sea_level {
+            properties.underwater_block
+        } else {
+            properties.surface_block
+        };
+
+        let chunk = provider.get_chunk(
+            x.div_euclid(CHUNK_SIZE),
+            y.div_euclid(CHUNK_SIZE),
+            height.div_euclid(CHUNK_SIZE),
+        );
+        let top = chunk.get_block(
+            x.rem_euclid(CHUNK_SIZE),
+            y.rem_euclid(CHUNK_SIZE),
+            height.rem_euclid(CHUNK_SIZE),
+        );
+        // Caves dig through some of the surface
+        let Some(top) = top else {
+            continue;
+        };
+        covered += 1;
+        if top.get_id() == Block::from_name(expected).unwrap().get_id() {
+            matching += 1;
+        }
+    }
+
+    // The stone showing through the surface takes the rest
+    assert!(covered * 2 > heights.len());
+    assert!(matching * 10 > covered * 9, "{matching} of {covered}");
+}
+
+#[test]
+fn heights_blend_across_biome_borders() {
+    let provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let biomes: HashMap<_, _> = (0..4 * CHUNK_SIZE)
+        .flat_map(|x| (0..4 * CHUNK_SIZE).map(move |y| (x, y)))
+        .map(|(x, y)| ((x, y), provider.biome_at(x, y)))
+        .collect();
+
+    let steepest_border = |terrain: TerrainStage| {
+        let heights = heights(terrain, 4);
+        let mut steepest = 0;
+        for (&(x, y), &height) in &heights {
+            for neighbour in [(x + 1, y), (x, y + 1)] {
+                let Some(&neighbour_height) = heights.get(&neighbour) else {
+                    continue;
+                };
+                if biomes[&(x, y)] != biomes[&neighbour] {
+                    steepest = steepest.max((height - neighbour_height).abs());
+                }
+            }
+        }
+        steepest
+    };
+
+    let blended = steepest_border(TerrainStage::default());
+    let sharp = steepest_border(TerrainStage {
+        biome_blend_radius: 0,
+        ..TerrainStage::default()
+    });
+    assert!(blended < sharp, "{blended} >= {sharp}");
+}
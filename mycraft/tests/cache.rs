@@ -0,0 +1,144 @@
+use mycraft::world::block::Block;
+use mycraft::world::cache::{CacheBudget, ChunkCache, EvictionPolicy};
+use mycraft::world::chunk::{Chunk, ChunkProvider};
+use mycraft::world::generator::GeneratorPreset;
+use mycraft::world::region::RegionStore;
+use std::sync::{Arc, Mutex};
+
+const SEED: u32 = 7;
+
+fn evicted_keys(cache: &mut ChunkCache) -> Vec<(i32, i32, i32)> {
+    let mut keys: Vec<_> = cache
+        .evict()
+        .into_iter()
+        .map(|chunk| (chunk.x, chunk.y, chunk.z))
+        .collect();
+    keys.sort();
+    keys
+}
+
+#[test]
+fn least_recently_used_chunks_are_evicted_first() {
+    let mut cache = ChunkCache::new(CacheBudget::Chunks(8), EvictionPolicy::LeastRecentlyUsed);
+    for x in 0..8 {
+        cache.touch(&(x, 0, 0));
+        cache.insert(Chunk::new(x, 0, 0));
+    }
+    cache.touch(&(0, 0, 0));
+
+    // Leaves room for one more chunk below seven eighths of the budget
+    assert_eq!(evicted_keys(&mut cache), vec![(1, 0, 0), (2, 0, 0)]);
+    assert_eq!(cache.len(), 6);
+    assert!(cache.evict().is_empty());
+}
+
+#[test]
+fn distant_chunks_are_evicted_first() {
+    let mut cache = ChunkCache::new(CacheBudget::Chunks(16), EvictionPolicy::Distance);
+    for x in -8..8 {
+        cache.insert(Chunk::new(x, 0, 0));
+    }
+    cache.set_focus((3, 0, 0));
+
+    assert_eq!(
+        evicted_keys(&mut cache),
+        vec![(-8, 0, 0), (-7, 0, 0), (-6, 0, 0)]
+    );
+}
+
+#[test]
+fn byte_budgets_bound_the_chunk_sizes() {
+    let stone = Block::from_name("stone").unwrap();
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let generated = provider.get_chunk(0, 0, 0);
+    let generated_size = generated.heap_size() + size_of::<Chunk>();
+    assert!(generated_size > size_of::<Chunk>());
+
+    let budget = 4 * generated_size;
+    let mut cache = ChunkCache::new(
+        CacheBudget::Bytes(budget),
+        EvictionPolicy::LeastRecentlyUsed,
+    );
+    for x in 0..64 {
+        cache.evict();
+        let mut chunk = Chunk::filled(x, 0, 0, stone);
+        for i in 0..16 {
+            chunk.set_block(i, 0, 0, Block::from_id(1 + (i % 8) as u16).unwrap());
+        }
+        cache.insert(chunk);
+    }
+
+    assert!(cache.len() < 64);
+    assert!(cache.metrics().evictions > 0);
+}
+
+#[test]
+fn chunks_growing_in_the_cache_count_against_byte_budgets() {
+    let empty_size = Chunk::new(0, 0, 0).heap_size() + size_of::<Chunk>();
+    let mut cache = ChunkCache::new(
+        CacheBudget::Bytes(8 * empty_size),
+        EvictionPolicy::LeastRecentlyUsed,
+    );
+    for x in 0..4 {
+        cache.insert(Chunk::new(x, 0, 0));
+    }
+    assert!(cache.evict().is_empty());
+
+    // Filled in after loading, like light and fluid
+    for x in 0..4 {
+        let chunk = cache.get_mut(&(x, 0, 0)).unwrap();
+        for i in 0..16 {
+            chunk.set_block(i, 0, 0, Block::from_id(1 + (i % 8) as u16).unwrap());
+        }
+    }
+    assert!(!cache.evict().is_empty());
+}
+
+#[test]
+fn metrics_count_hits_misses_and_evictions() {
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    provider.set_cache_budget(CacheBudget::Chunks(4));
+
+    for x in 0..4 {
+        provider.get_chunk(x, 0, 0);
+    }
+    provider.get_chunk(0, 0, 0);
+    provider.get_chunk(1, 0, 0);
+    provider.get_chunk(4, 0, 0);
+
+    let metrics = provider.cache_metrics();
+    assert_eq!((metrics.hits, metrics.misses), (2, 5));
+    assert!(metrics.evictions > 0);
+    assert!(provider.loaded_chunk_count() <= 4);
+}
+
+#[test]
+fn evicted_chunks_are_saved_and_handed_to_the_hook() {
+    let directory = tempfile::tempdir().unwrap();
+    // Not found underground, so that placing it is always an edit
+    let log = Block::from_name("tree_log").unwrap();
+    let mut provider = ChunkProvider::with_region_store(
+        SEED,
+        GeneratorPreset::Default,
+        RegionStore::new(directory.path()),
+    );
+    provider.set_cache_budget(CacheBudget::Chunks(2));
+    provider.set_eviction_policy(EvictionPolicy::Distance);
+    provider.set_focus(0, 0, 0);
+
+    let evicted = Arc::new(Mutex::new(Vec::new()));
+    let hook_evicted = evicted.clone();
+    provider.set_eviction_hook(move |chunk| hook_evicted.lock().unwrap().push(chunk.x));
+
+    provider.get_chunk_mut(5, 0, 0).set_block(1, 1, 1, log);
+    provider.get_chunk(0, 0, 0);
+    provider.get_chunk(1, 0, 0);
+
+    assert_eq!(*evicted.lock().unwrap(), vec![5]);
+    assert!(provider.get_loaded_chunk(5, 0, 0).is_none());
+    let saved = RegionStore::new(directory.path())
+        .load_chunk(5, 0, 0)
+        .unwrap()
+        .unwrap();
+    assert_eq!(saved.get_block(1, 1, 1).unwrap().get_id(), log.get_id());
+}
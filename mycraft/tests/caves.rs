@@ -0,0 +1,207 @@
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE, WORLD_BOTTOM};
+use mycraft::world::generator::{
+    CaveStage, CheeseCaves, GeneratorPipeline, GeneratorPreset, TunnelCarver,
+};
+use std::collections::HashSet;
+
+mod common;
+
+const SEED: u32 = 3;
+
+// Caves dug into solid stone up to z = 95, so that nothing but the caves
+// leaves air below it
+const LAYERS: &[(&str, i32)] = &[("stone", 96)];
+
+fn without_caves() -> CaveStage {
+    CaveStage {
+        worms: TunnelCarver {
+            per_chunk: 0.0,
+            ..TunnelCarver::default()
+        },
+        cheese: CheeseCaves {
+            max_height: WORLD_BOTTOM - 1,
+            ..CheeseCaves::default()
+        },
+        ravines: TunnelCarver {
+            per_chunk: 0.0,
+            ..TunnelCarver::default()
+        },
+        water_cover: 0,
+    }
+}
+
+// World coordinates of the air blocks below z = 96 in the sections
+fn air(pipeline: &GeneratorPipeline, sections: &[(i32, i32, i32)]) -> HashSet<(i32, i32, i32)> {
+    let mut air = HashSet::new();
+    for &(x, y, z) in sections {
+        let (chunk, _) = pipeline.generate(SEED, x, y, z);
+        for i in 0..CHUNK_SIZE {
+            for j in 0..CHUNK_SIZE {
+                for k in 0..CHUNK_SIZE {
+                    let world_z = z * CHUNK_SIZE + k;
+                    if world_z < 96 && chunk.get_block(i, j, k).is_none() {
+                        air.insert((x * CHUNK_SIZE + i, y * CHUNK_SIZE + j, world_z));
+                    }
+                }
+            }
+        }
+    }
+    air
+}
+
+#[test]
+fn cave_walls_match_up_across_chunk_seams() {
+    let pipeline = common::layered_pipeline(
+        LAYERS,
+        CaveStage {
+            worms: TunnelCarver {
+                per_chunk: 4.0,
+                ..TunnelCarver::default()
+            },
+            ..without_caves()
+        },
+    );
+
+    // Air on either side of the seams between x = 15 and x = 16
+    let (mut matching, mut seam_blocks) = (0, 0);
+    for y in 0..2 {
+        for z in 0..3 {
+            let left = air(&pipeline, &[(0, y, z)]);
+            let right = air(&pipeline, &[(1, y, z)]);
+            let face = |air: &HashSet<(i32, i32, i32)>, x: i32| -> HashSet<(i32, i32)> {
+                air.iter()
+                    .filter(|block| block.0 == x)
+                    .map(|&(_, y, z)| (y, z))
+                    .collect()
+            };
+            let (left, right) = (face(&left, CHUNK_SIZE - 1), face(&right, CHUNK_SIZE));
+
+            // Tunnels are smooth, so air on one side of the seam has air
+            // right next to it on the other side
+            let near = |face: &HashSet<(i32, i32)>, &(y, z): &(i32, i32)| {
+                (-1..=1).any(|dy| (-1..=1).any(|dz| face.contains(&(y + dy, z + dz))))
+            };
+            matching += left.iter().filter(|block| near(&right, block)).count();
+            matching += right.iter().filter(|block| near(&left, block)).count();
+            seam_blocks += left.len() + right.len();
+        }
+    }
+
+    // Give or take the tapered ends of tunnels and tunnels running along the
+    // seam
+    assert!(seam_blocks > 100, "{seam_blocks}");
+    assert!(
+        matching * 10 >= seam_blocks * 9,
+        "{matching} of {seam_blocks} seam blocks match"
+    );
+}
+
+#[test]
+fn tunnels_are_carved_the_same_whatever_section_generates_them() {
+    let pipeline = common::layered_pipeline(
+        LAYERS,
+        CaveStage {
+            worms: TunnelCarver {
+                per_chunk: 4.0,
+                ..TunnelCarver::default()
+            },
+            ..without_caves()
+        },
+    );
+    let sections = [(0, 0, 1), (1, 0, 1), (0, 1, 1), (1, 1, 1)];
+    let first = air(&pipeline, &sections);
+    let reversed: Vec<_> = sections.iter().rev().copied().collect();
+    assert_eq!(first, air(&pipeline, &reversed));
+    assert!(!first.is_empty());
+}
+
+#[test]
+fn every_carver_digs_its_own_kind_of_cave() {
+    let sections: Vec<_> = (0..4)
+        .flat_map(|x| (0..4).flat_map(move |y| (-2..2).map(move |z| (x, y, z))))
+        .collect();
+    assert!(air(
+        &common::layered_pipeline(LAYERS, without_caves()),
+        &sections
+    )
+    .is_empty());
+
+    let worms = air(
+        &common::layered_pipeline(
+            LAYERS,
+            CaveStage {
+                worms: TunnelCarver::default(),
+                ..without_caves()
+            },
+        ),
+        &sections,
+    );
+    let cheese = air(
+        &common::layered_pipeline(
+            LAYERS,
+            CaveStage {
+                cheese: CheeseCaves::default(),
+                ..without_caves()
+            },
+        ),
+        &sections,
+    );
+    let ravines = air(
+        &common::layered_pipeline(
+            LAYERS,
+            CaveStage {
+                ravines: TunnelCarver {
+                    per_chunk: 1.0,
+                    ..CaveStage::default().ravines
+                },
+                ..without_caves()
+            },
+        ),
+        &sections,
+    );
+
+    assert!(!worms.is_empty());
+    assert!(!cheese.is_empty());
+    assert!(cheese
+        .iter()
+        .all(|&(_, _, z)| z <= CheeseCaves::default().max_height));
+
+    // Ravines are deep and narrow: columns of air run far deeper than tunnels
+    let deepest = |air: &HashSet<(i32, i32, i32)>| {
+        air.iter()
+            .map(|&(x, y, z)| (z..).take_while(|&z| air.contains(&(x, y, z))).count())
+            .max()
+            .unwrap_or(0)
+    };
+    assert!(
+        deepest(&ravines) > 2 * deepest(&worms),
+        "{} <= {}",
+        deepest(&ravines),
+        deepest(&worms)
+    );
+}
+
+#[test]
+fn caves_leave_the_ground_under_water_whole() {
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let water = mycraft::world::block::Block::from_name("water")
+        .unwrap()
+        .get_id();
+
+    let mut checked = 0;
+    for x in -4..4 {
+        for y in -4..4 {
+            for z in 0..2 {
+                let chunk = provider.get_chunk(x, y, z);
+                for ((i, j, k), block) in chunk.blocks() {
+                    if block.get_id() != water || k == 0 {
+                        continue;
+                    }
+                    checked += 1;
+                    assert!(chunk.get_block(i, j, k - 1).is_some(), "air under water");
+                }
+            }
+        }
+    }
+    assert!(checked > 0);
+}
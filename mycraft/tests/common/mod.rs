@@ -0,0 +1,63 @@
+//! Worlds shared by the integration tests. Each test only uses some of them.
+#![allow(dead_code)]
+
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE, WORLD_BOTTOM};
+use mycraft::world::generator::{
+    FlatLayer, FlatStage, GeneratorPipeline, GeneratorPreset, GeneratorStage,
+};
+use mycraft::world::World;
+use std::ops::RangeInclusive;
+
+/// An empty world with a stone floor at z = 0 over `columns` by `columns`
+/// chunks from the origin, where the `sections` of each column are loaded.
+pub fn floored_world(columns: i32, sections: RangeInclusive<i32>) -> World {
+    floored_world_in(
+        ChunkProvider::new(0, GeneratorPreset::Void),
+        columns,
+        sections,
+    )
+}
+
+/// Like `floored_world`, but loads the chunks from `provider`.
+pub fn floored_world_in(
+    provider: ChunkProvider,
+    columns: i32,
+    sections: RangeInclusive<i32>,
+) -> World {
+    let mut world = World::new(provider);
+    for x in 0..columns {
+        for y in 0..columns {
+            for z in sections.clone() {
+                world.load_chunk(x, y, z);
+            }
+        }
+    }
+    let stone = Block::from_name("stone").unwrap();
+    for x in 0..columns * CHUNK_SIZE {
+        for y in 0..columns * CHUNK_SIZE {
+            world.set_block(x, y, 0, stone);
+        }
+    }
+    world
+}
+
+/// Flat terrain of `layers` from the bottom of the world up, each given by
+/// its block and the height it reaches, followed by `stage`.
+pub fn layered_pipeline(
+    layers: &[(&str, i32)],
+    stage: impl GeneratorStage + 'static,
+) -> GeneratorPipeline {
+    let mut bottom = WORLD_BOTTOM;
+    let layers = layers
+        .iter()
+        .map(|&(block, top)| {
+            let layer = FlatLayer::new(block, top - bottom);
+            bottom = top;
+            layer
+        })
+        .collect();
+    GeneratorPipeline::empty()
+        .with_stage(FlatStage { layers })
+        .with_stage(stage)
+}
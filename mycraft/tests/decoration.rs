@@ -0,0 +1,211 @@
+use mycraft::world::block::{Block, AIR_ID};
+use mycraft::world::cache::ChunkKey;
+use mycraft::world::chunk::{block_index, Chunk, ChunkProvider};
+use mycraft::world::decoration::{replaces, BlockWrite, PendingWrites};
+use mycraft::world::generator::GeneratorPreset;
+use mycraft::world::region::{ChunkRecord, RegionStore, SaveMode};
+use mycraft::world::World;
+
+const SEED: u32 = 7;
+
+fn area() -> Vec<(i32, i32, i32)> {
+    let mut keys = Vec::new();
+    for x in 3..7 {
+        for y in 3..7 {
+            for z in 0..3 {
+                keys.push((x, y, z));
+            }
+        }
+    }
+    keys
+}
+
+fn block_ids(provider: &ChunkProvider, (x, y, z): (i32, i32, i32)) -> Vec<((i32, i32, i32), u16)> {
+    provider
+        .get_loaded_chunk(x, y, z)
+        .unwrap()
+        .blocks()
+        .map(|(position, block)| (position, block.get_id()))
+        .collect()
+}
+
+// A block that the decoration of one chunk of the area placed in another,
+// along with the chunk that placed it
+fn border_decoration(provider: &ChunkProvider) -> (ChunkKey, BlockWrite) {
+    area()
+        .into_iter()
+        .flat_map(|(x, y, z)| {
+            let (_, overflow) = Chunk::generate_decorated(SEED, x, y, z);
+            overflow.into_iter().map(move |write| ((x, y, z), write))
+        })
+        .find(|(_, write)| {
+            let ((x, y, z), (i, j, k)) = write.target();
+            provider
+                .get_loaded_chunk(x, y, z)
+                .and_then(|chunk| chunk.get_block(i, j, k))
+                .is_some_and(|block| block.get_id() == write.id)
+        })
+        .expect("no structure crossed a chunk border")
+}
+
+#[test]
+fn decoration_does_not_depend_on_generation_order() {
+    let keys = area();
+    let mut forward = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let mut backward = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let mut interleaved = ChunkProvider::new(SEED, GeneratorPreset::Default);
+
+    for key in &keys {
+        forward.get_chunk(key.0, key.1, key.2);
+    }
+    for key in keys.iter().rev() {
+        backward.get_chunk(key.0, key.1, key.2);
+    }
+    for key in keys.iter().step_by(2).chain(keys.iter().skip(1).step_by(2)) {
+        interleaved.get_chunk(key.0, key.1, key.2);
+    }
+
+    for key in keys {
+        let expected = block_ids(&forward, key);
+        assert_eq!(expected, block_ids(&backward, key), "chunk {key:?} differs");
+        assert_eq!(
+            expected,
+            block_ids(&interleaved, key),
+            "chunk {key:?} differs"
+        );
+    }
+}
+
+#[test]
+fn structures_are_completed_across_chunk_borders() {
+    let mut world = World::new(ChunkProvider::new(SEED, GeneratorPreset::Default));
+    for (x, y, z) in area() {
+        world.load_chunk(x, y, z);
+    }
+
+    let mut checked = 0;
+    for (x, y, z) in area() {
+        let (_, overflow) = Chunk::generate_decorated(SEED, x, y, z);
+        for write in overflow {
+            let ((chunk_x, chunk_y, chunk_z), _) = write.target();
+            if world.get_chunk(chunk_x, chunk_y, chunk_z).is_none() {
+                continue;
+            }
+
+            let placed = world
+                .get_block(write.x, write.y, write.z)
+                .map_or(0, |block| block.get_id());
+            assert!(
+                !replaces(placed, write.id),
+                "block at ({}, {}, {}) was never placed",
+                write.x,
+                write.y,
+                write.z
+            );
+            checked += 1;
+        }
+    }
+
+    assert!(checked > 0, "no structure crossed a chunk border");
+}
+
+#[test]
+fn neighbour_decoration_marks_loaded_chunks_dirty() {
+    let (source, target) = area()
+        .into_iter()
+        .find_map(|(x, y, z)| {
+            let (_, overflow) = Chunk::generate_decorated(SEED, x, y, z);
+            overflow.first().map(|write| ((x, y, z), write.target().0))
+        })
+        .unwrap();
+
+    let mut world = World::new(ChunkProvider::new(SEED, GeneratorPreset::Default));
+    world.load_chunk(target.0, target.1, target.2);
+    world.take_dirty_chunks();
+
+    world.load_chunk(source.0, source.1, source.2);
+    assert!(world.take_dirty_chunks().contains(&target));
+}
+
+#[test]
+fn removed_border_decoration_stays_removed_after_reloading() {
+    for mode in [SaveMode::Full, SaveMode::Deltas] {
+        let directory = tempfile::tempdir().unwrap();
+        let store = || RegionStore::with_mode(directory.path(), mode);
+        let provider = || ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+        let mut first = provider();
+        for (x, y, z) in area() {
+            first.get_chunk(x, y, z);
+        }
+        let (source, write) = border_decoration(&first);
+        let ((x, y, z), (i, j, k)) = write.target();
+        first.get_chunk_mut(x, y, z).remove_block(i, j, k);
+        first.save_all();
+
+        // The neighbour comes first, so that its writes are queued when the
+        // edited chunk loads
+        let mut second = provider();
+        second.get_chunk(source.0, source.1, source.2);
+        let chunk = second.get_chunk(x, y, z);
+        assert!(chunk.get_block(i, j, k).is_none(), "{mode:?}");
+    }
+}
+
+#[test]
+fn deltas_only_hold_player_edits() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = RegionStore::with_mode(directory.path(), SaveMode::Deltas);
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store);
+    for (x, y, z) in area() {
+        provider.get_chunk(x, y, z);
+    }
+    let (_, write) = border_decoration(&provider);
+    let ((x, y, z), (i, j, k)) = write.target();
+    provider.get_chunk_mut(x, y, z).remove_block(i, j, k);
+    provider.save_all();
+
+    let record = RegionStore::new(directory.path())
+        .load_record(x, y, z)
+        .unwrap();
+    let Some(ChunkRecord::Delta(delta)) = record else {
+        panic!("chunk ({x}, {y}, {z}) wasn't saved as a delta");
+    };
+    let index = block_index(i, j, k).unwrap() as u16;
+    assert_eq!(delta.changes(), [(index, AIR_ID)]);
+}
+
+#[test]
+fn logs_push_through_leaves_but_not_terrain() {
+    let id = |name| Block::from_name(name).unwrap().get_id();
+
+    assert!(replaces(0, id("tree_leaf")));
+    assert!(replaces(id("tree_leaf"), id("tree_log")));
+    assert!(!replaces(id("tree_log"), id("tree_leaf")));
+    assert!(!replaces(id("stone"), id("tree_log")));
+    assert!(!replaces(id("dirt"), id("tree_leaf")));
+}
+
+#[test]
+fn pending_writes_are_replaced_and_dropped_per_source() {
+    let leaf = Block::from_name("tree_leaf").unwrap().get_id();
+    let write = |x| BlockWrite {
+        x,
+        y: 0,
+        z: 0,
+        id: leaf,
+    };
+    let mut pending = PendingWrites::new();
+
+    pending.insert((0, 0, 0), vec![write(16), write(17), write(-1)]);
+    pending.insert((2, 0, 0), vec![write(31)]);
+    assert_eq!(pending.for_target((1, 0, 0)).count(), 3);
+    assert_eq!(pending.for_target((-1, 0, 0)).count(), 1);
+
+    pending.insert((0, 0, 0), vec![write(18)]);
+    assert_eq!(pending.for_target((1, 0, 0)).count(), 2);
+    assert_eq!(pending.for_target((-1, 0, 0)).count(), 0);
+
+    pending.remove_source((0, 0, 0));
+    pending.remove_source((2, 0, 0));
+    assert!(pending.is_empty());
+}
@@ -0,0 +1,194 @@
+use mycraft::world::block::Block;
+use mycraft::world::cache::{CacheBudget, EvictionPolicy};
+use mycraft::world::chunk::ChunkProvider;
+use mycraft::world::generator::GeneratorPreset;
+use mycraft::world::region::{RegionStore, SaveMode};
+use mycraft::world::World;
+
+mod common;
+
+fn settle(world: &mut World) {
+    for _ in 0..1000 {
+        if world.fluids().is_settled() {
+            return;
+        }
+        world.tick();
+    }
+    panic!("Fluid didn't settle");
+}
+
+fn name_at(world: &World, x: i32, y: i32, z: i32) -> Option<&'static str> {
+    world.get_block(x, y, z).map(|block| block.get_name())
+}
+
+#[test]
+fn sources_spread_with_decreasing_levels() {
+    let mut world = common::floored_world(2, 0..=1);
+    let water = Block::from_name("water").unwrap();
+    let flow_distance = water.get_fluid().unwrap().flow_distance as i32;
+    world.set_block(16, 16, 1, water);
+    settle(&mut world);
+
+    assert_eq!(world.fluid_level(16, 16, 1), Some(0));
+    for distance in 1..=flow_distance {
+        assert_eq!(
+            world.fluid_level(16 + distance, 16, 1),
+            Some(distance as u8)
+        );
+        assert_eq!(
+            world.fluid_level(16, 16 - distance, 1),
+            Some(distance as u8)
+        );
+    }
+    assert_eq!(world.fluid_level(16 + flow_distance + 1, 16, 1), None);
+    assert_eq!(world.fluid_level(16, 16, 2), None);
+
+    // Levels follow the shortest way around the source
+    assert_eq!(world.fluid_level(18, 17, 1), Some(3));
+}
+
+#[test]
+fn fluid_falls_before_spreading() {
+    let mut world = common::floored_world(2, 0..=1);
+    let water = Block::from_name("water").unwrap();
+    world.set_block(8, 8, 6, water);
+    settle(&mut world);
+
+    for z in 1..6 {
+        assert_eq!(name_at(&world, 8, 8, z), Some("water"), "at {z}");
+        if z > 1 {
+            assert_eq!(name_at(&world, 9, 8, z), None, "at {z}");
+        }
+    }
+    assert_eq!(name_at(&world, 9, 8, 1), Some("water"));
+}
+
+#[test]
+fn flowing_fluid_dries_up_without_its_source() {
+    let mut world = common::floored_world(2, 0..=1);
+    let water = Block::from_name("water").unwrap();
+    world.set_block(16, 16, 1, water);
+    settle(&mut world);
+    assert!(world.get_block(20, 16, 1).is_some());
+
+    world.remove_block(16, 16, 1);
+    settle(&mut world);
+    for x in 0..32 {
+        for y in 0..32 {
+            assert!(world.get_block(x, y, 1).is_none(), "water left at {x}, {y}");
+        }
+    }
+}
+
+#[test]
+fn edits_wake_resting_fluid() {
+    let mut world = common::floored_world(2, 0..=1);
+    let water = Block::from_name("water").unwrap();
+    let stone = Block::from_name("stone").unwrap();
+
+    // A pool held in by a wall
+    for y in 0..32 {
+        world.set_block(10, y, 1, stone);
+    }
+    world.set_block(5, 5, 1, water);
+    settle(&mut world);
+    assert!(world.get_block(11, 5, 1).is_none());
+
+    world.remove_block(10, 5, 1);
+    assert!(!world.fluids().is_settled());
+    settle(&mut world);
+    assert_eq!(name_at(&world, 11, 5, 1), Some("water"));
+}
+
+#[test]
+fn lava_turns_into_stone_next_to_water() {
+    let mut world = common::floored_world(2, 0..=1);
+    let water = Block::from_name("water").unwrap();
+    let lava = Block::from_name("lava").unwrap();
+    world.set_block(4, 4, 1, lava);
+    world.set_block(12, 4, 1, water);
+    settle(&mut world);
+
+    // The flows meet between the sources and harden where they touch
+    let row: Vec<_> = (4..=12).map(|x| name_at(&world, x, 4, 1)).collect();
+    assert!(row.contains(&Some("stone")), "{row:?}");
+    for pair in row.windows(2) {
+        assert_ne!(pair, [Some("lava"), Some("water")], "{row:?}");
+        assert_ne!(pair, [Some("water"), Some("lava")], "{row:?}");
+    }
+}
+
+#[test]
+fn fluid_changes_are_batched_into_dirty_chunks() {
+    let mut world = common::floored_world(2, 0..=1);
+    world.take_dirty_chunks();
+    world.set_block(15, 15, 1, Block::from_name("water").unwrap());
+    settle(&mut world);
+
+    // The flood crossed into every chunk around the corner, each remeshed once
+    let dirty = world.take_dirty_chunks();
+    for chunk in [(0, 0, 0), (0, 1, 0), (1, 0, 0), (1, 1, 0)] {
+        assert_eq!(dirty.iter().filter(|&&key| key == chunk).count(), 1);
+    }
+    assert!(world.take_dirty_chunks().is_empty());
+}
+
+#[test]
+fn flow_levels_survive_saving() {
+    for mode in [SaveMode::Full, SaveMode::Deltas] {
+        let directory = tempfile::tempdir().unwrap();
+        let store = || RegionStore::with_mode(directory.path(), mode);
+        let provider = ChunkProvider::with_region_store(0, GeneratorPreset::Void, store());
+        let mut world = common::floored_world_in(provider, 2, 0..=1);
+        world.set_block(16, 16, 1, Block::from_name("water").unwrap());
+        settle(&mut world);
+        world.save();
+
+        let mut world = World::new(ChunkProvider::with_region_store(
+            0,
+            GeneratorPreset::Void,
+            store(),
+        ));
+        for x in 0..2 {
+            for y in 0..2 {
+                world.load_chunk(x, y, 0);
+            }
+        }
+        assert_eq!(world.fluid_level(16, 16, 1), Some(0), "{mode:?}");
+        assert_eq!(world.fluid_level(19, 16, 1), Some(3), "{mode:?}");
+
+        // The flow still knows where it came from
+        world.remove_block(16, 16, 1);
+        settle(&mut world);
+        assert!(world.get_block(19, 16, 1).is_none(), "{mode:?}");
+    }
+}
+
+#[test]
+fn fluid_waits_for_unloaded_neighbours() {
+    let mut world = common::floored_world(2, 0..=1);
+    world.set_block(31, 5, 1, Block::from_name("water").unwrap());
+    settle(&mut world);
+    assert!(world.get_chunk(2, 0, 0).is_none());
+
+    world.load_chunk(2, 0, 0);
+    assert!(!world.fluids().is_settled());
+    settle(&mut world);
+    assert_eq!(world.fluid_level(32, 5, 1), Some(1));
+}
+
+#[test]
+fn unloaded_sections_drop_their_updates() {
+    let mut provider = ChunkProvider::new(0, GeneratorPreset::Void);
+    provider.set_cache_budget(CacheBudget::Chunks(8));
+    provider.set_eviction_policy(EvictionPolicy::LeastRecentlyUsed);
+    let mut world = World::new(provider);
+    world.set_block(5, 5, 5, Block::from_name("water").unwrap());
+    assert!(!world.fluids().is_settled());
+
+    for x in 0..16 {
+        world.load_chunk(100 + x, 0, 0);
+    }
+    assert!(world.get_chunk(0, 0, 0).is_none());
+    assert!(world.fluids().is_settled());
+}
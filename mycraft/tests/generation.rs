@@ -0,0 +1,119 @@
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{Chunk, ChunkProvider, CHUNK_SIZE};
+use mycraft::world::generation::GenerationPool;
+use mycraft::world::generator::GeneratorPreset;
+use mycraft::world::region::{RegionStore, SaveMode};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+const SEED: u32 = 7;
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+fn receive_all(provider: &mut ChunkProvider) -> Vec<(i32, i32, i32)> {
+    let started = Instant::now();
+    let mut received = Vec::new();
+    while provider.requested_chunk_count() > 0 {
+        assert!(started.elapsed() < TIMEOUT, "chunks never arrived");
+        received.extend(provider.receive_chunks());
+        std::thread::sleep(Duration::from_millis(1));
+    }
+    received
+}
+
+#[test]
+fn requested_chunks_match_synchronous_generation() {
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let mut expected = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    for x in 0..3 {
+        provider.request_chunk(x, 0, 0);
+        provider.request_chunk(x, 0, 0);
+    }
+
+    let mut received = receive_all(&mut provider);
+    received.sort();
+    assert_eq!(received, vec![(0, 0, 0), (1, 0, 0), (2, 0, 0)]);
+
+    for x in 0..3 {
+        let chunk = provider.get_loaded_chunk(x, 0, 0).unwrap();
+        let expected = expected.get_chunk(x, 0, 0);
+        assert!(chunk
+            .blocks()
+            .map(|(position, block)| (position, block.get_id()))
+            .eq(expected
+                .blocks()
+                .map(|(position, block)| (position, block.get_id()))));
+    }
+}
+
+#[test]
+fn nearest_requests_are_generated_first() {
+    let gate = Arc::new(Mutex::new(()));
+    let order = Arc::new(Mutex::new(Vec::new()));
+
+    let held = gate.lock().unwrap();
+    let pool = {
+        let gate = gate.clone();
+        let order = order.clone();
+        GenerationPool::new(1, move |x, y, z| {
+            drop(gate.lock().unwrap());
+            order.lock().unwrap().push(x);
+            Chunk::new(x, y, z)
+        })
+    };
+
+    // The only worker blocks on the first job while the rest queue up
+    pool.request(100, 0, 0);
+    while pool.queued() > 0 {
+        std::thread::yield_now();
+    }
+    for x in [9, -3, 5, 1, -7] {
+        pool.request(x, 0, 0);
+    }
+    pool.set_focus((4, 0, 0));
+    drop(held);
+
+    let started = Instant::now();
+    let mut received = 0;
+    while received < 6 {
+        assert!(started.elapsed() < TIMEOUT, "chunks never arrived");
+        received += std::iter::from_fn(|| pool.try_recv()).count();
+    }
+    assert_eq!(*order.lock().unwrap(), vec![100, 5, 1, 9, -3, -7]);
+}
+
+#[test]
+fn cancelled_requests_are_not_received() {
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    for x in 0..8 {
+        provider.request_chunk(x, 0, 0);
+    }
+    provider.cancel_requests(|(x, _, _)| *x < 4);
+
+    let mut received = receive_all(&mut provider);
+    received.sort();
+    assert_eq!(received, vec![(0, 0, 0), (1, 0, 0), (2, 0, 0), (3, 0, 0)]);
+    assert!(provider.get_loaded_chunk(5, 0, 0).is_none());
+}
+
+#[test]
+fn generated_chunks_keep_saved_edits() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = || RegionStore::with_mode(directory.path(), SaveMode::Deltas);
+    let sand = Block::from_name("sand").unwrap();
+
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    provider
+        .get_chunk_mut(0, 0, 0)
+        .set_block(CHUNK_SIZE - 1, 0, 0, sand);
+    provider.unload_chunk(0, 0, 0);
+
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    provider.request_chunk(0, 0, 0);
+    assert_eq!(receive_all(&mut provider), vec![(0, 0, 0)]);
+
+    let chunk = provider.get_loaded_chunk(0, 0, 0).unwrap();
+    assert_eq!(
+        chunk.get_block(CHUNK_SIZE - 1, 0, 0).unwrap().get_id(),
+        sand.get_id()
+    );
+}
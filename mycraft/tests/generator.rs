@@ -0,0 +1,252 @@
+use mycraft::world::block::{Block, BlockId};
+use mycraft::world::chunk::{Chunk, ChunkProvider, CHUNK_SIZE, WORLD_BOTTOM};
+use mycraft::world::generator::{
+    FlatLayer, FlatStage, GenerationContext, GeneratorPipeline, GeneratorPreset, GeneratorStage,
+    TreeStage,
+};
+use mycraft::world::region::{RegionStore, SaveMode};
+
+const SEED: u32 = 7;
+
+// Covers a layer of each section below `height` with `block`
+struct LayerStage {
+    block: &'static str,
+    height: i32,
+}
+
+impl GeneratorStage for LayerStage {
+    fn name(&self) -> &str {
+        "layer"
+    }
+
+    fn generate(&self, context: &mut GenerationContext) {
+        let id = Block::from_name(self.block).unwrap().get_id();
+        if context.bottom() >= self.height {
+            return;
+        }
+        for x in 0..CHUNK_SIZE {
+            for y in 0..CHUNK_SIZE {
+                context.set(x, y, 0, id);
+            }
+        }
+    }
+}
+
+// Digs the surface block out of every other column, like a cave reaching
+// the surface
+struct HoleStage;
+
+impl GeneratorStage for HoleStage {
+    fn name(&self) -> &str {
+        "holes"
+    }
+
+    fn generate(&self, context: &mut GenerationContext) {
+        for x in 0..CHUNK_SIZE {
+            for y in (x % 2..CHUNK_SIZE).step_by(2) {
+                let surface = context.surface_height(x, y) - context.bottom();
+                context.remove(x, y, surface);
+            }
+        }
+    }
+}
+
+fn block_ids(chunk: &Chunk) -> Vec<((i32, i32, i32), BlockId)> {
+    chunk
+        .blocks()
+        .map(|(position, block)| (position, block.get_id()))
+        .collect()
+}
+
+fn count_blocks(pipeline: &GeneratorPipeline, name: &str) -> usize {
+    let id = Block::from_name(name).unwrap().get_id();
+
+    let mut count = 0;
+    for x in 3..7 {
+        for y in 3..7 {
+            for z in 0..3 {
+                let (chunk, overflow) = pipeline.generate(SEED, x, y, z);
+                count += chunk
+                    .blocks()
+                    .filter(|(_, block)| block.get_id() == id)
+                    .count();
+                count += overflow.iter().filter(|write| write.id == id).count();
+            }
+        }
+    }
+    count
+}
+
+#[test]
+fn default_pipeline_runs_the_built_in_stages() {
+    let pipeline = GeneratorPipeline::default();
+    assert_eq!(
+        pipeline.stage_names().collect::<Vec<_>>(),
+        ["terrain", "caves", "surface", "ores", "water", "trees"]
+    );
+
+    let (chunk, _) = pipeline.generate(SEED, 1, 2, 1);
+    assert_eq!(
+        block_ids(&chunk),
+        block_ids(&Chunk::generate(SEED, 1, 2, 1))
+    );
+}
+
+#[test]
+fn removed_stages_are_skipped() {
+    let mut pipeline = GeneratorPipeline::default();
+    assert!(count_blocks(&pipeline, "tree_log") > 0);
+
+    assert!(pipeline.remove("trees").is_some());
+    assert!(pipeline.remove("trees").is_none());
+    assert_eq!(count_blocks(&pipeline, "tree_log"), 0);
+    assert_eq!(count_blocks(&pipeline, "tree_leaf"), 0);
+    assert!(count_blocks(&pipeline, "grass") > 0);
+}
+
+#[test]
+fn reordered_stages_see_the_blocks_of_earlier_ones() {
+    let mut pipeline = GeneratorPipeline::default();
+    let version = pipeline.version();
+
+    // Without terrain below it, the surface has nothing to cover
+    assert!(pipeline.move_stage("surface", 0));
+    assert_eq!(
+        pipeline.stage_names().collect::<Vec<_>>(),
+        ["surface", "terrain", "caves", "ores", "water", "trees"]
+    );
+    assert_ne!(pipeline.version(), version);
+    assert_eq!(count_blocks(&pipeline, "grass"), 0);
+    assert!(count_blocks(&pipeline, "stone") > 0);
+}
+
+#[test]
+fn custom_stages_can_be_added_and_replaced() {
+    let mut pipeline = GeneratorPipeline::default();
+    assert!(pipeline.insert_after(
+        "surface",
+        LayerStage {
+            block: "snow",
+            height: 0
+        }
+    ));
+    assert!(!pipeline.insert_after(
+        "structures",
+        LayerStage {
+            block: "snow",
+            height: 0
+        }
+    ));
+    assert_eq!(pipeline.position("layer"), Some(3));
+
+    let snow = Block::from_name("snow").unwrap().get_id();
+    let (chunk, _) = pipeline.generate(SEED, 0, 0, -1);
+    assert_eq!(chunk.get_block(3, 4, 0).unwrap().get_id(), snow);
+
+    let replaced = pipeline.replace(
+        "layer",
+        LayerStage {
+            block: "sand",
+            height: 0,
+        },
+    );
+    assert_eq!(replaced.unwrap().name(), "layer");
+    let (chunk, _) = pipeline.generate(SEED, 0, 0, -1);
+    assert_eq!(
+        chunk.get_block(3, 4, 0).unwrap().get_id(),
+        Block::from_name("sand").unwrap().get_id()
+    );
+}
+
+#[test]
+fn providers_generate_with_their_pipeline() {
+    let pipeline = || {
+        GeneratorPipeline::empty().with_stage(LayerStage {
+            block: "dirt",
+            height: 32,
+        })
+    };
+    let dirt = Block::from_name("dirt").unwrap().get_id();
+
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    provider.set_pipeline(pipeline());
+    let chunk = provider.get_chunk(5, 5, 1);
+    assert_eq!(chunk.blocks().count(), (CHUNK_SIZE * CHUNK_SIZE) as usize);
+    assert!(chunk
+        .blocks()
+        .all(|((_, _, z), block)| z == 0 && block.get_id() == dirt));
+    assert!(provider.get_chunk(5, 5, 2).is_empty());
+
+    provider.request_chunk(6, 5, 1);
+    let started = std::time::Instant::now();
+    while provider.requested_chunk_count() > 0 {
+        assert!(started.elapsed().as_secs() < 30, "chunk never arrived");
+        provider.receive_chunks();
+        std::thread::sleep(std::time::Duration::from_millis(1));
+    }
+    assert_eq!(
+        block_ids(provider.get_loaded_chunk(6, 5, 1).unwrap()),
+        block_ids(&pipeline().generate(SEED, 6, 5, 1).0)
+    );
+}
+
+#[test]
+fn edits_are_not_replayed_on_another_pipeline() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = || RegionStore::with_mode(directory.path(), SaveMode::Deltas);
+    let stone = Block::from_name("stone").unwrap();
+
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    provider.get_chunk_mut(0, 0, 5).set_block(1, 1, 1, stone);
+    provider.save_all();
+
+    let mut pipeline = GeneratorPipeline::default();
+    pipeline.remove("caves");
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    provider.set_pipeline(pipeline);
+    assert!(provider.get_chunk(0, 0, 5).get_block(1, 1, 1).is_none());
+
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    let block = provider.get_chunk(0, 0, 5).get_block(1, 1, 1);
+    assert_eq!(block.map(|block| block.get_id()), Some(stone.get_id()));
+}
+
+#[test]
+fn trees_only_grow_on_solid_ground() {
+    // Stone up to z = 63, the top layer of a section, so that the trunks
+    // start in the section above their ground
+    let pipeline = GeneratorPipeline::empty()
+        .with_stage(FlatStage {
+            layers: vec![FlatLayer::new("stone", 64 - WORLD_BOTTOM)],
+        })
+        .with_stage(HoleStage)
+        .with_stage(TreeStage {
+            density_scale: 1000.0,
+            ..TreeStage::default()
+        });
+    let tree_log = Block::from_name("tree_log").unwrap().get_id();
+
+    // Trunk bases at z = 64, whichever section placed them. Branches only
+    // start above the trunk.
+    let (ground, overflow) = pipeline.generate(SEED, 0, 0, 3);
+    let (above, _) = pipeline.generate(SEED, 0, 0, 4);
+    let mut bases: Vec<_> = block_ids(&above)
+        .into_iter()
+        .filter(|&((_, _, k), id)| k == 0 && id == tree_log)
+        .map(|((i, j, _), _)| (i, j))
+        .collect();
+    bases.extend(
+        overflow
+            .iter()
+            .filter(|write| write.z == 64 && write.id == tree_log)
+            .map(|write| (write.x, write.y)),
+    );
+
+    assert!(!bases.is_empty());
+    for (i, j) in bases {
+        assert!(
+            ground.get_block(i, j, CHUNK_SIZE - 1).is_some(),
+            "tree floating at {i}, {j}"
+        );
+    }
+}
@@ -0,0 +1,244 @@
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE, MAX_CHUNK_Z, MIN_CHUNK_Z};
+use mycraft::world::generator::GeneratorPreset;
+use mycraft::world::light::MAX_LIGHT;
+use mycraft::world::region::RegionStore;
+use mycraft::world::World;
+
+mod common;
+
+const SEED: u32 = 5;
+
+fn load_columns(world: &mut World, columns: i32, lowest: i32) {
+    for x in 0..columns {
+        for y in 0..columns {
+            for z in lowest..=MAX_CHUNK_Z {
+                world.load_chunk(x, y, z);
+            }
+        }
+    }
+}
+
+// Sections are only lit below lit sections
+fn load_column(world: &mut World, x: i32, y: i32) {
+    for z in 0..=MAX_CHUNK_Z {
+        world.load_chunk(x, y, z);
+    }
+}
+
+fn sky(world: &World, x: i32, y: i32, z: i32) -> u8 {
+    world.get_light(x, y, z).sky
+}
+
+fn block(world: &World, x: i32, y: i32, z: i32) -> u8 {
+    world.get_light(x, y, z).block
+}
+
+#[test]
+fn sky_light_falls_to_the_ground_and_fades_under_a_roof() {
+    let mut world = common::floored_world(2, 0..=MAX_CHUNK_Z);
+    assert_eq!(sky(&world, 5, 5, 1), MAX_LIGHT);
+    assert_eq!(sky(&world, 5, 5, 0), 0);
+
+    // A roof over x and y from 10 to 20, open to the sides
+    let stone = Block::from_name("stone").unwrap();
+    for x in 10..=20 {
+        for y in 10..=20 {
+            world.set_block(x, y, 5, stone);
+        }
+    }
+    assert_eq!(sky(&world, 15, 15, 6), MAX_LIGHT);
+    assert_eq!(sky(&world, 9, 15, 1), MAX_LIGHT);
+    for distance in 1..=5 {
+        assert_eq!(sky(&world, 9 + distance, 15, 1), MAX_LIGHT - distance as u8);
+    }
+    assert_eq!(sky(&world, 15, 15, 4), MAX_LIGHT - 6);
+
+    // Taking a block out of the roof lets the sky straight in again
+    world.remove_block(15, 15, 5);
+    for z in 1..=5 {
+        assert_eq!(sky(&world, 15, 15, z), MAX_LIGHT);
+    }
+    assert_eq!(sky(&world, 16, 15, 1), MAX_LIGHT - 1);
+}
+
+#[test]
+fn see_through_blocks_dim_the_sky() {
+    let mut world = common::floored_world(2, 0..=MAX_CHUNK_Z);
+    let water = Block::from_name("water").unwrap();
+    for z in 1..=4 {
+        world.set_block(8, 8, z, water);
+    }
+    assert_eq!(sky(&world, 8, 8, 4), MAX_LIGHT - 1);
+    assert_eq!(sky(&world, 8, 8, 1), MAX_LIGHT - 1);
+    assert!(world.get_light(8, 8, 0).sky == 0);
+
+    // Light from the sides still comes in at full strength minus one
+    let stone = Block::from_name("stone").unwrap();
+    for (x, y) in [(7, 8), (9, 8), (8, 7), (8, 9)] {
+        for z in 1..=4 {
+            world.set_block(x, y, z, stone);
+        }
+    }
+    assert_eq!(sky(&world, 8, 8, 4), MAX_LIGHT - 1);
+    assert_eq!(sky(&world, 8, 8, 1), MAX_LIGHT - 4);
+}
+
+#[test]
+fn block_light_spreads_from_emissive_blocks_and_goes_with_them() {
+    let mut world = common::floored_world(2, 0..=MAX_CHUNK_Z);
+    let lava = Block::from_name("lava").unwrap();
+    let emission = lava.get_light_emission();
+    world.set_block(16, 16, 1, lava);
+
+    assert_eq!(block(&world, 16, 16, 1), emission);
+    for distance in 1..emission as i32 {
+        assert_eq!(
+            block(&world, 16 - distance, 16, 1),
+            emission - distance as u8
+        );
+    }
+    assert_eq!(block(&world, 17, 17, 2), emission - 3);
+    // The floor is opaque
+    assert_eq!(block(&world, 16, 16, 0), 0);
+
+    world.remove_block(16, 16, 1);
+    for x in 0..32 {
+        assert_eq!(block(&world, x, 16, 1), 0, "light left at {x}");
+    }
+}
+
+#[test]
+fn opaque_blocks_hold_light_back() {
+    let mut world = common::floored_world(2, 0..=MAX_CHUNK_Z);
+    let stone = Block::from_name("stone").unwrap();
+    let lava = Block::from_name("lava").unwrap();
+
+    // A wall three blocks high at x = 10 with the lava on one side
+    for y in 0..32 {
+        for z in 1..=3 {
+            world.set_block(10, y, z, stone);
+        }
+    }
+    world.set_block(8, 16, 1, lava);
+    assert_eq!(block(&world, 9, 16, 1), 14);
+    assert_eq!(block(&world, 10, 16, 1), 0);
+    // Around the top of the wall instead of through it
+    assert_eq!(block(&world, 11, 16, 1), 15 - 9);
+}
+
+#[test]
+fn light_crosses_chunk_borders_in_either_loading_order() {
+    let lava = Block::from_name("lava").unwrap();
+
+    // Lava next to a column that is loaded after it
+    let mut world = World::new(ChunkProvider::new(0, GeneratorPreset::Void));
+    load_column(&mut world, 0, 0);
+    world.set_block(CHUNK_SIZE - 1, 4, 4, lava);
+    assert_eq!(block(&world, CHUNK_SIZE, 4, 4), 0);
+    load_column(&mut world, 1, 0);
+    assert_eq!(block(&world, CHUNK_SIZE + 2, 4, 4), 12);
+
+    // Lava placed next to a loaded section lights it up, and marks it dirty
+    load_column(&mut world, 0, 1);
+    world.take_dirty_chunks();
+    world.set_block(4, CHUNK_SIZE - 2, 4, lava);
+    assert_eq!(block(&world, 4, CHUNK_SIZE + 3, 4), 10);
+    assert!(world.take_dirty_chunks().contains(&(0, 1, 0)));
+}
+
+#[test]
+fn sky_reaches_sections_loaded_before_the_ones_above() {
+    let mut world = World::new(ChunkProvider::new(0, GeneratorPreset::Void));
+    for z in (0..=MAX_CHUNK_Z).rev().filter(|z| z % 2 == 0) {
+        world.load_chunk(0, 0, z);
+    }
+    assert_eq!(sky(&world, 3, 3, 3), 0);
+    for z in (0..=MAX_CHUNK_Z).filter(|z| z % 2 == 1) {
+        world.load_chunk(0, 0, z);
+    }
+    assert_eq!(sky(&world, 3, 3, 3), MAX_LIGHT);
+    assert_eq!(sky(&world, 3, 3, MAX_CHUNK_Z * CHUNK_SIZE), MAX_LIGHT);
+}
+
+#[test]
+fn caves_are_dark() {
+    let mut world = World::new(ChunkProvider::new(SEED, GeneratorPreset::Default));
+    load_columns(&mut world, 2, MIN_CHUNK_Z);
+
+    let mut dark_air = 0;
+    for x in 0..2 * CHUNK_SIZE {
+        for y in 0..2 * CHUNK_SIZE {
+            for z in (MIN_CHUNK_Z * CHUNK_SIZE)..-CHUNK_SIZE {
+                if world.get_block(x, y, z).is_none() && sky(&world, x, y, z) == 0 {
+                    dark_air += 1;
+                }
+            }
+        }
+    }
+    assert!(dark_air > 0);
+    assert_eq!(
+        sky(&world, 0, 0, (MAX_CHUNK_Z + 1) * CHUNK_SIZE - 1),
+        MAX_LIGHT
+    );
+}
+
+#[test]
+fn incremental_updates_match_lighting_from_scratch() {
+    let directory = tempfile::tempdir().unwrap();
+    let provider = || {
+        ChunkProvider::with_region_store(
+            SEED,
+            GeneratorPreset::Default,
+            RegionStore::new(directory.path()),
+        )
+    };
+    let stone = Block::from_name("stone").unwrap();
+    let lava = Block::from_name("lava").unwrap();
+    let water = Block::from_name("water").unwrap();
+
+    let mut edited = World::new(provider());
+    load_columns(&mut edited, 2, -1);
+    let surface = |world: &World, x, y| {
+        (-CHUNK_SIZE..(MAX_CHUNK_Z + 1) * CHUNK_SIZE)
+            .rev()
+            .find(|&z| world.get_block(x, y, z).is_some())
+            .unwrap()
+    };
+
+    // A shaft from the surface, a tunnel across the chunk border at its
+    // foot, lava in it, a lid over part of it and some of it filled in again
+    let top = surface(&edited, 14, 14);
+    for z in 0..=top {
+        edited.remove_block(14, 14, z);
+    }
+    for x in 4..28 {
+        for z in 0..3 {
+            edited.remove_block(x, 14, z);
+        }
+    }
+    edited.set_block(20, 14, 0, lava);
+    edited.set_block(6, 14, 0, lava);
+    edited.set_block(14, 14, top - 3, stone);
+    edited.set_block(14, 14, top - 4, water);
+    edited.remove_block(14, 14, top - 3);
+    edited.set_block(20, 14, 0, stone);
+    for x in 8..12 {
+        edited.set_block(x, 14, 1, stone);
+    }
+    edited.save();
+
+    let mut loaded = World::new(provider());
+    load_columns(&mut loaded, 2, -1);
+    for x in 0..2 * CHUNK_SIZE {
+        for y in 0..2 * CHUNK_SIZE {
+            for z in -CHUNK_SIZE..(MAX_CHUNK_Z + 1) * CHUNK_SIZE {
+                assert_eq!(
+                    edited.get_light(x, y, z),
+                    loaded.get_light(x, y, z),
+                    "at {x}, {y}, {z}"
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use mycraft::world::block::{Block, Face};
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE, MAX_CHUNK_Z, MIN_CHUNK_Z};
+use mycraft::world::generator::GeneratorPreset;
+use mycraft::world::mesh::Quad;
+use mycraft::world::World;
+use std::collections::HashMap;
+
+const SEED: u32 = 11;
+
+// An empty column lit by the open sky
+fn void_world() -> World {
+    let mut world = World::new(ChunkProvider::new(0, GeneratorPreset::Void));
+    for z in 0..=MAX_CHUNK_Z {
+        world.load_chunk(0, 0, z);
+    }
+    world
+}
+
+fn fill_floor(world: &mut World, block: impl Fn(i32, i32) -> Block) {
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            world.set_block(x, y, 0, block(x, y));
+        }
+    }
+}
+
+fn quads_of(world: &World, chunk: (i32, i32, i32)) -> Vec<Quad> {
+    let chunk = world.get_chunk(chunk.0, chunk.1, chunk.2).unwrap();
+    world.greedy_quads(chunk)
+}
+
+fn quads_facing(quads: &[Quad], face: Face) -> Vec<&Quad> {
+    quads.iter().filter(|quad| quad.face == face).collect()
+}
+
+#[test]
+fn a_flat_floor_is_one_quad_per_side() {
+    let mut world = void_world();
+    let stone = Block::from_name("stone").unwrap();
+    fill_floor(&mut world, |_, _| stone);
+
+    let quads = quads_of(&world, (0, 0, 0));
+    assert_eq!(quads.len(), 6);
+    let top = quads_facing(&quads, Face::Top);
+    assert_eq!(top[0].position, (0, 0, 0));
+    assert_eq!(top[0].extent, (CHUNK_SIZE, CHUNK_SIZE, 1));
+    let right = quads_facing(&quads, Face::Right);
+    assert_eq!(right[0].position, (CHUNK_SIZE - 1, 0, 0));
+    assert_eq!(right[0].extent, (1, CHUNK_SIZE, 1));
+}
+
+#[test]
+fn faces_of_different_materials_are_not_merged() {
+    let mut world = void_world();
+    let stone = Block::from_name("stone").unwrap();
+    let dirt = Block::from_name("dirt").unwrap();
+    fill_floor(&mut world, |x, _| if x < 4 { dirt } else { stone });
+
+    let quads = quads_of(&world, (0, 0, 0));
+    let top = quads_facing(&quads, Face::Top);
+    assert_eq!(top.len(), 2);
+    for quad in top {
+        let expected = if quad.position.0 == 0 { dirt } else { stone };
+        assert_eq!(quad.material, expected.get_material_id(Face::Top));
+    }
+}
+
+#[test]
+fn faces_under_different_light_are_not_merged() {
+    let mut world = void_world();
+    let stone = Block::from_name("stone").unwrap();
+    let lava = Block::from_name("lava").unwrap();
+    fill_floor(&mut world, |_, _| stone);
+    world.set_block(8, 8, 1, lava);
+
+    let quads = quads_of(&world, (0, 0, 0));
+    let top = quads_facing(&quads, Face::Top);
+    assert!(top.len() > 1);
+    for quad in top {
+        let (x, y, z) = quad.position;
+        let light = world.get_light(x, y, z + 1);
+        assert_eq!(quad.light, light);
+    }
+}
+
+#[test]
+fn quads_cover_every_visible_face_exactly_once() {
+    let mut world = World::new(ChunkProvider::new(SEED, GeneratorPreset::Default));
+    for x in 0..3 {
+        for y in 0..3 {
+            for z in MIN_CHUNK_Z..=MAX_CHUNK_Z {
+                world.load_chunk(x, y, z);
+            }
+        }
+    }
+
+    let mut merged_faces = 0;
+    for z in MIN_CHUNK_Z..=MAX_CHUNK_Z {
+        let chunk = world.get_chunk(1, 1, z).unwrap();
+        let mut expected = HashMap::new();
+        for ((x, y, z), block) in chunk.blocks() {
+            let visible_faces = world.visible_faces(chunk, x, y, z);
+            let light = world.face_light(chunk, x, y, z);
+            for face in Face::ALL
+                .into_iter()
+                .filter(|face| visible_faces[*face as usize])
+            {
+                expected.insert(
+                    ((x, y, z), face as usize),
+                    (block.get_material_id(face), light[face as usize]),
+                );
+            }
+        }
+
+        let quads = world.greedy_quads(chunk);
+        let mut covered = 0;
+        for quad in &quads {
+            let (x0, y0, z0) = quad.position;
+            let (dx, dy, dz) = quad.extent;
+            for x in x0..x0 + dx {
+                for y in y0..y0 + dy {
+                    for z in z0..z0 + dz {
+                        let face = expected.remove(&((x, y, z), quad.face as usize));
+                        assert_eq!(face, Some((quad.material, quad.light)));
+                        covered += 1;
+                    }
+                }
+            }
+        }
+        assert!(expected.is_empty(), "faces left out in section {z}");
+        if !quads.is_empty() {
+            merged_faces += covered - quads.len();
+        }
+    }
+    assert!(merged_faces > 0);
+}
+
+#[test]
+fn lod_quads_cover_a_floor_with_scaled_cubes() {
+    let mut world = void_world();
+    let stone = Block::from_name("stone").unwrap();
+    let dirt = Block::from_name("dirt").unwrap();
+    fill_floor(&mut world, |_, _| stone);
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            world.set_block(x, y, 1, dirt);
+        }
+    }
+
+    let chunk = world.get_chunk(0, 0, 0).unwrap();
+    let quads = world.lod_quads(chunk, 4);
+    assert_eq!(quads.len(), 6);
+    let top = quads_facing(&quads, Face::Top);
+    assert_eq!(top[0].position, (0, 0, 0));
+    assert_eq!(top[0].extent, (CHUNK_SIZE, CHUNK_SIZE, 4));
+    // The cubes show the highest of their blocks
+    assert_eq!(top[0].material, dirt.get_material_id(Face::Top));
+    let right = quads_facing(&quads, Face::Right);
+    assert_eq!(right[0].position, (CHUNK_SIZE - 4, 0, 0));
+    assert_eq!(right[0].extent, (4, CHUNK_SIZE, 4));
+}
+
+#[test]
+fn lod_quads_leave_out_mostly_empty_cubes() {
+    let mut world = void_world();
+    let stone = Block::from_name("stone").unwrap();
+    world.set_block(0, 0, 0, stone);
+    world.set_block(1, 1, 1, stone);
+    world.set_block(5, 5, 5, stone);
+    world.set_block(5, 4, 5, stone);
+    world.set_block(4, 5, 5, stone);
+    world.set_block(4, 4, 4, stone);
+
+    let chunk = world.get_chunk(0, 0, 0).unwrap();
+    let quads = world.lod_quads(chunk, 2);
+    // Only the cube holding four of its eight blocks is left
+    assert_eq!(quads.len(), 6);
+    assert!(quads.iter().all(|quad| quad.position.0 >= 4));
+}
+
+#[test]
+fn lod_quads_keep_the_faces_on_the_sides_of_the_chunk() {
+    let mut world = void_world();
+    for z in 0..=MAX_CHUNK_Z {
+        world.load_chunk(1, 0, z);
+    }
+    let stone = Block::from_name("stone").unwrap();
+    for x in 0..2 * CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            world.set_block(x, y, 0, stone);
+        }
+    }
+
+    let chunk = world.get_chunk(0, 0, 0).unwrap();
+    assert!(quads_facing(&world.greedy_quads(chunk), Face::Right).is_empty());
+    // The neighbour may be meshed at another scale, so the face stays
+    let lod_quads = world.lod_quads(chunk, 2);
+    assert_eq!(quads_facing(&lod_quads, Face::Right).len(), 1);
+}
@@ -0,0 +1,112 @@
+use mycraft::world::generator::{FractalNoise, GeneratorPreset};
+use noise::Simplex;
+
+fn samples(settings: &FractalNoise) -> Vec<f64> {
+    let noise = Simplex::new(3);
+    (0..64)
+        .flat_map(|x| (0..64).map(move |y| (x as f64 * 3.0, y as f64 * 3.0)))
+        .map(|(x, y)| settings.sample(&noise, x, y))
+        .collect()
+}
+
+// Mean difference between horizontally neighbouring blocks
+fn roughness(settings: &FractalNoise) -> f64 {
+    let noise = Simplex::new(3);
+    let differences: Vec<f64> = (0..1000)
+        .map(|x| {
+            let x = x as f64 * 7.0;
+            (settings.sample(&noise, x, 5.0) - settings.sample(&noise, x + 1.0, 5.0)).abs()
+        })
+        .collect();
+    differences.iter().sum::<f64>() / differences.len() as f64
+}
+
+#[test]
+fn samples_stay_in_range() {
+    let configurations = [
+        FractalNoise::default(),
+        FractalNoise {
+            octaves: 6,
+            contrast: 4.0,
+            ..FractalNoise::default()
+        },
+        FractalNoise {
+            octaves: 4,
+            ridged: true,
+            warp_strength: 30.0,
+            ..FractalNoise::default()
+        },
+        FractalNoise {
+            octaves: 0,
+            persistence: 0.0,
+            ..FractalNoise::default()
+        },
+    ];
+
+    for settings in &configurations {
+        assert!(samples(settings)
+            .iter()
+            .all(|value| (0.0..=1.0).contains(value)));
+    }
+}
+
+#[test]
+fn octaves_add_finer_detail() {
+    let smooth = FractalNoise {
+        increment: 0.005,
+        ..FractalNoise::default()
+    };
+    let detailed = FractalNoise {
+        octaves: 6,
+        persistence: 0.7,
+        ..smooth.clone()
+    };
+    assert!(roughness(&detailed) > 1.5 * roughness(&smooth));
+}
+
+#[test]
+fn ridges_and_warping_change_the_noise() {
+    let base = FractalNoise {
+        octaves: 3,
+        ..FractalNoise::default()
+    };
+    let ridged = FractalNoise {
+        ridged: true,
+        ..base.clone()
+    };
+    let warped = FractalNoise {
+        warp_strength: 20.0,
+        ..base.clone()
+    };
+
+    assert_eq!(samples(&base), samples(&base.clone()));
+    assert_ne!(samples(&base), samples(&ridged));
+    assert_ne!(samples(&base), samples(&warped));
+}
+
+#[test]
+fn single_octaves_have_no_valleys_at_zero_crossings() {
+    // The old `.abs()` height dropped to zero along every zero crossing
+    let values = samples(&FractalNoise::default());
+    let low = values.iter().filter(|&&value| value < 0.05).count();
+    assert!(
+        low * 100 < values.len(),
+        "{low} of {} samples",
+        values.len()
+    );
+}
+
+#[test]
+fn noise_settings_load_from_world_configs() {
+    let config = "preset = \"custom\"\n\n\
+                  [terrain.height_noise]\noctaves = 3\nridged = true\n\n\
+                  [terrain.humidity_noise]\nwarp_strength = 8.0\n";
+    let GeneratorPreset::Custom(config) = GeneratorPreset::from_toml(config).unwrap() else {
+        panic!("expected a custom preset");
+    };
+
+    assert_eq!(config.terrain.height_noise.octaves, 3);
+    assert!(config.terrain.height_noise.ridged);
+    assert_eq!(config.terrain.height_noise.lacunarity, 2.0);
+    assert_eq!(config.terrain.humidity_noise.warp_strength, 8.0);
+}
@@ -0,0 +1,149 @@
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{CHUNK_SIZE, WORLD_BOTTOM};
+use mycraft::world::generator::{GeneratorPipeline, OreStage, OreVein};
+use std::collections::{HashMap, HashSet};
+
+mod common;
+
+const SEED: u32 = 13;
+
+// Solid stone from the bottom of the world up to z = 63, topped with dirt
+const LAYERS: &[(&str, i32)] = &[("stone", 64), ("dirt", 80)];
+
+// World coordinates of the non-stone, non-dirt blocks by block name
+fn ore_positions(
+    pipeline: &GeneratorPipeline,
+    columns: i32,
+    layers: std::ops::Range<i32>,
+) -> HashMap<&'static str, Vec<(i32, i32, i32)>> {
+    let mut positions: HashMap<_, Vec<_>> = HashMap::new();
+    for x in 0..columns {
+        for y in 0..columns {
+            for z in layers.clone() {
+                let (chunk, _) = pipeline.generate(SEED, x, y, z);
+                for ((i, j, k), block) in chunk.blocks() {
+                    if !["stone", "dirt"].contains(&block.get_name()) {
+                        positions.entry(block.get_name()).or_default().push((
+                            x * CHUNK_SIZE + i,
+                            y * CHUNK_SIZE + j,
+                            z * CHUNK_SIZE + k,
+                        ));
+                    }
+                }
+            }
+        }
+    }
+    positions
+}
+
+#[test]
+fn veins_come_at_the_configured_frequency() {
+    let ore = OreVein::new("iron_ore", (0, 3 * CHUNK_SIZE - 1), 8, 4.0);
+    let pipeline = common::layered_pipeline(LAYERS, OreStage { ores: vec![ore] });
+    let found = ore_positions(&pipeline, 6, 0..3)["iron_ore"].len() as f64;
+
+    // Give or take veins overlapping themselves and running out of range
+    let expected = (6 * 6 * 3 * 4 * 8) as f64;
+    assert!(
+        (expected * 0.8..expected * 1.05).contains(&found),
+        "{found} blocks, {expected} expected"
+    );
+}
+
+#[test]
+fn ores_keep_to_their_height_ranges() {
+    let pipeline = common::layered_pipeline(LAYERS, OreStage::default());
+    let positions = ore_positions(&pipeline, 4, -2..4);
+
+    for ore in OreStage::default().ores {
+        let found = positions
+            .get(ore.block.as_str())
+            .map_or(&[][..], |found| found);
+        assert!(!found.is_empty(), "no {}", ore.block);
+        for &(_, _, z) in found {
+            assert!(
+                (ore.min_height..=ore.max_height).contains(&z),
+                "{} at {z}",
+                ore.block
+            );
+            assert!(z > WORLD_BOTTOM);
+        }
+    }
+
+    // Rare ores are rare
+    let count = |name: &str| positions[name].len();
+    assert!(count("diamond_ore") * 4 < count("iron_ore"));
+    assert!(count("gold_ore") < count("iron_ore"));
+    assert!(count("iron_ore") < count("coal"));
+}
+
+#[test]
+fn ores_only_replace_their_hosts() {
+    let mut ore = OreVein::new("gold_ore", (WORLD_BOTTOM, 96), 12, 8.0);
+    ore.hosts = vec!["dirt".to_string()];
+    let pipeline = common::layered_pipeline(LAYERS, OreStage { ores: vec![ore] });
+
+    let positions = &ore_positions(&pipeline, 3, 3..5)["gold_ore"];
+    assert!(positions.len() > 100);
+    assert!(positions.iter().all(|&(_, _, z)| (64..80).contains(&z)));
+}
+
+#[test]
+fn veins_run_on_across_section_borders() {
+    let ore = OreVein::new("diamond_ore", (WORLD_BOTTOM, 96), 12, 6.0);
+    let pipeline = common::layered_pipeline(
+        LAYERS,
+        OreStage {
+            ores: vec![ore.clone()],
+        },
+    );
+    let generated: HashSet<_> = ore_positions(&pipeline, 3, 0..3)["diamond_ore"]
+        .iter()
+        .copied()
+        .collect();
+
+    // Blocks of the veins started in or next to the middle section that land
+    // inside of it
+    let section = |(x, y, z): (i32, i32, i32)| {
+        (
+            x.div_euclid(CHUNK_SIZE),
+            y.div_euclid(CHUNK_SIZE),
+            z.div_euclid(CHUNK_SIZE),
+        )
+    };
+    let mut expected = HashSet::new();
+    let mut crossing = 0;
+    for x in 0..3 {
+        for y in 0..3 {
+            for z in 0..3 {
+                for vein in ore.veins(SEED, x, y, z) {
+                    let sections: HashSet<_> = vein.iter().map(|&block| section(block)).collect();
+                    if sections.len() > 1 {
+                        crossing += 1;
+                    }
+                    expected.extend(
+                        vein.into_iter()
+                            .filter(|&block| section(block) == (1, 1, 1)),
+                    );
+                }
+            }
+        }
+    }
+
+    assert!(crossing > 0);
+    let generated_middle: HashSet<_> = generated
+        .into_iter()
+        .filter(|&block| section(block) == (1, 1, 1))
+        .collect();
+    assert_eq!(generated_middle, expected);
+}
+
+#[test]
+fn the_default_world_has_ores_underground() {
+    let pipeline = GeneratorPipeline::default();
+    let positions = ore_positions(&pipeline, 2, -2..1);
+    for name in ["coal", "iron_ore", "copper_ore"] {
+        assert!(positions.contains_key(name), "no {name}");
+        assert!(Block::from_name(name).unwrap().is_opaque());
+    }
+}
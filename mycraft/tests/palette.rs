@@ -0,0 +1,178 @@
+use mycraft::world::block::{BlockId, AIR_ID};
+use mycraft::world::palette::{words_for, PaletteStorage};
+
+// The voxels of a chunk section
+const LEN: usize = 4096;
+
+// Storage where index i holds the (i % distinct + 1)th id, so that every id
+// but air is used
+fn cycling(distinct: usize) -> PaletteStorage {
+    let mut storage = PaletteStorage::new(LEN);
+    for index in 0..LEN {
+        storage.set(index, id_at(index, distinct));
+    }
+    storage
+}
+
+fn id_at(index: usize, distinct: usize) -> BlockId {
+    (index % distinct + 1) as BlockId
+}
+
+#[test]
+fn entries_widen_as_the_palette_grows() {
+    // Air stays in the palette, so n distinct ids need a palette of n + 1
+    for (distinct, bits) in [
+        (1, 1),
+        (2, 2),
+        (3, 2),
+        (4, 3),
+        (15, 4),
+        (16, 5),
+        (255, 8),
+        (256, 9),
+        (1000, 10),
+    ] {
+        let storage = cycling(distinct);
+        assert_eq!(storage.palette().len(), distinct + 1);
+        assert_eq!(storage.bits_per_entry(), bits, "{distinct} ids");
+        assert_eq!(storage.data().len(), words_for(bits, LEN));
+        for index in 0..LEN {
+            assert_eq!(
+                storage.get(index),
+                id_at(index, distinct),
+                "{distinct} ids at {index}"
+            );
+        }
+    }
+}
+
+#[test]
+fn entries_are_kept_while_widening() {
+    let mut storage = PaletteStorage::new(LEN);
+    let mut expected = vec![AIR_ID; LEN];
+    // A new id every few entries, so that each widening repacks a mix of ids
+    for index in (0..LEN).step_by(3) {
+        let id = (index / 12 + 1) as BlockId;
+        storage.set(index, id);
+        expected[index] = id;
+    }
+    assert_eq!(storage.bits_per_entry(), 9);
+    assert!(storage.iter().eq(expected));
+}
+
+#[test]
+fn odd_widths_leave_the_top_of_each_word_unused() {
+    for (distinct, bits) in [(4, 3), (16, 5), (100, 7), (256, 9)] {
+        let storage = cycling(distinct);
+        assert_eq!(storage.bits_per_entry(), bits);
+
+        let entries_per_word = 64 / bits as usize;
+        assert_eq!(storage.data().len(), LEN.div_ceil(entries_per_word));
+        for word in storage.data() {
+            assert_eq!(word >> (entries_per_word as u32 * bits), 0, "{bits} bits");
+        }
+    }
+}
+
+#[test]
+fn neighbours_across_word_ends_are_independent() {
+    let mut storage = cycling(4);
+    let entries_per_word = 64 / storage.bits_per_entry() as usize;
+    for last in (entries_per_word - 1..LEN - 1).step_by(entries_per_word) {
+        storage.set(last, 4);
+        storage.set(last + 1, 1);
+        assert_eq!(storage.get(last), 4);
+        assert_eq!(storage.get(last + 1), 1);
+        assert_eq!(storage.get(last - 1), id_at(last - 1, 4));
+        if last + 2 < LEN {
+            assert_eq!(storage.get(last + 2), id_at(last + 2, 4));
+        }
+    }
+}
+
+#[test]
+fn non_air_iteration_matches_every_entry() {
+    let mut storage = PaletteStorage::new(LEN);
+    // Runs of air long enough to fill whole words, and air between ids
+    for index in [0, 1, 5, 700, 701, 2047, 2048, 4000, LEN - 1] {
+        storage.set(index, (index % 7 + 1) as BlockId);
+    }
+    storage.set(1, AIR_ID);
+
+    let expected: Vec<_> = storage
+        .iter()
+        .enumerate()
+        .filter(|(_, id)| *id != AIR_ID)
+        .collect();
+    assert_eq!(expected.len(), 8);
+    assert_eq!(storage.iter_non_air().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn non_air_iteration_without_air_at_the_front_of_the_palette() {
+    let mut storage = PaletteStorage::filled(LEN, 3);
+    storage.set(10, AIR_ID);
+    storage.set(20, 4);
+
+    let found: Vec<_> = storage.iter_non_air().collect();
+    assert_eq!(found.len(), LEN - 1);
+    assert!(!found.iter().any(|&(index, _)| index == 10));
+    assert!(found.contains(&(20, 4)));
+}
+
+#[test]
+fn uniform_storage() {
+    assert!(PaletteStorage::new(LEN).is_uniform());
+    assert_eq!(PaletteStorage::new(LEN).iter_non_air().count(), 0);
+
+    let mut stone = PaletteStorage::filled(LEN, 2);
+    assert!(stone.is_uniform());
+    assert!(stone.data().is_empty());
+    assert!(stone.iter_non_air().eq((0..LEN).map(|index| (index, 2))));
+
+    stone.set(5, 2);
+    assert!(stone.is_uniform());
+    stone.set(5, AIR_ID);
+    assert!(!stone.is_uniform());
+}
+
+#[test]
+fn raw_parts_round_trip() {
+    let storage = cycling(20);
+    let rebuilt = PaletteStorage::from_raw(
+        storage.palette().to_vec(),
+        storage.bits_per_entry(),
+        storage.data().to_vec(),
+        LEN,
+    )
+    .unwrap();
+    assert!(rebuilt.iter().eq(storage.iter()));
+
+    let uniform = PaletteStorage::from_raw(vec![2], 0, Vec::new(), LEN).unwrap();
+    assert!(uniform.is_uniform());
+    assert_eq!(uniform.get(LEN - 1), 2);
+}
+
+#[test]
+fn mismatched_raw_parts_are_rejected() {
+    let storage = cycling(4);
+    let palette = storage.palette().to_vec();
+    let data = storage.data().to_vec();
+
+    assert!(PaletteStorage::from_raw(Vec::new(), 0, Vec::new(), LEN).is_err());
+    assert!(PaletteStorage::from_raw(palette.clone(), 4, data.clone(), LEN).is_err());
+    assert!(PaletteStorage::from_raw(palette.clone(), 2, data.clone(), LEN).is_err());
+    assert!(PaletteStorage::from_raw(vec![2], 0, vec![0], LEN).is_err());
+
+    let mut short = data.clone();
+    short.pop();
+    assert!(PaletteStorage::from_raw(palette.clone(), 3, short, LEN).is_err());
+    let mut long = data.clone();
+    long.push(0);
+    assert!(PaletteStorage::from_raw(palette.clone(), 3, long, LEN).is_err());
+
+    // Five ids fit in three bits, which can also point past the palette
+    let mut outside = data;
+    outside[0] |= 0b111;
+    assert!(PaletteStorage::from_raw(palette, 3, outside, LEN).is_err());
+}
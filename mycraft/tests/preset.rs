@@ -0,0 +1,140 @@
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE};
+use mycraft::world::generator::{
+    FlatLayer, FlatStage, GeneratorConfig, GeneratorPipeline, GeneratorPreset,
+};
+
+const SEED: u32 = 7;
+
+fn id(name: &str) -> u16 {
+    Block::from_name(name).unwrap().get_id()
+}
+
+// Blocks of the area above z = 32, where only mountains reach
+fn mountain_blocks(preset: GeneratorPreset) -> usize {
+    let mut provider = ChunkProvider::new(SEED, preset);
+    let mut count = 0;
+    for x in 0..4 {
+        for y in 0..4 {
+            for z in 2..8 {
+                count += provider.get_chunk(x, y, z).blocks().count();
+            }
+        }
+    }
+    count
+}
+
+#[test]
+fn superflat_layers_stack_from_the_bottom_of_the_world() {
+    let preset = GeneratorPreset::Superflat(FlatStage {
+        layers: vec![
+            FlatLayer::new("stone", 30),
+            FlatLayer::new("dirt", 1),
+            FlatLayer::new("grass", 1),
+        ],
+    });
+    let mut provider = ChunkProvider::new(SEED, preset);
+
+    // The layers end right below z = 0
+    let chunk = provider.get_chunk(3, -2, -1);
+    for z in 0..CHUNK_SIZE - 2 {
+        assert_eq!(chunk.get_block(5, 7, z).unwrap().get_id(), id("stone"));
+    }
+    assert_eq!(chunk.get_block(5, 7, 14).unwrap().get_id(), id("dirt"));
+    assert_eq!(chunk.get_block(0, 15, 15).unwrap().get_id(), id("grass"));
+    assert_eq!(chunk.blocks().count(), CHUNK_SIZE.pow(3) as usize);
+    assert!(provider.get_chunk(3, -2, 0).is_empty());
+}
+
+#[test]
+fn void_worlds_are_empty() {
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Void);
+    for z in -2..3 {
+        assert!(provider.get_chunk(0, 0, z).is_empty());
+    }
+}
+
+#[test]
+fn amplified_terrain_reaches_higher() {
+    let default = mountain_blocks(GeneratorPreset::Default);
+    let amplified = mountain_blocks(GeneratorPreset::Amplified);
+    assert!(amplified > default, "{amplified} <= {default}");
+}
+
+#[test]
+fn world_configs_load_from_json_and_toml() {
+    let directory = tempfile::tempdir().unwrap();
+
+    let toml_path = directory.path().join("world.toml");
+    std::fs::write(
+        &toml_path,
+        "preset = \"custom\"\n\n[terrain]\nheight_amplifier = 2.0\n\n[trees]\ntrunk_height = 6\n",
+    )
+    .unwrap();
+    let GeneratorPreset::Custom(config) = GeneratorPreset::load(&toml_path).unwrap() else {
+        panic!("expected a custom preset");
+    };
+    assert_eq!(config.terrain.height_amplifier, 2.0);
+    assert_eq!(config.trees.trunk_height, 6);
+    assert_eq!(config.caves.worms, GeneratorConfig::default().caves.worms);
+
+    let json_path = directory.path().join("world.json");
+    std::fs::write(
+        &json_path,
+        r#"{"preset": "superflat", "layers": [{"block": "sand", "thickness": 2}]}"#,
+    )
+    .unwrap();
+    let GeneratorPreset::Superflat(flat) = GeneratorPreset::load(&json_path).unwrap() else {
+        panic!("expected a superflat preset");
+    };
+    assert_eq!(flat.layers, vec![FlatLayer::new("sand", 2)]);
+
+    assert!(matches!(
+        GeneratorPreset::from_toml("preset = \"void\"").unwrap(),
+        GeneratorPreset::Void
+    ));
+}
+
+#[test]
+fn invalid_world_configs_are_rejected() {
+    assert!(GeneratorPreset::from_toml("preset = \"custom\"\n[terrain]\nheight = 2\n").is_err());
+    assert!(GeneratorPreset::from_toml("preset = \"floating_islands\"").is_err());
+    assert!(GeneratorPreset::from_json(
+        r#"{"preset": "superflat", "layers": [{"block": "bedrock", "thickness": 1}]}"#
+    )
+    .is_err());
+    assert!(GeneratorPreset::from_json(
+        r#"{"preset": "superflat", "layers": [{"block": "dirt", "thickness": -1}]}"#
+    )
+    .is_err());
+
+    let ore = |block: &str, host: &str| {
+        format!(
+            "preset = \"custom\"\n[[ores.ores]]\nblock = \"{block}\"\nmin_height = 0\n\
+             max_height = 10\nvein_size = 4\nveins_per_chunk = 1.0\nhosts = [\"{host}\"]\n"
+        )
+    };
+    assert!(GeneratorPreset::from_toml(&ore("gold_ore", "stone")).is_ok());
+    assert!(GeneratorPreset::from_toml(&ore("mithril_ore", "stone")).is_err());
+    assert!(GeneratorPreset::from_toml(&ore("gold_ore", "bedrock")).is_err());
+}
+
+#[test]
+fn generator_settings_are_part_of_the_version() {
+    let default = GeneratorPipeline::default().version();
+    assert_eq!(GeneratorPreset::Default.pipeline().version(), default);
+    assert_eq!(
+        GeneratorPreset::Custom(Box::default()).pipeline().version(),
+        default
+    );
+    assert_ne!(GeneratorPreset::Amplified.pipeline().version(), default);
+
+    let flat = |thickness| {
+        GeneratorPreset::Superflat(FlatStage {
+            layers: vec![FlatLayer::new("dirt", thickness)],
+        })
+        .pipeline()
+        .version()
+    };
+    assert_ne!(flat(1), flat(2));
+}
@@ -0,0 +1,119 @@
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE};
+use mycraft::world::generator::{sub_seed, ChunkRng, GeneratorPreset};
+use noise::{NoiseFn, Simplex};
+
+const SEED: u32 = 11;
+
+fn correlation(a: &[f64], b: &[f64]) -> f64 {
+    let mean = |values: &[f64]| values.iter().sum::<f64>() / values.len() as f64;
+    let (mean_a, mean_b) = (mean(a), mean(b));
+    let covariance: f64 = a
+        .iter()
+        .zip(b)
+        .map(|(a, b)| (a - mean_a) * (b - mean_b))
+        .sum();
+    let variance = |values: &[f64], mean: f64| -> f64 {
+        values.iter().map(|value| (value - mean).powi(2)).sum()
+    };
+    covariance / (variance(a, mean_a) * variance(b, mean_b)).sqrt()
+}
+
+#[test]
+fn channels_get_their_own_seeds() {
+    assert_eq!(sub_seed(SEED, "height"), sub_seed(SEED, "height"));
+    assert_ne!(sub_seed(SEED, "height"), sub_seed(SEED, "temperature"));
+    assert_ne!(sub_seed(SEED, "height"), sub_seed(SEED + 1, "height"));
+    assert_ne!(sub_seed(SEED, "height"), SEED);
+}
+
+#[test]
+fn channels_are_uncorrelated() {
+    let samples = |channel: &str| -> Vec<f64> {
+        let noise = Simplex::new(sub_seed(SEED, channel));
+        (0..100)
+            .flat_map(|x| (0..100).map(move |y| (x as f64 * 0.1, y as f64 * 0.1)))
+            .map(|(x, y)| noise.get([x, y, 0.0]))
+            .collect()
+    };
+
+    let height = samples("height");
+    let temperature = samples("temperature");
+    assert_eq!(correlation(&height, &samples("height")), 1.0);
+    assert!(correlation(&height, &temperature).abs() < 0.2);
+}
+
+#[test]
+fn chunk_rngs_depend_only_on_the_chunk() {
+    let numbers = |x, y, z| {
+        let mut rng = ChunkRng::for_chunk(SEED, "trees", x, y, z);
+        (0..8).map(|_| rng.next_u64()).collect::<Vec<_>>()
+    };
+
+    assert_eq!(numbers(3, -4, 1), numbers(3, -4, 1));
+    assert_ne!(numbers(3, -4, 1), numbers(-4, 3, 1));
+    assert_ne!(numbers(3, -4, 1), numbers(3, -4, 2));
+
+    let mut rng = ChunkRng::for_chunk(SEED, "ores", 0, 0, 0);
+    for _ in 0..1000 {
+        assert!((0.0..1.0).contains(&rng.next_f64()));
+        assert!((-3..5).contains(&rng.range(-3, 5)));
+    }
+    assert_eq!(rng.range(2, 2), 2);
+}
+
+#[test]
+fn trees_grow_at_about_the_density_of_their_biome() {
+    let tree_log = Block::from_name("tree_log").unwrap().get_id();
+    let tree_leaf = Block::from_name("tree_leaf").unwrap().get_id();
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+
+    // Trunks stand on the ground, four logs high
+    let mut trees = 0;
+    let mut expected = 0.0;
+    for x in 0..6 {
+        for y in 0..6 {
+            // Block ids of every column, from the bottom of the world up
+            let mut columns = vec![Vec::new(); (CHUNK_SIZE * CHUNK_SIZE) as usize];
+            for z in -2..8 {
+                let section = provider.get_chunk(x, y, z);
+                for i in 0..CHUNK_SIZE {
+                    for j in 0..CHUNK_SIZE {
+                        let column = &mut columns[(i * CHUNK_SIZE + j) as usize];
+                        column.extend(
+                            (0..CHUNK_SIZE)
+                                .map(|k| section.get_block(i, j, k).map(|block| block.get_id())),
+                        );
+                    }
+                }
+            }
+
+            for i in 0..CHUNK_SIZE {
+                for j in 0..CHUNK_SIZE {
+                    let biome = provider.biome_at(x * CHUNK_SIZE + i, y * CHUNK_SIZE + j);
+                    expected += biome.unwrap().properties().tree_density;
+                }
+            }
+
+            for column in columns {
+                for k in 1..column.len() - 4 {
+                    let on_ground =
+                        column[k - 1].is_some_and(|id| id != tree_log && id != tree_leaf);
+                    let trunk = column[k..k + 4]
+                        .iter()
+                        .all(|&block| block == Some(tree_log));
+                    if on_ground && trunk {
+                        trees += 1;
+                    }
+                }
+            }
+        }
+    }
+
+    // Give or take trees under water or on ground dug away by caves
+    assert!(expected > 20.0);
+    assert!(
+        (expected * 0.5..expected * 1.5).contains(&(trees as f64)),
+        "{trees} trees, {expected} expected"
+    );
+}
@@ -0,0 +1,204 @@
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{Chunk, ChunkProvider, CHUNK_SIZE};
+use mycraft::world::delta::ChunkDelta;
+use mycraft::world::generator::{GeneratorPipeline, GeneratorPreset};
+use mycraft::world::region::{ChunkRecord, RegionStore, SaveMode, REGION_SIZE};
+
+const SEED: u32 = 7;
+
+fn assert_same_blocks(expected: &Chunk, actual: &Chunk) {
+    assert_eq!(
+        (expected.x, expected.y, expected.z),
+        (actual.x, actual.y, actual.z)
+    );
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                assert_eq!(
+                    expected.get_block(x, y, z).map(|block| block.get_id()),
+                    actual.get_block(x, y, z).map(|block| block.get_id()),
+                    "block ({x}, {y}, {z}) differs"
+                );
+            }
+        }
+    }
+}
+
+#[test]
+fn empty_chunks_round_trip() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = RegionStore::new(directory.path());
+    let chunk = Chunk::new(1, -2, 3);
+
+    store.save_chunk(&chunk).unwrap();
+    let loaded = store.load_chunk(1, -2, 3).unwrap().unwrap();
+
+    assert!(loaded.is_empty());
+    assert_same_blocks(&chunk, &loaded);
+}
+
+#[test]
+fn full_chunks_round_trip() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = RegionStore::new(directory.path());
+    let chunk = Chunk::filled(0, 0, 0, Block::from_name("stone").unwrap());
+
+    store.save_chunk(&chunk).unwrap();
+    let loaded = store.load_chunk(0, 0, 0).unwrap().unwrap();
+
+    assert_same_blocks(&chunk, &loaded);
+}
+
+#[test]
+fn edited_chunks_round_trip() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = RegionStore::new(directory.path());
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let chunk = provider.get_chunk_mut(2, 3, 0);
+
+    let blocks = ["dirt", "sand", "coal", "tree_leaf"].map(|name| Block::from_name(name).unwrap());
+    for i in 0..CHUNK_SIZE {
+        chunk.set_block(i, i, i, blocks[i as usize % blocks.len()]);
+        chunk.remove_block(CHUNK_SIZE - 1 - i, i, 0);
+    }
+    assert!(chunk.is_modified());
+
+    store.save_chunk(chunk).unwrap();
+    let loaded = store.load_chunk(2, 3, 0).unwrap().unwrap();
+
+    assert_same_blocks(chunk, &loaded);
+    assert!(!loaded.is_modified());
+}
+
+#[test]
+fn chunks_share_regions_and_survive_rewrites() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = RegionStore::new(directory.path());
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+
+    let neighbour = Chunk::filled(REGION_SIZE - 1, 0, 0, Block::from_name("dirt").unwrap());
+    let mut chunk = Chunk::new(0, 0, 0);
+    store.save_chunk(&chunk).unwrap();
+    store.save_chunk(&neighbour).unwrap();
+
+    chunk = std::mem::replace(provider.get_chunk_mut(0, 0, 0), Chunk::new(0, 0, 0));
+    store.save_chunk(&chunk).unwrap();
+
+    assert_same_blocks(&chunk, &store.load_chunk(0, 0, 0).unwrap().unwrap());
+    assert_same_blocks(
+        &neighbour,
+        &store.load_chunk(REGION_SIZE - 1, 0, 0).unwrap().unwrap(),
+    );
+    assert!(store.load_chunk(1, 0, 0).unwrap().is_none());
+    assert!(store.load_chunk(-1, 0, 0).unwrap().is_none());
+}
+
+#[test]
+fn provider_loads_chunks_saved_on_unload() {
+    let directory = tempfile::tempdir().unwrap();
+    let stone = Block::from_name("stone").unwrap();
+
+    let mut provider = ChunkProvider::with_region_store(
+        SEED,
+        GeneratorPreset::Default,
+        RegionStore::new(directory.path()),
+    );
+    provider.get_chunk_mut(0, 0, 5).set_block(4, 5, 6, stone);
+    provider.unload_chunk(0, 0, 5);
+
+    let mut provider = ChunkProvider::with_region_store(
+        SEED,
+        GeneratorPreset::Default,
+        RegionStore::new(directory.path()),
+    );
+    let chunk = provider.get_chunk(0, 0, 5);
+    assert_eq!(chunk.get_block(4, 5, 6).unwrap().get_id(), stone.get_id());
+}
+
+#[test]
+fn corrupted_payloads_are_reported() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = RegionStore::new(directory.path());
+    store
+        .save_chunk(&Chunk::filled(0, 0, 0, Block::from_name("stone").unwrap()))
+        .unwrap();
+
+    let path = std::fs::read_dir(directory.path())
+        .unwrap()
+        .next()
+        .unwrap()
+        .unwrap()
+        .path();
+    let mut bytes = std::fs::read(&path).unwrap();
+    let payload_start = bytes.len() - 8;
+    bytes[payload_start..].fill(0xff);
+    std::fs::write(&path, bytes).unwrap();
+
+    assert!(store.load_chunk(0, 0, 0).is_err());
+}
+
+#[test]
+fn deltas_only_store_edited_blocks() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = || RegionStore::with_mode(directory.path(), SaveMode::Deltas);
+    let stone = Block::from_name("stone").unwrap();
+
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    let chunk = provider.get_chunk_mut(1, 1, 5);
+    chunk.set_block(1, 2, 3, stone);
+    chunk.set_block(4, 5, 6, stone);
+    chunk.remove_block(4, 5, 6);
+    provider.unload_chunk(1, 1, 5);
+
+    let Some(ChunkRecord::Delta(delta)) = store().load_record(1, 1, 5).unwrap() else {
+        panic!("expected a delta record");
+    };
+    assert_eq!(delta.changes().len(), 1);
+    assert!(delta.matches(GeneratorPipeline::default().version(), SEED));
+
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    let chunk = provider.get_chunk(1, 1, 5);
+    assert_eq!(chunk.get_block(1, 2, 3).unwrap().get_id(), stone.get_id());
+    assert!(chunk.get_block(4, 5, 6).is_none());
+}
+
+#[test]
+fn deltas_replay_on_generated_terrain() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = || RegionStore::with_mode(directory.path(), SaveMode::Deltas);
+    let sand = Block::from_name("sand").unwrap();
+
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    let chunk = provider.get_chunk_mut(0, 0, 0);
+    for i in 0..CHUNK_SIZE {
+        chunk.set_block(i, 0, i, sand);
+        chunk.remove_block(i, i, 0);
+    }
+    provider.save_all();
+    let edited = provider.get_chunk(0, 0, 0);
+
+    let mut reloaded = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    assert_same_blocks(edited, reloaded.get_chunk(0, 0, 0));
+}
+
+#[test]
+fn deltas_from_another_generator_are_not_applied() {
+    let directory = tempfile::tempdir().unwrap();
+    let store = || RegionStore::with_mode(directory.path(), SaveMode::Deltas);
+    let mut generated = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let expected = generated.get_chunk(0, 0, 0);
+
+    // Fill the chunk with air through both an outdated and a foreign delta
+    let changes: Vec<_> = (0..CHUNK_SIZE.pow(3) as u16)
+        .map(|index| (index, 0))
+        .collect();
+    let version = GeneratorPipeline::default().version();
+    let outdated = ChunkDelta::new(version + 1, SEED, changes.clone());
+    let foreign = ChunkDelta::new(version, SEED + 1, changes);
+    store().save_delta(0, 0, 0, &outdated).unwrap();
+    store().save_delta(0, 0, 1, &foreign).unwrap();
+
+    let mut provider = ChunkProvider::with_region_store(SEED, GeneratorPreset::Default, store());
+    assert_same_blocks(expected, provider.get_chunk(0, 0, 0));
+    assert_same_blocks(generated.get_chunk(0, 0, 1), provider.get_chunk(0, 0, 1));
+}
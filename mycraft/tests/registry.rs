@@ -0,0 +1,91 @@
+use mycraft::world::block::{Block, Face};
+use mycraft::world::registry::BlockRegistry;
+
+#[test]
+fn specific_face_textures_override_sides_and_all() {
+    let registry = BlockRegistry::from_json(
+        r#"{ "blocks": [
+            { "name": "log", "id": 1, "textures": { "all": "bark.png", "top": "rings.png" } },
+            { "name": "chest", "id": 2, "textures": { "sides": "side.png", "top": "lid.png", "bottom": "bark.png", "front": "lock.png" } }
+        ] }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        registry.textures(),
+        ["bark.png", "rings.png", "lock.png", "side.png", "lid.png"]
+    );
+
+    let log = registry.get_by_name("log").unwrap();
+    assert_eq!(log.get_material_id(Face::Top), 1);
+    assert_eq!(log.get_material_id(Face::Bottom), 0);
+    assert_eq!(log.get_material_id(Face::Left), 0);
+
+    let chest = registry.get(2).unwrap();
+    assert_eq!(chest.get_material_id(Face::Front), 2);
+    assert_eq!(chest.get_material_id(Face::Back), 3);
+    assert_eq!(chest.get_material_id(Face::Top), 4);
+    assert_eq!(chest.get_material_id(Face::Bottom), 0);
+    assert!(chest.opaque);
+}
+
+#[test]
+fn invalid_definitions_are_rejected() {
+    let duplicate_id = r#"{ "blocks": [
+        { "name": "a", "id": 1, "textures": { "all": "a.png" } },
+        { "name": "b", "id": 1, "textures": { "all": "b.png" } }
+    ] }"#;
+    let air_id = r#"{ "blocks": [ { "name": "a", "id": 0, "textures": { "all": "a.png" } } ] }"#;
+    let missing_texture =
+        r#"{ "blocks": [ { "name": "a", "id": 1, "textures": { "top": "a.png" } } ] }"#;
+
+    assert!(BlockRegistry::from_json(duplicate_id).is_err());
+    assert!(BlockRegistry::from_json(air_id).is_err());
+    assert!(BlockRegistry::from_json(missing_texture).is_err());
+}
+
+#[test]
+fn bundled_definitions_cover_the_generator_blocks() {
+    for name in [
+        "dirt",
+        "grass",
+        "sand",
+        "snow",
+        "stone",
+        "coal",
+        "tree_log",
+        "tree_leaf",
+        "water",
+        "iron_ore",
+        "gold_ore",
+        "diamond_ore",
+        "redstone_ore",
+        "copper_ore",
+    ] {
+        let block = Block::from_name(name).unwrap();
+        assert_eq!(Block::from_id(block.get_id()).unwrap().get_name(), name);
+    }
+
+    assert!(Block::from_name("tree_leaf").unwrap().is_transparent());
+    assert!(Block::from_name("water").unwrap().is_transparent());
+    assert!(Block::from_name("stone").unwrap().is_opaque());
+}
+
+#[test]
+fn fluid_definitions_are_checked() {
+    let unknown_reaction = r#"{ "blocks": [
+        { "name": "lava", "id": 1, "textures": { "all": "a.png" },
+          "fluid": { "flow_distance": 3, "tick_rate": 30, "reactions": { "water": "stone" } } }
+    ] }"#;
+    let frozen = r#"{ "blocks": [
+        { "name": "water", "id": 1, "textures": { "all": "a.png" },
+          "fluid": { "flow_distance": 7, "tick_rate": 0 } }
+    ] }"#;
+    assert!(BlockRegistry::from_json(unknown_reaction).is_err());
+    assert!(BlockRegistry::from_json(frozen).is_err());
+
+    let lava = Block::from_name("lava").unwrap().get_fluid().unwrap();
+    assert_eq!(lava.reactions["water"], "stone");
+    assert!(Block::from_name("water").unwrap().is_fluid());
+    assert!(!Block::from_name("stone").unwrap().is_fluid());
+}
@@ -0,0 +1,96 @@
+use mycraft::world::block::{Block, Face};
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE};
+use mycraft::world::generator::GeneratorPreset;
+use mycraft::world::visibility::{visible_sections, FaceConnections};
+use mycraft::world::World;
+
+fn void_section() -> World {
+    let mut world = World::new(ChunkProvider::new(0, GeneratorPreset::Void));
+    world.load_chunk(0, 0, 0);
+    world
+}
+
+fn fill(world: &mut World, block: Block, keep_open: impl Fn(i32, i32, i32) -> bool) {
+    for x in 0..CHUNK_SIZE {
+        for y in 0..CHUNK_SIZE {
+            for z in 0..CHUNK_SIZE {
+                if !keep_open(x, y, z) {
+                    world.set_block(x, y, z, block);
+                }
+            }
+        }
+    }
+}
+
+fn connections(world: &World) -> FaceConnections {
+    world.get_chunk(0, 0, 0).unwrap().face_connections()
+}
+
+#[test]
+fn empty_sections_connect_every_face_and_solid_ones_none() {
+    let mut world = void_section();
+    assert_eq!(connections(&world), FaceConnections::ALL);
+
+    fill(&mut world, Block::from_name("stone").unwrap(), |_, _, _| {
+        false
+    });
+    assert_eq!(connections(&world), FaceConnections::NONE);
+}
+
+#[test]
+fn a_wall_splits_the_faces_on_either_side() {
+    let mut world = void_section();
+    let stone = Block::from_name("stone").unwrap();
+    fill(&mut world, stone, |x, _, _| x != 8);
+
+    let split = connections(&world);
+    assert!(!split.connects(Face::Left, Face::Right));
+    assert!(split.connects(Face::Left, Face::Top));
+    assert!(split.connects(Face::Right, Face::Front));
+    assert!(split.connects(Face::Top, Face::Bottom));
+
+    // A see-through block in the wall lets the two sides see each other
+    world.set_block(8, 4, 4, Block::from_name("water").unwrap());
+    assert!(connections(&world).connects(Face::Left, Face::Right));
+}
+
+#[test]
+fn a_tunnel_connects_only_the_faces_it_opens_onto() {
+    let mut world = void_section();
+    let stone = Block::from_name("stone").unwrap();
+    // Along x from the left face to the middle, then up to the top
+    fill(&mut world, stone, |x, y, z| {
+        (y == 5 && z == 5 && x <= 8) || (x == 8 && y == 5 && z >= 5)
+    });
+
+    let connections = connections(&world);
+    assert!(connections.connects(Face::Left, Face::Top));
+    assert!(!connections.connects(Face::Left, Face::Right));
+    assert!(!connections.connects(Face::Top, Face::Bottom));
+    assert!(!connections.connects(Face::Front, Face::Back));
+}
+
+#[test]
+fn sealed_layers_hide_the_sections_beyond_them() {
+    let in_range = |(x, y, z): (i32, i32, i32)| x.abs() <= 2 && y.abs() <= 2 && z.abs() <= 2;
+    // Open air over a layer of solid rock, with caves below it
+    let connections = |(_, _, z): (i32, i32, i32)| {
+        if z == -1 {
+            FaceConnections::NONE
+        } else {
+            FaceConnections::ALL
+        }
+    };
+
+    let visible = visible_sections((0, 0, 1), in_range, connections);
+    assert!(visible.contains(&(2, 2, 2)));
+    // The rock itself shows its top, but nothing under it can be seen
+    assert!(visible.contains(&(0, 0, -1)));
+    assert!(!visible.contains(&(0, 0, -2)));
+    assert!(!visible.contains(&(2, -2, -2)));
+
+    // From inside the caves, the surface is hidden instead
+    let visible = visible_sections((0, 0, -2), in_range, connections);
+    assert!(visible.contains(&(2, 2, -2)));
+    assert!(!visible.contains(&(0, 0, 0)));
+}
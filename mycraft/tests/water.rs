@@ -0,0 +1,119 @@
+use mycraft::world::biome::Biome;
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE};
+use mycraft::world::generator::{GeneratorPipeline, GeneratorPreset, TerrainStage};
+use mycraft::world::World;
+
+const SEED: u32 = 7;
+
+// Caves dig some land columns below sea level, so they are left out
+fn load_world(xs: std::ops::Range<i32>, ys: std::ops::Range<i32>) -> World {
+    let mut provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let mut pipeline = GeneratorPipeline::default();
+    pipeline.remove("caves");
+    provider.set_pipeline(pipeline);
+    let mut world = World::new(provider);
+    for x in xs {
+        for y in ys.clone() {
+            for z in -2..5 {
+                world.load_chunk(x, y, z);
+            }
+        }
+    }
+    world
+}
+
+fn is_water(world: &World, x: i32, y: i32, z: i32) -> bool {
+    let water = Block::from_name("water").unwrap().get_id();
+    world
+        .get_block(x, y, z)
+        .is_some_and(|block| block.get_id() == water)
+}
+
+// Top block of a column that isn't water or air
+fn ground(world: &World, x: i32, y: i32) -> i32 {
+    (-2 * CHUNK_SIZE..5 * CHUNK_SIZE)
+        .rev()
+        .find(|&z| world.get_block(x, y, z).is_some() && !is_water(world, x, y, z))
+        .unwrap()
+}
+
+#[test]
+fn the_sea_fills_low_terrain_up_to_sea_level() {
+    let sea_level = TerrainStage::default().sea_level;
+    let world = load_world(0..4, 0..4);
+
+    let mut sea_columns = 0;
+    for x in 0..4 * CHUNK_SIZE {
+        for y in 0..4 * CHUNK_SIZE {
+            assert!(!is_water(&world, x, y, sea_level + 1));
+            if ground(&world, x, y) < sea_level {
+                sea_columns += 1;
+                assert!(is_water(&world, x, y, sea_level));
+            }
+        }
+    }
+    assert!(sea_columns > 100, "{sea_columns} columns under water");
+}
+
+#[test]
+fn beaches_line_the_sea() {
+    let sea_level = TerrainStage::default().sea_level;
+    let world = load_world(0..4, 0..4);
+    let provider = ChunkProvider::new(SEED, GeneratorPreset::Default);
+    let sand = Block::from_name("sand").unwrap().get_id();
+
+    let mut beaches = 0;
+    for x in 1..4 * CHUNK_SIZE - 1 {
+        for y in 1..4 * CHUNK_SIZE - 1 {
+            let ground = ground(&world, x, y);
+            let by_the_sea = [(x + 1, y), (x - 1, y), (x, y + 1), (x, y - 1)]
+                .iter()
+                .any(|&(x, y)| is_water(&world, x, y, sea_level));
+            if !by_the_sea || !(sea_level - 2..=sea_level + 2).contains(&ground) {
+                continue;
+            }
+
+            let biome = provider.biome_at(x, y).unwrap();
+            assert!(biome == Biome::Beach || !biome.has_beaches(), "{biome:?}");
+            if biome == Biome::Beach {
+                beaches += 1;
+                let top = world.get_block(x, y, ground).unwrap();
+                assert!(top.get_id() == sand || top.get_name() == "stone");
+            }
+        }
+    }
+    assert!(beaches > 10, "{beaches} beach columns");
+}
+
+#[test]
+fn lakes_are_held_in_by_the_terrain() {
+    let sea_level = TerrainStage::default().sea_level;
+    let world = load_world(-1..3, -11..-7);
+
+    let mut lake_blocks = 0;
+    for x in -CHUNK_SIZE..3 * CHUNK_SIZE {
+        for y in -11 * CHUNK_SIZE..-7 * CHUNK_SIZE {
+            for z in sea_level + 1..5 * CHUNK_SIZE {
+                if !is_water(&world, x, y, z) {
+                    continue;
+                }
+
+                lake_blocks += 1;
+                for (x, y, z) in [
+                    (x + 1, y, z),
+                    (x - 1, y, z),
+                    (x, y + 1, z),
+                    (x, y - 1, z),
+                    (x, y, z - 1),
+                ] {
+                    assert!(
+                        world.get_block(x, y, z).is_some(),
+                        "water leaks out at ({x}, {y}, {z})"
+                    );
+                }
+            }
+        }
+    }
+    assert!(lake_blocks > 50, "{lake_blocks} lake blocks");
+}
@@ -0,0 +1,146 @@
+use mycraft::world::block::Block;
+use mycraft::world::chunk::{ChunkProvider, CHUNK_SIZE};
+use mycraft::world::generator::GeneratorPreset;
+use mycraft::world::World;
+
+const SEED: u32 = 7;
+
+// Right face in the block renderer's face order
+const RIGHT_FACE: usize = 4;
+
+fn load_world(columns: i32, layers: i32) -> World {
+    let mut world = World::new(ChunkProvider::new(SEED, GeneratorPreset::Default));
+    for x in 0..columns {
+        for y in 0..columns {
+            for z in 0..layers {
+                world.load_chunk(x, y, z);
+            }
+        }
+    }
+    world
+}
+
+#[test]
+fn get_block_follows_coordinates_into_adjacent_chunks() {
+    let world = load_world(2, 2);
+
+    for (chunk_x, chunk_y, chunk_z) in [(0, 0, 0), (1, 0, 0), (0, 1, 1), (1, 1, 1)] {
+        let chunk = world.get_chunk(chunk_x, chunk_y, chunk_z).unwrap();
+        for ((x, y, z), block) in chunk.blocks() {
+            let world_block = world
+                .get_block(
+                    chunk_x * CHUNK_SIZE + x,
+                    chunk_y * CHUNK_SIZE + y,
+                    chunk_z * CHUNK_SIZE + z,
+                )
+                .unwrap();
+            assert_eq!(world_block.get_id(), block.get_id());
+        }
+    }
+}
+
+#[test]
+fn unloaded_chunks_are_treated_as_air() {
+    let world = load_world(1, 1);
+
+    assert!(world.get_block(-1, 0, 0).is_none());
+    assert!(!world.is_opaque(CHUNK_SIZE, 0, 0));
+}
+
+#[test]
+fn chunk_seams_produce_no_interior_faces() {
+    let world = load_world(2, 3);
+    let mut seam_blocks = 0;
+
+    for z_chunk in 0..3 {
+        let chunk = world.get_chunk(0, 0, z_chunk).unwrap();
+        let neighbour = world.get_chunk(1, 0, z_chunk).unwrap();
+
+        for ((x, y, z), _) in chunk.blocks().filter(|((x, _, _), _)| *x == CHUNK_SIZE - 1) {
+            let hidden = neighbour
+                .get_block(0, y, z)
+                .is_some_and(|block| block.is_opaque());
+            if !hidden {
+                continue;
+            }
+
+            seam_blocks += 1;
+            assert!(
+                !chunk.is_opaque(x + 1, y, z),
+                "the chunk alone cannot see its neighbour"
+            );
+            assert!(
+                !world.visible_faces(chunk, x, y, z)[RIGHT_FACE],
+                "interior face emitted on the chunk seam at ({x}, {y}, {z})"
+            );
+        }
+    }
+
+    assert!(seam_blocks > 0, "no solid blocks found along the seam");
+}
+
+#[test]
+fn edits_replace_blocks_and_return_the_previous_one() {
+    let mut world = load_world(1, 1);
+    let stone = Block::from_name("stone").unwrap();
+
+    world.remove_block(3, 3, 3);
+    assert!(world.set_block(3, 3, 3, stone).is_none());
+    assert_eq!(world.get_block(3, 3, 3).unwrap().get_id(), stone.get_id());
+
+    let removed = world.remove_block(3, 3, 3).unwrap();
+    assert_eq!(removed.get_id(), stone.get_id());
+    assert!(world.get_block(3, 3, 3).is_none());
+}
+
+#[test]
+fn edits_mark_their_chunk_and_seam_neighbours_dirty() {
+    let mut world = load_world(1, 1);
+    let stone = Block::from_name("stone").unwrap();
+    world.take_dirty_chunks();
+
+    world.remove_block(5, 5, 5);
+    world.set_block(5, 5, 5, stone);
+    assert_eq!(world.take_dirty_chunks(), vec![(0, 0, 0)]);
+
+    world.remove_block(0, 5, CHUNK_SIZE - 1);
+    world.set_block(0, 5, CHUNK_SIZE - 1, stone);
+    let mut dirty = world.take_dirty_chunks();
+    dirty.sort();
+    assert_eq!(dirty, vec![(-1, 0, 0), (0, 0, 0), (0, 0, 1)]);
+}
+
+#[test]
+fn setting_the_same_block_leaves_chunks_clean() {
+    let mut world = load_world(1, 1);
+    let stone = Block::from_name("stone").unwrap();
+    world.set_block(5, 5, 5, stone);
+    world.take_dirty_chunks();
+
+    world.set_block(5, 5, 5, stone);
+    assert!(world.take_dirty_chunks().is_empty());
+}
+
+#[test]
+fn water_culls_faces_against_water_but_not_against_solids() {
+    let mut world = World::new(ChunkProvider::new(SEED, GeneratorPreset::Void));
+    let water = Block::from_name("water").unwrap();
+    let stone = Block::from_name("stone").unwrap();
+    world.load_chunk(0, 0, 0);
+    world.set_block(4, 4, 4, water);
+    world.set_block(5, 4, 4, water);
+    world.set_block(4, 4, 5, stone);
+
+    let chunk = world.get_chunk(0, 0, 0).unwrap();
+    // Front, back, top, bottom, right and left
+    assert_eq!(
+        world.visible_faces(chunk, 4, 4, 4),
+        [true, true, false, true, false, true]
+    );
+    assert_eq!(
+        world.visible_faces(chunk, 5, 4, 4),
+        [true, true, true, true, true, false]
+    );
+    // The stone stays visible through the water below it
+    assert!(world.visible_faces(chunk, 4, 4, 5)[3]);
+}